@@ -3,6 +3,8 @@ use std::fs;
 use std::path::PathBuf;
 use std::time::Duration;
 
+use super::error::CommandError;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AiProvider {
@@ -88,7 +90,7 @@ pub struct AiSettingsPublic {
 }
 
 #[tauri::command]
-pub async fn get_ai_settings() -> Result<Option<AiSettingsPublic>, String> {
+pub async fn get_ai_settings() -> Result<Option<AiSettingsPublic>, CommandError> {
     let settings = read_ai_settings()?;
     Ok(settings.map(|s| AiSettingsPublic {
         provider: s.provider,
@@ -98,12 +100,12 @@ pub async fn get_ai_settings() -> Result<Option<AiSettingsPublic>, String> {
 }
 
 #[tauri::command]
-pub async fn save_ai_settings(settings: AiSettings) -> Result<(), String> {
+pub async fn save_ai_settings(settings: AiSettings) -> Result<(), CommandError> {
     if settings.model.trim().is_empty() {
-        return Err("Model name is required".to_string());
+        return Err("Model name is required".into());
     }
     if settings.model.len() > 100 {
-        return Err("Model name is too long".to_string());
+        return Err("Model name is too long".into());
     }
 
     let path = get_ai_settings_path()?;
@@ -111,7 +113,7 @@ pub async fn save_ai_settings(settings: AiSettings) -> Result<(), String> {
     let content = serde_json::to_string_pretty(&settings)
         .map_err(|e| format!("Failed to serialize AI settings: {}", e))?;
 
-    fs::write(&path, content).map_err(|e| format!("Failed to write AI settings: {}", e))
+    fs::write(&path, content).map_err(|e| format!("Failed to write AI settings: {}", e).into())
 }
 
 fn get_api_url(provider: &AiProvider) -> String {
@@ -132,12 +134,12 @@ fn parse_api_error(status: reqwest::StatusCode, body: &str) -> String {
 }
 
 #[tauri::command]
-pub async fn send_ai_message(messages: Vec<ChatMessage>) -> Result<String, String> {
+pub async fn send_ai_message(messages: Vec<ChatMessage>) -> Result<String, CommandError> {
     let settings = read_ai_settings()?
         .ok_or("AI settings not configured. Open settings to add your API key.")?;
 
     if settings.api_key.is_empty() {
-        return Err("API key is not configured".to_string());
+        return Err("API key is not configured".into());
     }
 
     let url = get_api_url(&settings.provider);
@@ -164,7 +166,7 @@ pub async fn send_ai_message(messages: Vec<ChatMessage>) -> Result<String, Strin
     if !response.status().is_success() {
         let status = response.status();
         let body = response.text().await.unwrap_or_default();
-        return Err(parse_api_error(status, &body));
+        return Err(parse_api_error(status, &body).into());
     }
 
     let completion: ChatCompletionResponse = response
@@ -1,23 +1,143 @@
-use sqlx::postgres::PgPool;
+use sqlx::postgres::{PgConnectOptions, PgPool};
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
 use std::sync::Arc;
 use tauri::State;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
 use urlencoding::encode;
 
+use super::error::CommandError;
+use super::jobs::QueryJob;
+use super::notifications::{NotificationListener, NotificationLog};
+use super::query::{close_results, OpenResult};
+use super::relay::NoticeRelay;
+use super::safety::SafetyLevel;
+use super::session::{open_transactions, TabSession};
+use super::timeouts::Timeouts;
+
+/// The database the app is currently connected to.
+#[derive(Clone)]
+pub struct ActiveConnection {
+    pub pool: PgPool,
+    pub config: ConnectionConfig,
+    /// Carries the pool's connections; it stops taking new ones once the last
+    /// handle is dropped.
+    pub relay: Arc<NoticeRelay>,
+}
+
+/// Shared state for all commands.
+///
+/// The active connection lock is only ever held long enough to clone or swap the
+/// pool handle, never across a query, so commands from different tabs run
+/// concurrently on their own connections.
 pub struct AppState {
-    pub pool: Arc<Mutex<Option<PgPool>>>,
-    pub current_connection: Arc<Mutex<Option<ConnectionConfig>>>,
+    active: Arc<RwLock<Option<ActiveConnection>>>,
+    pub results: Arc<Mutex<HashMap<String, OpenResult>>>,
+    pub jobs: Arc<Mutex<HashMap<String, QueryJob>>>,
+    /// Connections pinned to editor tabs, keyed by tab id.
+    pub sessions: Arc<Mutex<HashMap<String, TabSession>>>,
+    /// SQL waiting for the user to confirm it is meant to be destructive, by
+    /// confirmation token.
+    pub confirmations: Arc<Mutex<HashMap<String, String>>>,
+    /// The connection `LISTEN` runs on, opened on first use.
+    pub listener: Arc<Mutex<Option<NotificationListener>>>,
+    /// Notifications received on the current connection.
+    pub notifications: NotificationLog,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
-            pool: Arc::new(Mutex::new(None)),
-            current_connection: Arc::new(Mutex::new(None)),
+            active: Arc::new(RwLock::new(None)),
+            results: Arc::new(Mutex::new(HashMap::new())),
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            confirmations: Arc::new(Mutex::new(HashMap::new())),
+            listener: Arc::new(Mutex::new(None)),
+            notifications: Arc::new(Mutex::new(VecDeque::new())),
         }
     }
 }
 
+impl AppState {
+    /// Returns a handle to the connected pool.
+    pub async fn pool(&self) -> Result<PgPool, CommandError> {
+        self.active
+            .read()
+            .await
+            .as_ref()
+            .map(|active| active.pool.clone())
+            .ok_or(CommandError::NotConnected)
+    }
+
+    pub async fn active_connection(&self) -> Option<ActiveConnection> {
+        self.active.read().await.clone()
+    }
+
+    /// Id of the saved connection in use, if any.
+    pub async fn connection_id(&self) -> Option<String> {
+        self.active
+            .read()
+            .await
+            .as_ref()
+            .map(|active| active.config.id.clone())
+    }
+
+    /// The active connection's default time limits.
+    pub async fn timeouts(&self) -> Timeouts {
+        self.active
+            .read()
+            .await
+            .as_ref()
+            .map(|active| active.config.timeouts)
+            .unwrap_or_default()
+    }
+
+    /// The active connection's safety level.
+    pub async fn safety(&self) -> SafetyLevel {
+        self.active
+            .read()
+            .await
+            .as_ref()
+            .map(|active| active.config.safety)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State connected, through a relay like the app's, to the database at
+    /// `DBEE_TEST_DATABASE_URL`, for tests that need a server. The URL needs a
+    /// TCP address. `None` when the variable is not set.
+    pub(crate) async fn connected_for_tests() -> Option<Self> {
+        let url = std::env::var("DBEE_TEST_DATABASE_URL").ok()?;
+        let options = PgConnectOptions::from_str(&url).expect("test database URL");
+        let relay = NoticeRelay::start(&options).await.expect("test database");
+        let pool = PgPool::connect_with(relay.route(options))
+            .await
+            .expect("test database");
+        let config = ConnectionConfig {
+            id: "test".to_string(),
+            name: "test".to_string(),
+            host: String::new(),
+            port: 0,
+            user: String::new(),
+            password: String::new(),
+            database: String::new(),
+            timeouts: Timeouts::default(),
+            safety: SafetyLevel::default(),
+        };
+
+        let state = Self::default();
+        *state.active.write().await = Some(ActiveConnection {
+            pool,
+            config,
+            relay: Arc::new(relay),
+        });
+        Some(state)
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ConnectionConfig {
     pub id: String,
@@ -27,6 +147,12 @@ pub struct ConnectionConfig {
     pub user: String,
     pub password: String,
     pub database: String,
+    /// Limits every statement on this connection runs under, unless an
+    /// execution overrides them.
+    #[serde(default)]
+    pub timeouts: Timeouts,
+    #[serde(default)]
+    pub safety: SafetyLevel,
 }
 
 impl ConnectionConfig {
@@ -56,6 +182,7 @@ pub struct ConnectionInfo {
     pub port: u16,
     pub database: String,
     pub is_connected: bool,
+    pub safety: SafetyLevel,
 }
 
 impl From<&ConnectionConfig> for ConnectionInfo {
@@ -67,6 +194,7 @@ impl From<&ConnectionConfig> for ConnectionInfo {
             port: config.port,
             database: config.database.clone(),
             is_connected: true,
+            safety: config.safety,
         }
     }
 }
@@ -82,59 +210,111 @@ pub fn build_connection_string(config: &ConnectionConfig) -> String {
     )
 }
 
+/// Options for connecting to `config`, its time limits and safety level
+/// included.
+pub fn connect_options(config: &ConnectionConfig) -> Result<PgConnectOptions, CommandError> {
+    let options = PgConnectOptions::from_str(&build_connection_string(config))
+        .map_err(|e| CommandError::sqlx("Invalid connection settings", e))?;
+    Ok(config.safety.apply_to(config.timeouts.apply_to(options)))
+}
+
+/// Refuses to drop the current connection while tabs hold uncommitted work,
+/// unless the user has confirmed it should be discarded.
+async fn ensure_no_open_transactions(state: &AppState, force: bool) -> Result<(), CommandError> {
+    let tab_ids = open_transactions(state).await;
+    if force || tab_ids.is_empty() {
+        return Ok(());
+    }
+    Err(CommandError::OpenTransactions(tab_ids))
+}
+
+/// Connects to `config`, replacing the current connection. Pass `force` to
+/// discard transactions still open on the current one.
 #[tauri::command]
 pub async fn connect(
     config: ConnectionConfig,
+    force: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<ConnectionInfo, String> {
+) -> Result<ConnectionInfo, CommandError> {
     config.validate()?;
+    ensure_no_open_transactions(&state, force.unwrap_or(false)).await?;
 
-    let connection_string = build_connection_string(&config);
-
-    let pool = PgPool::connect(&connection_string)
+    let options = connect_options(&config)?;
+    let relay = NoticeRelay::start(&options).await?;
+    let pool = PgPool::connect_with(relay.route(options))
         .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
+        .map_err(|e| CommandError::sqlx("Failed to connect", e))?;
 
     let info = ConnectionInfo::from(&config);
 
-    {
-        let mut guard = state.pool.lock().await;
-        *guard = Some(pool);
-    }
+    let previous = state.active.write().await.replace(ActiveConnection {
+        pool,
+        config,
+        relay: Arc::new(relay),
+    });
 
-    {
-        let mut config_guard = state.current_connection.lock().await;
-        *config_guard = Some(config);
+    if let Some(previous) = previous {
+        release_connection(&state, previous).await;
     }
 
     Ok(info)
 }
 
+/// Closes the current connection. Pass `force` to discard transactions that
+/// are still open.
 #[tauri::command]
-pub async fn disconnect(state: State<'_, AppState>) -> Result<(), String> {
-    let mut guard = state.pool.lock().await;
-    if let Some(pool) = guard.take() {
-        pool.close().await;
-    }
+pub async fn disconnect(
+    force: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    ensure_no_open_transactions(&state, force.unwrap_or(false)).await?;
+
+    let previous = state.active.write().await.take();
 
-    let mut config_guard = state.current_connection.lock().await;
-    *config_guard = None;
+    if let Some(previous) = previous {
+        release_connection(&state, previous).await;
+    }
 
     Ok(())
 }
 
-#[tauri::command]
-pub async fn get_connection_info(
-    state: State<'_, AppState>,
-) -> Result<Option<ConnectionInfo>, String> {
-    let pool_guard = state.pool.lock().await;
-    let config_guard = state.current_connection.lock().await;
+/// Shuts down a pool that is no longer the active connection.
+///
+/// Open result sets and running statements hold pooled connections, and closing
+/// the pool waits for those to come back, so they are dropped and cancelled first.
+/// Tab sessions are dropped too, which rolls back their open transactions, and
+/// so is the listening connection along with the notifications it received.
+async fn release_connection(state: &AppState, previous: ActiveConnection) {
+    close_results(state, |_, _| true).await;
+    state.sessions.lock().await.clear();
+    state.confirmations.lock().await.clear();
+    state.listener.lock().await.take();
+    state.notifications.lock().await.clear();
 
-    if pool_guard.is_some() {
-        if let Some(config) = config_guard.as_ref() {
-            return Ok(Some(ConnectionInfo::from(config)));
-        }
+    let running: Vec<_> = state
+        .jobs
+        .lock()
+        .await
+        .values_mut()
+        .map(|job| job.request_cancel())
+        .collect();
+
+    for handle in running {
+        let _ = handle.send().await;
     }
 
-    Ok(None)
+    previous.pool.close().await;
+    // Other handles may outlive this one, but the closed pool opens nothing
+    // more through the relay.
+    drop(previous.relay);
+}
+
+#[tauri::command]
+pub async fn get_connection_info(
+    state: State<'_, AppState>,
+) -> Result<Option<ConnectionInfo>, CommandError> {
+    Ok(state
+        .active_connection()
+        .await
+        .map(|active| ConnectionInfo::from(&active.config)))
 }
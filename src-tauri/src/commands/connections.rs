@@ -4,7 +4,10 @@ use std::fs;
 use std::path::PathBuf;
 use uuid::Uuid;
 
-use super::connection::{build_connection_string, ConnectionConfig};
+use super::connection::{connect_options, ConnectionConfig};
+use super::error::CommandError;
+use super::safety::SafetyLevel;
+use super::timeouts::Timeouts;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SavedConnection {
@@ -15,6 +18,10 @@ pub struct SavedConnection {
     pub user: String,
     pub password: String,
     pub database: String,
+    #[serde(default)]
+    pub timeouts: Timeouts,
+    #[serde(default)]
+    pub safety: SafetyLevel,
 }
 
 impl From<SavedConnection> for ConnectionConfig {
@@ -27,6 +34,8 @@ impl From<SavedConnection> for ConnectionConfig {
             user: saved.user,
             password: saved.password,
             database: saved.database,
+            timeouts: saved.timeouts,
+            safety: saved.safety,
         }
     }
 }
@@ -41,6 +50,8 @@ impl From<ConnectionConfig> for SavedConnection {
             user: config.user,
             password: config.password,
             database: config.database,
+            timeouts: config.timeouts,
+            safety: config.safety,
         }
     }
 }
@@ -85,13 +96,15 @@ fn write_connections_file(file: &ConnectionsFile) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn list_saved_connections() -> Result<Vec<SavedConnection>, String> {
+pub async fn list_saved_connections() -> Result<Vec<SavedConnection>, CommandError> {
     let file = read_connections_file()?;
     Ok(file.connections)
 }
 
 #[tauri::command]
-pub async fn save_connection(mut connection: SavedConnection) -> Result<SavedConnection, String> {
+pub async fn save_connection(
+    mut connection: SavedConnection,
+) -> Result<SavedConnection, CommandError> {
     let mut file = read_connections_file()?;
 
     if connection.id.is_empty() {
@@ -114,7 +127,7 @@ pub async fn save_connection(mut connection: SavedConnection) -> Result<SavedCon
 }
 
 #[tauri::command]
-pub async fn delete_connection(id: String) -> Result<(), String> {
+pub async fn delete_connection(id: String) -> Result<(), CommandError> {
     let mut file = read_connections_file()?;
     file.connections.retain(|c| c.id != id);
     write_connections_file(&file)?;
@@ -122,17 +135,15 @@ pub async fn delete_connection(id: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn test_connection(connection: SavedConnection) -> Result<bool, String> {
+pub async fn test_connection(connection: SavedConnection) -> Result<bool, CommandError> {
     let config: ConnectionConfig = connection.into();
     config.validate()?;
 
-    let connection_string = build_connection_string(&config);
-
-    match PgPool::connect(&connection_string).await {
+    match PgPool::connect_with(connect_options(&config)?).await {
         Ok(pool) => {
             pool.close().await;
             Ok(true)
         }
-        Err(e) => Err(format!("Connection failed: {}", e)),
+        Err(e) => Err(CommandError::sqlx("Connection failed", e)),
     }
 }
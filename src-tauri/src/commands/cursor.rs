@@ -0,0 +1,223 @@
+use futures_util::TryStreamExt;
+use sqlx::postgres::PgPool;
+use sqlx::{Either, Executor, Statement};
+use tokio::sync::{mpsc, oneshot};
+
+use super::decode::{DisplayZone, RowDecoder, ValueStyle};
+use super::error::CommandError;
+use super::jobs::JobConnection;
+use super::notices::{NoticeSink, ServerNotice};
+use super::query::{prepare_statement, ResultColumn};
+use super::session::TransactionState;
+use super::sql::reads_only;
+use super::timeouts::SavedSettings;
+
+/// Set before a statement that may be cancelled inside a transaction, so the
+/// cancellation does not abort the transaction.
+const STOP_SAVEPOINT: &str = "dbee_cursor";
+
+/// Rows returned by a single [`RowCursor::fetch`] call.
+pub struct CursorPage {
+    /// Only filled in on the first page.
+    pub columns: Vec<ResultColumn>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+    pub exhausted: bool,
+    /// Rows inserted, updated, deleted or returned, once the statement completes.
+    pub rows_affected: Option<u64>,
+    /// Notices raised since the previous page.
+    pub notices: Vec<ServerNotice>,
+}
+
+struct FetchRequest {
+    limit: usize,
+    reply: oneshot::Sender<Result<CursorPage, CommandError>>,
+}
+
+/// A running query whose rows are pulled from the server a page at a time.
+///
+/// The statement runs on a connection handed to a background task, so the row
+/// stream stays open between pages. `binds` are text values for its `$n`
+/// placeholders, and `saved` holds settings overridden for this statement,
+/// put back once it is done. Dropping the cursor ends the task; if the
+/// stream was not exhausted the connection is abandoned as described on
+/// [`JobConnection::abandon`]. `pool` lends a second connection for rendering
+/// values only the server knows how to print, and `style` says what the
+/// values are decoded for.
+///
+/// A tab's session cannot be closed to stop its statement, and reading every
+/// row left would undo the row limit, so a statement that only reads is
+/// cancelled on the server instead. Inside a transaction the cancellation is
+/// rolled back to a savepoint set before the statement; anything that writes
+/// or locks rows is still read to the end.
+pub struct RowCursor {
+    requests: mpsc::Sender<FetchRequest>,
+}
+
+impl RowCursor {
+    pub fn open(
+        mut conn: JobConnection,
+        pool: PgPool,
+        sql: String,
+        binds: Vec<Option<String>>,
+        display_zone: DisplayZone,
+        style: ValueStyle,
+        saved: Option<SavedSettings>,
+    ) -> Self {
+        let (requests, mut receiver) = mpsc::channel::<FetchRequest>(1);
+
+        tokio::spawn(async move {
+            let notices = NoticeSink::new(conn.backend_pid());
+            let prepared = prepare_statement(&mut conn, &sql).await;
+            let (statement, mut columns) = match prepared {
+                Ok(prepared) => prepared,
+                Err(e) => {
+                    conn.statement_finished(&sql, true).await;
+                    if let Some(saved) = saved {
+                        saved.restore(&mut conn).await;
+                    }
+                    if let Some(request) = receiver.recv().await {
+                        let error = CommandError::sqlx("Query error", e);
+                        let _ = request.reply.send(Err(error.with_notices(notices.take())));
+                    }
+                    return;
+                }
+            };
+
+            let decoder =
+                RowDecoder::new(&mut conn, statement.columns(), display_zone, style).await;
+
+            let stoppable = reads_only(&sql)
+                && matches!(
+                    conn.transaction_state(),
+                    TransactionState::Idle | TransactionState::InTransaction
+                );
+            let savepoint = stoppable
+                && conn.transaction_state() == TransactionState::InTransaction
+                && (&mut *conn)
+                    .execute(format!("SAVEPOINT {STOP_SAVEPOINT}").as_str())
+                    .await
+                    .is_ok();
+            let cancel = conn.session_cancel(&pool).filter(|_| stoppable);
+
+            let mut exhausted = false;
+            {
+                let query = binds
+                    .into_iter()
+                    .fold(statement.query(), |query, value| query.bind(value));
+                let mut stream = (&mut *conn).fetch_many(query);
+                let mut rows_affected = 0;
+                let mut last = None;
+
+                while let Some(request) = receiver.recv().await {
+                    let mut rows = Vec::new();
+                    let mut undecoded = Vec::new();
+                    let mut error = None;
+
+                    while rows.len() < request.limit {
+                        match stream.try_next().await {
+                            Ok(Some(Either::Left(done))) => {
+                                rows_affected += done.rows_affected();
+                            }
+                            Ok(Some(Either::Right(row))) => {
+                                let decoded = decoder.decode_row(&row, rows.len(), &mut undecoded);
+                                rows.push(decoded);
+                            }
+                            Ok(None) => {
+                                exhausted = true;
+                                break;
+                            }
+                            Err(e) => {
+                                error = Some(CommandError::sqlx("Query error", e));
+                                break;
+                            }
+                        }
+                    }
+
+                    decoder.render_undecoded(&pool, &mut rows, undecoded).await;
+
+                    let failed = error.is_some();
+                    let reply = match error {
+                        Some(message) => Err(message.with_notices(notices.take())),
+                        None => Ok(CursorPage {
+                            columns: std::mem::take(&mut columns),
+                            rows,
+                            exhausted,
+                            rows_affected: exhausted.then_some(rows_affected),
+                            notices: notices.take(),
+                        }),
+                    };
+
+                    if exhausted || failed {
+                        last = Some((request.reply, reply, failed));
+                        break;
+                    }
+                    let _ = request.reply.send(reply);
+                }
+
+                match last {
+                    // The statement's effect on the transaction is recorded
+                    // before replying, so the caller reads the state it left.
+                    Some((reply_to, reply, failed)) => {
+                        drop(stream);
+                        if savepoint && !failed {
+                            release_savepoint(&mut conn, false).await;
+                        }
+                        conn.statement_finished(&sql, failed).await;
+                        let _ = reply_to.send(reply);
+                    }
+                    None => {
+                        // The cancellation ends the stream with an error, or
+                        // finds the statement already done and is ignored.
+                        if let Some(cancel) = cancel {
+                            if cancel.send().await.is_ok() {
+                                while let Ok(Some(_)) = stream.try_next().await {}
+                            }
+                        }
+                        drop(stream);
+                        if savepoint {
+                            release_savepoint(&mut conn, true).await;
+                        }
+                    }
+                }
+            }
+
+            // A pooled connection that still has rows is closed, settings and
+            // all; a session is drained first so its settings can be restored.
+            let pooled = matches!(conn, JobConnection::Pooled(..));
+            if let Some(saved) = saved.filter(|_| exhausted || !pooled) {
+                saved.restore(&mut conn).await;
+            }
+            if !exhausted {
+                conn.abandon();
+            }
+        });
+
+        Self { requests }
+    }
+
+    /// Pulls up to `limit` more rows from the server.
+    pub async fn fetch(&self, limit: usize) -> Result<CursorPage, CommandError> {
+        let (reply, response) = oneshot::channel();
+
+        self.requests
+            .send(FetchRequest { limit, reply })
+            .await
+            .map_err(|_| CommandError::from("Result set is closed"))?;
+
+        response
+            .await
+            .map_err(|_| CommandError::from("Result set is closed"))?
+    }
+}
+
+/// Ends the savepoint set before a stoppable statement, first rolling back to
+/// it if the statement was cancelled.
+async fn release_savepoint(conn: &mut JobConnection, roll_back: bool) {
+    let release = format!("RELEASE SAVEPOINT {STOP_SAVEPOINT}");
+    let sql = if roll_back {
+        format!("ROLLBACK TO SAVEPOINT {STOP_SAVEPOINT}; {release}")
+    } else {
+        release
+    };
+    let _ = (&mut **conn).execute(sql.as_str()).await;
+}
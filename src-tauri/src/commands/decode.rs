@@ -0,0 +1,1622 @@
+//! Turns PostgreSQL's binary wire values into JSON for the result grid and for
+//! exports.
+//!
+//! Built-in types are decoded here; arrays, ranges, composites and enums are
+//! walked using the type information resolved when the statement was prepared.
+//! Anything left over, such as extension types, is sent back to the server and
+//! rendered through the type's own output function, so a non-null value is
+//! never shown as NULL.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use chrono::{Datelike, TimeZone as _, Timelike};
+use serde_json::Value;
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::types::Oid;
+use sqlx::postgres::{
+    PgArgumentBuffer, PgColumn, PgConnection, PgPool, PgRow, PgTypeInfo, PgTypeKind, PgValueFormat,
+    PgValueRef,
+};
+use sqlx::{Column, Connection, Postgres, Row, ValueRef};
+
+/// Built-in type OIDs, as listed in PostgreSQL's `pg_type.dat`.
+pub(crate) mod oid {
+    pub const BOOL: u32 = 16;
+    pub const BYTEA: u32 = 17;
+    pub const CHAR: u32 = 18;
+    pub const NAME: u32 = 19;
+    pub const INT8: u32 = 20;
+    pub const INT2: u32 = 21;
+    pub const INT4: u32 = 23;
+    pub const TEXT: u32 = 25;
+    pub const OID: u32 = 26;
+    pub const TID: u32 = 27;
+    pub const XID: u32 = 28;
+    pub const CID: u32 = 29;
+    pub const JSON: u32 = 114;
+    pub const XML: u32 = 142;
+    pub const POINT: u32 = 600;
+    pub const LSEG: u32 = 601;
+    pub const PATH: u32 = 602;
+    pub const BOX: u32 = 603;
+    pub const POLYGON: u32 = 604;
+    pub const LINE: u32 = 628;
+    pub const CIDR: u32 = 650;
+    pub const FLOAT4: u32 = 700;
+    pub const FLOAT8: u32 = 701;
+    pub const UNKNOWN: u32 = 705;
+    pub const CIRCLE: u32 = 718;
+    pub const MACADDR8: u32 = 774;
+    pub const MONEY: u32 = 790;
+    pub const MACADDR: u32 = 829;
+    pub const INET: u32 = 869;
+    pub const BPCHAR: u32 = 1042;
+    pub const VARCHAR: u32 = 1043;
+    pub const DATE: u32 = 1082;
+    pub const TIME: u32 = 1083;
+    pub const TIMESTAMP: u32 = 1114;
+    pub const TIMESTAMPTZ: u32 = 1184;
+    pub const INTERVAL: u32 = 1186;
+    pub const TIMETZ: u32 = 1266;
+    pub const BIT: u32 = 1560;
+    pub const VARBIT: u32 = 1562;
+    pub const NUMERIC: u32 = 1700;
+    pub const VOID: u32 = 2278;
+    pub const UUID: u32 = 2950;
+    pub const PG_LSN: u32 = 3220;
+    pub const JSONB: u32 = 3802;
+    pub const INT4MULTIRANGE: u32 = 4451;
+    pub const NUMMULTIRANGE: u32 = 4532;
+    pub const TSMULTIRANGE: u32 = 4533;
+    pub const TSTZMULTIRANGE: u32 = 4534;
+    pub const DATEMULTIRANGE: u32 = 4535;
+    pub const INT8MULTIRANGE: u32 = 4536;
+    pub const XID8: u32 = 5069;
+}
+
+/// How to decode one column, derived from its resolved type.
+#[derive(Debug, Clone)]
+enum Decoder {
+    /// A type identified by its OID alone.
+    Scalar(u32),
+    /// Enum labels travel as their text.
+    Text,
+    /// `bytea` in full, for [`ValueStyle::Export`].
+    Bytes,
+    /// MONEY, a count of the currency's smallest unit, with the number of
+    /// fractional digits the server's `lc_monetary` gives it.
+    Money(u32),
+    Array(Box<Decoder>),
+    Range(Box<Decoder>),
+    Multirange(Box<Decoder>),
+    Composite(Vec<Decoder>),
+}
+
+impl Decoder {
+    /// Builds the decoder for a type resolved by the server, as every column of
+    /// a prepared statement is.
+    fn for_type(type_info: &PgTypeInfo, style: ValueStyle) -> Self {
+        let type_oid = type_info.oid().map(|oid| oid.0).unwrap_or_default();
+
+        // Multiranges come back from sqlx as plain types.
+        if let Some(element) = multirange_element(type_oid) {
+            return Decoder::Multirange(Box::new(Decoder::Scalar(element)));
+        }
+
+        match type_info.kind() {
+            PgTypeKind::Simple if type_oid == oid::BYTEA && style == ValueStyle::Export => {
+                Decoder::Bytes
+            }
+            PgTypeKind::Simple | PgTypeKind::Pseudo => Decoder::Scalar(type_oid),
+            PgTypeKind::Domain(base) => Decoder::for_type(base, style),
+            PgTypeKind::Enum(_) => Decoder::Text,
+            PgTypeKind::Array(element) => {
+                Decoder::Array(Box::new(Decoder::for_type(element, style)))
+            }
+            PgTypeKind::Range(element) => {
+                Decoder::Range(Box::new(Decoder::for_type(element, style)))
+            }
+            PgTypeKind::Composite(fields) => Decoder::Composite(
+                fields
+                    .iter()
+                    .map(|(_, field)| Decoder::for_type(field, style))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn uses_money(&self) -> bool {
+        match self {
+            Decoder::Scalar(type_oid) => *type_oid == oid::MONEY,
+            Decoder::Array(element) | Decoder::Range(element) | Decoder::Multirange(element) => {
+                element.uses_money()
+            }
+            Decoder::Composite(fields) => fields.iter().any(Decoder::uses_money),
+            Decoder::Text | Decoder::Bytes | Decoder::Money(_) => false,
+        }
+    }
+
+    /// Decodes MONEY with `scale` fractional digits; other scalars stay as
+    /// they are.
+    fn with_money_scale(self, scale: u32) -> Self {
+        match self {
+            Decoder::Scalar(oid::MONEY) => Decoder::Money(scale),
+            Decoder::Array(element) => Decoder::Array(Box::new(element.with_money_scale(scale))),
+            Decoder::Composite(fields) => Decoder::Composite(
+                fields
+                    .into_iter()
+                    .map(|field| field.with_money_scale(scale))
+                    .collect(),
+            ),
+            decoder => decoder,
+        }
+    }
+
+    fn decode(&self, bytes: &[u8], tz: &TimeZone) -> Option<Value> {
+        match self {
+            Decoder::Scalar(type_oid) => decode_scalar(*type_oid, bytes, tz),
+            Decoder::Text => text(bytes),
+            Decoder::Bytes => string(format_bytea(bytes)),
+            Decoder::Money(scale) => string(format_money(
+                i64::from_be_bytes(bytes.try_into().ok()?),
+                *scale,
+            )),
+            Decoder::Array(element) => decode_array(element, bytes, tz),
+            Decoder::Range(element) => decode_range(element, bytes, tz).map(Value::String),
+            Decoder::Multirange(element) => {
+                decode_multirange(element, bytes, tz).map(Value::String)
+            }
+            Decoder::Composite(fields) => decode_composite(fields, bytes, tz).map(Value::String),
+        }
+    }
+}
+
+fn multirange_element(type_oid: u32) -> Option<u32> {
+    match type_oid {
+        oid::INT4MULTIRANGE => Some(oid::INT4),
+        oid::NUMMULTIRANGE => Some(oid::NUMERIC),
+        oid::TSMULTIRANGE => Some(oid::TIMESTAMP),
+        oid::TSTZMULTIRANGE => Some(oid::TIMESTAMPTZ),
+        oid::DATEMULTIRANGE => Some(oid::DATE),
+        oid::INT8MULTIRANGE => Some(oid::INT8),
+        _ => None,
+    }
+}
+
+/// A value no decoder understood, to be rendered by the server.
+pub(crate) struct UndecodedValue {
+    row: usize,
+    column: usize,
+    type_oid: u32,
+    bytes: Vec<u8>,
+}
+
+/// Decodes the rows of one statement.
+pub(crate) struct RowDecoder {
+    columns: Vec<Decoder>,
+    time_zone: TimeZone,
+}
+
+impl RowDecoder {
+    /// Builds decoders for a prepared statement's columns, resolving the display
+    /// zone on the connection the statement runs on.
+    pub(crate) async fn new(
+        conn: &mut PgConnection,
+        columns: &[PgColumn],
+        display_zone: DisplayZone,
+        style: ValueStyle,
+    ) -> Self {
+        let time_zone = if columns.is_empty() {
+            TimeZone::Utc
+        } else {
+            TimeZone::resolve(conn, display_zone).await
+        };
+
+        let mut decoders: Vec<Decoder> = columns
+            .iter()
+            .map(|c| Decoder::for_type(c.type_info(), style))
+            .collect();
+        if decoders.iter().any(Decoder::uses_money) {
+            let scale = money_scale(conn).await;
+            decoders = decoders
+                .into_iter()
+                .map(|decoder| decoder.with_money_scale(scale))
+                .collect();
+        }
+
+        Self {
+            columns: decoders,
+            time_zone,
+        }
+    }
+
+    /// Decodes every column of `row`, which is row number `row_index` of the
+    /// page being built. Values that cannot be decoded here are left as
+    /// placeholders and recorded in `undecoded` for [`Self::render_undecoded`].
+    pub(crate) fn decode_row(
+        &self,
+        row: &PgRow,
+        row_index: usize,
+        undecoded: &mut Vec<UndecodedValue>,
+    ) -> Vec<Value> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(column, decoder)| {
+                let Ok(value) = row.try_get_raw(column) else {
+                    return Value::Null;
+                };
+                if value.is_null() {
+                    return Value::Null;
+                }
+                if let Some(decoded) = decode_value(decoder, &value, &self.time_zone) {
+                    return decoded;
+                }
+
+                undecoded.push(UndecodedValue {
+                    row: row_index,
+                    column,
+                    type_oid: value.type_info().oid().map(|oid| oid.0).unwrap_or_default(),
+                    bytes: value.as_bytes().map(<[u8]>::to_vec).unwrap_or_default(),
+                });
+                Value::Null
+            })
+            .collect()
+    }
+
+    /// Fills in the values [`Self::decode_row`] could not decode by asking the
+    /// server to cast them to text, which runs the type's output function in
+    /// the display zone.
+    ///
+    /// This runs on its own pooled connection so a failure can never abort a
+    /// transaction the user has open. Should the server be unable to render a
+    /// value, its raw bytes are shown instead.
+    pub(crate) async fn render_undecoded(
+        &self,
+        pool: &PgPool,
+        rows: &mut [Vec<Value>],
+        mut undecoded: Vec<UndecodedValue>,
+    ) {
+        if undecoded.is_empty() {
+            return;
+        }
+
+        let mut conn = pool.acquire().await.ok();
+        let mut time_zone = self.time_zone.setting();
+
+        while !undecoded.is_empty() {
+            let batch: Vec<UndecodedValue> = undecoded
+                .drain(..undecoded.len().min(RENDER_BATCH))
+                .collect();
+
+            let mut rendered = None;
+            if let Some(conn) = conn.as_mut() {
+                rendered = render_batch(conn, &batch, time_zone.as_deref()).await.ok();
+                // A zone name the server rejects should not cost every value.
+                if rendered.is_none() && time_zone.take().is_some() {
+                    rendered = render_batch(conn, &batch, None).await.ok();
+                }
+            }
+
+            for (i, value) in batch.into_iter().enumerate() {
+                let text = rendered
+                    .as_ref()
+                    .and_then(|row| row.try_get::<Option<String>, _>(i).ok().flatten())
+                    .unwrap_or_else(|| raw_text(&value.bytes));
+                rows[value.row][value.column] = Value::String(text);
+            }
+        }
+    }
+}
+
+fn decode_value(decoder: &Decoder, value: &PgValueRef<'_>, tz: &TimeZone) -> Option<Value> {
+    match value.format() {
+        PgValueFormat::Binary => decoder.decode(value.as_bytes().ok()?, tz),
+        PgValueFormat::Text => value.as_str().ok().map(|s| Value::String(s.to_string())),
+    }
+}
+
+/// Most values rendered by a single round trip.
+const RENDER_BATCH: usize = 1000;
+
+/// Casts a batch of values to text in one round trip. `DateStyle` and
+/// `TimeZone` are set for the duration so dates read the same as those decoded
+/// locally.
+async fn render_batch(
+    conn: &mut PgConnection,
+    batch: &[UndecodedValue],
+    time_zone: Option<&str>,
+) -> Result<PgRow, sqlx::Error> {
+    let select = (1..=batch.len())
+        .map(|i| format!("${}::text", i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!("SELECT {}", select);
+
+    let mut tx = conn.begin().await?;
+    sqlx::query(
+        "SELECT set_config('DateStyle', 'ISO', true), \
+         set_config('TimeZone', COALESCE($1, current_setting('TimeZone')), true)",
+    )
+    .bind(time_zone)
+    .execute(&mut *tx)
+    .await?;
+
+    let mut query = sqlx::query(&sql).persistent(false);
+    for value in batch {
+        query = query.bind(RawValue {
+            type_oid: value.type_oid,
+            bytes: value.bytes.clone(),
+        });
+    }
+    let row = query.fetch_one(&mut *tx).await?;
+
+    tx.rollback().await?;
+    Ok(row)
+}
+
+/// The bytes of a value as text, or as a `bytea`-style hex string when they are
+/// not valid UTF-8.
+fn raw_text(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.to_string(),
+        Err(_) => format_bytea(bytes),
+    }
+}
+
+/// `bytea` in PostgreSQL's hex output format, which its input function reads
+/// back.
+fn format_bytea(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("\\x{}", hex)
+}
+
+/// A binary value sent back to the server under its original type.
+struct RawValue {
+    type_oid: u32,
+    bytes: Vec<u8>,
+}
+
+impl sqlx::Type<Postgres> for RawValue {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("unknown")
+    }
+}
+
+impl sqlx::Encode<'_, Postgres> for RawValue {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        buf.extend_from_slice(&self.bytes);
+        Ok(IsNull::No)
+    }
+
+    fn produces(&self) -> Option<PgTypeInfo> {
+        Some(PgTypeInfo::with_oid(Oid(self.type_oid)))
+    }
+}
+
+/// Sequential reader over a binary value, all integers big-endian.
+struct WireReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> WireReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.array::<1>()?[0])
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        Some(i16::from_be_bytes(self.array()?))
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        Some(i32::from_be_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        Some(i64::from_be_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_be_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        Some(f64::from_be_bytes(self.array()?))
+    }
+
+    /// A length-prefixed field; `None` inside the option stands for NULL.
+    fn field(&mut self) -> Option<Option<&'a [u8]>> {
+        let len = self.i32()?;
+        if len < 0 {
+            return Some(None);
+        }
+        self.take(len as usize).map(Some)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.bytes)
+    }
+}
+
+fn text(bytes: &[u8]) -> Option<Value> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .map(|s| Value::String(s.to_string()))
+}
+
+fn string(s: String) -> Option<Value> {
+    Some(Value::String(s))
+}
+
+fn decode_scalar(type_oid: u32, bytes: &[u8], tz: &TimeZone) -> Option<Value> {
+    let mut r = WireReader::new(bytes);
+
+    match type_oid {
+        oid::BOOL => Some(Value::Bool(r.u8()? != 0)),
+        oid::INT2 => Some(Value::Number(r.i16()?.into())),
+        oid::INT4 => Some(Value::Number(r.i32()?.into())),
+        oid::INT8 => Some(int8_to_json(r.i64()?)),
+        oid::OID | oid::XID | oid::CID => Some(Value::Number(r.u32()?.into())),
+        oid::XID8 => {
+            let v = r.u64()?;
+            Some(i64::try_from(v).map_or_else(|_| Value::String(v.to_string()), int8_to_json))
+        }
+        oid::FLOAT4 => Some(float_to_json(f32::from_be_bytes(r.array()?) as f64)),
+        oid::FLOAT8 => Some(float_to_json(r.f64()?)),
+        oid::NUMERIC => decode_numeric(bytes).map(Value::String),
+        oid::TEXT
+        | oid::VARCHAR
+        | oid::BPCHAR
+        | oid::NAME
+        | oid::CHAR
+        | oid::XML
+        | oid::UNKNOWN => text(bytes),
+        oid::VOID => string(String::new()),
+        oid::BYTEA => string(format!("<BLOB {} bytes>", bytes.len())),
+        oid::JSON => serde_json::from_slice(bytes).ok(),
+        oid::JSONB => match bytes.split_first()? {
+            (1, json) => serde_json::from_slice(json).ok(),
+            _ => None,
+        },
+        oid::UUID => string(uuid::Uuid::from_slice(bytes).ok()?.to_string()),
+        oid::DATE => string(format_date(r.i32()?)?),
+        oid::TIME => string(format_time(r.i64()?)?),
+        oid::TIMETZ => string(format_timetz(r.i64()?, r.i32()?, tz)?),
+        oid::TIMESTAMP => string(format_timestamp(r.i64()?)?),
+        oid::TIMESTAMPTZ => string(format_timestamptz(r.i64()?, tz)?),
+        oid::INTERVAL => string(format_interval(r.i64()?, r.i32()?, r.i32()?)),
+        oid::INET | oid::CIDR => string(format_inet(bytes, type_oid == oid::CIDR)?),
+        oid::MACADDR | oid::MACADDR8 => string(format_macaddr(bytes)),
+        oid::BIT | oid::VARBIT => string(format_bits(bytes)?),
+        oid::TID => string(format!("({},{})", r.u32()?, r.i16()? as u16)),
+        oid::PG_LSN => {
+            let lsn = r.u64()?;
+            string(format!("{:X}/{:X}", lsn >> 32, lsn & 0xFFFF_FFFF))
+        }
+        oid::POINT => string(format_point(&mut r)?),
+        oid::LINE => string(format!(
+            "{{{},{},{}}}",
+            format_float(r.f64()?),
+            format_float(r.f64()?),
+            format_float(r.f64()?)
+        )),
+        oid::LSEG => string(format!(
+            "[{},{}]",
+            format_point(&mut r)?,
+            format_point(&mut r)?
+        )),
+        oid::BOX => string(format!(
+            "{},{}",
+            format_point(&mut r)?,
+            format_point(&mut r)?
+        )),
+        oid::PATH => {
+            let closed = r.u8()? != 0;
+            let points = format_points(&mut r)?;
+            string(if closed {
+                format!("({})", points)
+            } else {
+                format!("[{}]", points)
+            })
+        }
+        oid::POLYGON => string(format!("({})", format_points(&mut r)?)),
+        oid::CIRCLE => string(format!(
+            "<{},{}>",
+            format_point(&mut r)?,
+            format_float(r.f64()?)
+        )),
+        _ => None,
+    }
+}
+
+/// Largest integer a JavaScript number holds exactly (`Number.MAX_SAFE_INTEGER`).
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// INT8 values outside the range a JavaScript number can hold exactly are sent
+/// as strings so the webview does not round them.
+fn int8_to_json(v: i64) -> Value {
+    if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&v) {
+        Value::Number(v.into())
+    } else {
+        Value::String(v.to_string())
+    }
+}
+
+/// JSON has no NaN or infinities, so those are sent as PostgreSQL spells them.
+fn float_to_json(v: f64) -> Value {
+    match serde_json::Number::from_f64(v) {
+        Some(n) => Value::Number(n),
+        None => Value::String(format_float(v)),
+    }
+}
+
+fn format_float(v: f64) -> String {
+    if v.is_nan() {
+        "NaN".to_string()
+    } else if v.is_infinite() {
+        if v > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else {
+        v.to_string()
+    }
+}
+
+const NUMERIC_NEG: u16 = 0x4000;
+const NUMERIC_NAN: u16 = 0xC000;
+const NUMERIC_PINF: u16 = 0xD000;
+const NUMERIC_NINF: u16 = 0xF000;
+
+/// Renders a binary NUMERIC exactly, with as many fractional digits as its
+/// display scale, the same way PostgreSQL's `numeric_out` does.
+///
+/// The wire format is a header of digit count, weight, sign and display scale,
+/// followed by base-10000 digits, the first of which is multiplied by
+/// 10000^weight.
+fn decode_numeric(bytes: &[u8]) -> Option<String> {
+    let read = |at: usize| {
+        bytes
+            .get(at..at + 2)
+            .map(|b| i16::from_be_bytes([b[0], b[1]]))
+    };
+
+    let ndigits = read(0)? as usize;
+    let weight = read(2)? as i32;
+    let sign = read(4)? as u16;
+    let dscale = read(6)? as u16 as usize;
+
+    match sign {
+        NUMERIC_NAN => return Some("NaN".to_string()),
+        NUMERIC_PINF => return Some("Infinity".to_string()),
+        NUMERIC_NINF => return Some("-Infinity".to_string()),
+        _ => {}
+    }
+
+    let digits: Vec<i16> = (0..ndigits)
+        .map(|i| read(8 + i * 2))
+        .collect::<Option<_>>()?;
+    let digit = |group: i32| {
+        usize::try_from(group)
+            .ok()
+            .and_then(|g| digits.get(g).copied())
+            .unwrap_or(0)
+    };
+
+    let mut out = String::new();
+    if sign == NUMERIC_NEG {
+        out.push('-');
+    }
+
+    if weight < 0 {
+        out.push('0');
+    } else {
+        out.push_str(&digit(0).to_string());
+        for group in 1..=weight {
+            out.push_str(&format!("{:04}", digit(group)));
+        }
+    }
+
+    if dscale > 0 {
+        let mut fraction = String::with_capacity(dscale + 4);
+        let mut group = weight + 1;
+        while fraction.len() < dscale {
+            fraction.push_str(&format!("{:04}", digit(group)));
+            group += 1;
+        }
+        out.push('.');
+        out.push_str(&fraction[..dscale]);
+    }
+
+    Some(out)
+}
+
+/// Fractional digits of MONEY under the connection's `lc_monetary`, which the
+/// server also gives the NUMERIC a MONEY converts to. Without an answer, two are
+/// assumed, as the server does for locales that do not say.
+async fn money_scale(conn: &mut PgConnection) -> u32 {
+    sqlx::query_scalar::<_, i32>("SELECT scale('0'::money::numeric)")
+        .fetch_one(conn)
+        .await
+        .ok()
+        .and_then(|scale| u32::try_from(scale).ok())
+        .unwrap_or(2)
+}
+
+/// An amount of `scale` fractional digits as an exact decimal, without the
+/// currency symbol and grouping the server's own text has.
+fn format_money(amount: i64, scale: u32) -> String {
+    let sign = if amount < 0 { "-" } else { "" };
+    let amount = amount.unsigned_abs();
+    match 10u64.checked_pow(scale) {
+        Some(unit) if scale > 0 => format!(
+            "{sign}{}.{:0width$}",
+            amount / unit,
+            amount % unit,
+            width = scale as usize
+        ),
+        _ => format!("{sign}{amount}"),
+    }
+}
+
+/// Where TIMESTAMPTZ and TIMETZ values are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DisplayZone {
+    /// The connection's `TimeZone` setting, as `psql` would show them.
+    #[default]
+    Session,
+    /// The time zone of the machine running the app.
+    Local,
+    Utc,
+}
+
+/// What decoded values are for, which decides how much of a `bytea` is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueStyle {
+    /// The result grid, which shows a `bytea` by its size alone.
+    Grid,
+    /// A file, which gets every byte.
+    Export,
+}
+
+/// A [`DisplayZone`] resolved against the connection a statement runs on.
+#[derive(Debug, Clone)]
+pub(crate) enum TimeZone {
+    Utc,
+    Local,
+    /// A zone only the server knows the rules of, such as `Europe/Berlin`.
+    /// Values in it are rendered by the server.
+    Named(String),
+}
+
+impl TimeZone {
+    pub(crate) async fn resolve(conn: &mut PgConnection, display: DisplayZone) -> Self {
+        match display {
+            DisplayZone::Utc => TimeZone::Utc,
+            DisplayZone::Local => TimeZone::Local,
+            DisplayZone::Session => {
+                let setting = sqlx::query_scalar::<_, String>("SELECT current_setting('TimeZone')")
+                    .fetch_one(conn)
+                    .await;
+                match setting {
+                    Ok(name) if is_utc(&name) => TimeZone::Utc,
+                    Ok(name) => TimeZone::Named(name),
+                    Err(_) => TimeZone::Utc,
+                }
+            }
+        }
+    }
+
+    /// The zone's name as the server's `TimeZone` setting accepts it.
+    fn setting(&self) -> Option<String> {
+        match self {
+            TimeZone::Utc => Some("UTC".to_string()),
+            TimeZone::Local => iana_time_zone::get_timezone().ok(),
+            TimeZone::Named(name) => Some(name.clone()),
+        }
+    }
+
+    /// Seconds east of UTC at the given instant, when known locally.
+    fn offset_at(&self, utc: chrono::NaiveDateTime) -> Option<i32> {
+        match self {
+            TimeZone::Utc => Some(0),
+            TimeZone::Local => Some(
+                chrono::Local
+                    .offset_from_utc_datetime(&utc)
+                    .local_minus_utc(),
+            ),
+            TimeZone::Named(_) => None,
+        }
+    }
+}
+
+fn is_utc(name: &str) -> bool {
+    [
+        "UTC",
+        "Etc/UTC",
+        "GMT",
+        "Etc/GMT",
+        "UCT",
+        "Universal",
+        "Zulu",
+        "Z",
+    ]
+    .iter()
+    .any(|utc| name.eq_ignore_ascii_case(utc))
+}
+
+fn postgres_epoch() -> chrono::NaiveDateTime {
+    chrono::NaiveDate::from_ymd_opt(2000, 1, 1)
+        .unwrap_or_default()
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+}
+
+const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+/// `YYYY-MM-DD`, with the era split off because PostgreSQL puts ` BC` after
+/// everything else, offsets included.
+fn format_ymd(date: chrono::NaiveDate) -> (String, &'static str) {
+    let (year, era) = match date.year() {
+        year if year <= 0 => (1 - year, " BC"),
+        year => (year, ""),
+    };
+    (
+        format!("{:04}-{:02}-{:02}", year, date.month(), date.day()),
+        era,
+    )
+}
+
+/// `HH:MM:SS` plus only as many fractional digits as the value has.
+fn format_hms(time: chrono::NaiveTime) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}",
+        time.hour(),
+        time.minute(),
+        time.second(),
+        fractional_seconds(u64::from(time.nanosecond() / 1000))
+    )
+}
+
+/// `+HH`, `+HH:MM` or `+HH:MM:SS`, the shortest that is exact.
+fn format_offset(east: i32) -> String {
+    let sign = if east < 0 { '-' } else { '+' };
+    let east = east.unsigned_abs();
+    let mut offset = format!("{}{:02}", sign, east / 3600);
+    if !east.is_multiple_of(3600) {
+        offset.push_str(&format!(":{:02}", east % 3600 / 60));
+        if !east.is_multiple_of(60) {
+            offset.push_str(&format!(":{:02}", east % 60));
+        }
+    }
+    offset
+}
+
+/// Days since 2000-01-01.
+fn format_date(days: i32) -> Option<String> {
+    match days {
+        i32::MAX => Some("infinity".to_string()),
+        i32::MIN => Some("-infinity".to_string()),
+        _ => {
+            let date = postgres_epoch()
+                .date()
+                .checked_add_signed(chrono::TimeDelta::days(days.into()))?;
+            let (ymd, era) = format_ymd(date);
+            Some(format!("{}{}", ymd, era))
+        }
+    }
+}
+
+/// Microseconds since midnight; `24:00:00` is a valid time of day.
+fn format_time(micros: i64) -> Option<String> {
+    if micros == MICROS_PER_DAY {
+        return Some("24:00:00".to_string());
+    }
+    let secs = u32::try_from(micros.div_euclid(1_000_000)).ok()?;
+    let nanos = (micros.rem_euclid(1_000_000) * 1000) as u32;
+    chrono::NaiveTime::from_num_seconds_from_midnight_opt(secs, nanos).map(format_hms)
+}
+
+/// Time of day followed by the zone's offset in seconds west of UTC. In the
+/// session zone the value keeps the offset it was stored with, as PostgreSQL
+/// shows it; otherwise it is moved to the display zone's current offset.
+fn format_timetz(micros: i64, zone: i32, tz: &TimeZone) -> Option<String> {
+    let stored_east = -zone;
+    let east = match tz {
+        TimeZone::Named(_) => stored_east,
+        tz => tz.offset_at(chrono::Utc::now().naive_utc())?,
+    };
+    if east == stored_east {
+        return Some(format!("{}{}", format_time(micros)?, format_offset(east)));
+    }
+
+    let shifted = (micros + i64::from(east - stored_east) * 1_000_000).rem_euclid(MICROS_PER_DAY);
+    Some(format!("{}{}", format_time(shifted)?, format_offset(east)))
+}
+
+/// Microseconds since 2000-01-01 00:00:00.
+fn timestamp(micros: i64) -> Option<chrono::NaiveDateTime> {
+    postgres_epoch().checked_add_signed(chrono::TimeDelta::microseconds(micros))
+}
+
+fn format_datetime(ts: chrono::NaiveDateTime, offset: Option<i32>) -> String {
+    let (ymd, era) = format_ymd(ts.date());
+    let offset = offset.map(format_offset).unwrap_or_default();
+    format!("{} {}{}{}", ymd, format_hms(ts.time()), offset, era)
+}
+
+fn format_timestamp(micros: i64) -> Option<String> {
+    match micros {
+        i64::MAX => Some("infinity".to_string()),
+        i64::MIN => Some("-infinity".to_string()),
+        _ => timestamp(micros).map(|ts| format_datetime(ts, None)),
+    }
+}
+
+/// An instant shown as wall-clock time in the display zone with its offset,
+/// e.g. `2024-03-31 03:30:00.25+02`. `None` when the zone's rules are only
+/// known to the server.
+fn format_timestamptz(micros: i64, tz: &TimeZone) -> Option<String> {
+    match micros {
+        i64::MAX => Some("infinity".to_string()),
+        i64::MIN => Some("-infinity".to_string()),
+        _ => {
+            let utc = timestamp(micros)?;
+            let east = tz.offset_at(utc)?;
+            let local = utc.checked_add_signed(chrono::TimeDelta::seconds(east.into()))?;
+            Some(format_datetime(local, Some(east)))
+        }
+    }
+}
+
+/// Reads back a date as [`format_date`] writes it, for exports that store
+/// dates natively. Infinities give `None`.
+pub(crate) fn parse_date(text: &str) -> Option<chrono::NaiveDate> {
+    let (ymd, bc) = match text.strip_suffix(" BC") {
+        Some(ymd) => (ymd, true),
+        None => (text, false),
+    };
+    let date = chrono::NaiveDate::parse_from_str(ymd, "%Y-%m-%d").ok()?;
+    if bc {
+        date.with_year(1 - date.year())
+    } else {
+        Some(date)
+    }
+}
+
+/// Reads back a time of day as [`format_time`] writes it; `24:00:00` gives
+/// `None`.
+pub(crate) fn parse_time(text: &str) -> Option<chrono::NaiveTime> {
+    chrono::NaiveTime::parse_from_str(text, "%H:%M:%S%.f").ok()
+}
+
+/// Reads back a timestamp as [`format_timestamp`] or [`format_timestamptz`]
+/// writes it: the wall-clock time, and for TIMESTAMPTZ the offset it is shown
+/// at in seconds east of UTC. Infinities give `None`.
+pub(crate) fn parse_timestamp(text: &str) -> Option<(chrono::NaiveDateTime, Option<i32>)> {
+    let (text, era) = match text.strip_suffix(" BC") {
+        Some(text) => (text, " BC"),
+        None => (text, ""),
+    };
+    let (ymd, rest) = text.split_once(' ')?;
+    let (hms, offset) = match rest.find(['+', '-']) {
+        Some(at) => (&rest[..at], Some(parse_offset(&rest[at..])?)),
+        None => (rest, None),
+    };
+
+    let date = parse_date(&format!("{}{}", ymd, era))?;
+    Some((date.and_time(parse_time(hms)?), offset))
+}
+
+/// The inverse of [`format_offset`].
+fn parse_offset(text: &str) -> Option<i32> {
+    let (sign, hms) = match text.split_at_checked(1)? {
+        ("+", hms) => (1, hms),
+        ("-", hms) => (-1, hms),
+        _ => return None,
+    };
+    let mut east = 0;
+    for (part, unit) in hms.split(':').zip([3600, 60, 1]) {
+        east += part.parse::<i32>().ok()? * unit;
+    }
+    Some(sign * east)
+}
+
+/// Renders an interval the way `IntervalStyle = postgres` does, e.g.
+/// `1 year 2 mons -3 days +04:05:06.5`.
+fn format_interval(micros: i64, days: i32, months: i32) -> String {
+    if micros == i64::MAX && days == i32::MAX && months == i32::MAX {
+        return "infinity".to_string();
+    }
+    if micros == i64::MIN && days == i32::MIN && months == i32::MIN {
+        return "-infinity".to_string();
+    }
+
+    let mut out = String::new();
+    let mut is_zero = true;
+    let mut is_before = false;
+
+    for (value, unit) in [
+        (i64::from(months / 12), "year"),
+        (i64::from(months % 12), "mon"),
+        (i64::from(days), "day"),
+    ] {
+        if value == 0 {
+            continue;
+        }
+        out.push_str(&format!(
+            "{}{}{} {}{}",
+            if is_zero { "" } else { " " },
+            if is_before && value > 0 { "+" } else { "" },
+            value,
+            unit,
+            if value != 1 { "s" } else { "" }
+        ));
+        is_before = value < 0;
+        is_zero = false;
+    }
+
+    if micros != 0 || is_zero {
+        let abs = micros.unsigned_abs();
+        let sign = if micros < 0 {
+            "-"
+        } else if is_before {
+            "+"
+        } else {
+            ""
+        };
+        out.push_str(&format!(
+            "{}{}{:02}:{:02}:{:02}{}",
+            if is_zero { "" } else { " " },
+            sign,
+            abs / 3_600_000_000,
+            abs / 60_000_000 % 60,
+            abs / 1_000_000 % 60,
+            fractional_seconds(abs % 1_000_000)
+        ));
+    }
+
+    out
+}
+
+/// `.ffffff` with trailing zeros trimmed, or nothing for whole seconds.
+fn fractional_seconds(micros: u64) -> String {
+    if micros == 0 {
+        return String::new();
+    }
+    let digits = format!("{:06}", micros);
+    format!(".{}", digits.trim_end_matches('0'))
+}
+
+/// Address family, prefix length, CIDR flag and address bytes. The prefix
+/// length is only shown for `inet` when it does not cover the whole address.
+fn format_inet(bytes: &[u8], is_cidr: bool) -> Option<String> {
+    let mut r = WireReader::new(bytes);
+    let family = r.u8()?;
+    let bits = r.u8()?;
+    let _is_cidr = r.u8()?;
+    let len = r.u8()?;
+    let address = r.take(len as usize)?;
+
+    let (address, max_bits) = match family {
+        2 => (
+            Ipv4Addr::from(<[u8; 4]>::try_from(address).ok()?).to_string(),
+            32,
+        ),
+        3 => (
+            Ipv6Addr::from(<[u8; 16]>::try_from(address).ok()?).to_string(),
+            128,
+        ),
+        _ => return None,
+    };
+
+    Some(if is_cidr || bits != max_bits {
+        format!("{}/{}", address, bits)
+    } else {
+        address
+    })
+}
+
+fn format_macaddr(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Bit length followed by the bits, most significant first.
+fn format_bits(bytes: &[u8]) -> Option<String> {
+    let mut r = WireReader::new(bytes);
+    let len = usize::try_from(r.i32()?).ok()?;
+    let data = r.rest();
+    (0..len)
+        .map(|i| {
+            let byte = data.get(i / 8)?;
+            Some(if byte & (0x80 >> (i % 8)) != 0 {
+                '1'
+            } else {
+                '0'
+            })
+        })
+        .collect()
+}
+
+fn format_point(r: &mut WireReader) -> Option<String> {
+    Some(format!(
+        "({},{})",
+        format_float(r.f64()?),
+        format_float(r.f64()?)
+    ))
+}
+
+/// A point count followed by that many points.
+fn format_points(r: &mut WireReader) -> Option<String> {
+    let count = r.i32()?;
+    (0..count)
+        .map(|_| format_point(r))
+        .collect::<Option<Vec<_>>>()
+        .map(|points| points.join(","))
+}
+
+/// Arrays become nested JSON arrays, one level per dimension. The header holds
+/// the dimension count, a has-nulls flag, the element OID and each dimension's
+/// length and lower bound, followed by the elements in row-major order.
+fn decode_array(element: &Decoder, bytes: &[u8], tz: &TimeZone) -> Option<Value> {
+    let mut r = WireReader::new(bytes);
+    let ndim = r.i32()?;
+    let _has_nulls = r.i32()?;
+    let _element_oid = r.u32()?;
+
+    let dims = (0..ndim)
+        .map(|_| {
+            let len = r.i32()?;
+            let _lower_bound = r.i32()?;
+            usize::try_from(len).ok()
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    if dims.is_empty() {
+        return Some(Value::Array(vec![]));
+    }
+    decode_array_dimension(element, &dims, &mut r, tz)
+}
+
+fn decode_array_dimension(
+    element: &Decoder,
+    dims: &[usize],
+    r: &mut WireReader,
+    tz: &TimeZone,
+) -> Option<Value> {
+    let Some((&len, inner)) = dims.split_first() else {
+        return match r.field()? {
+            Some(bytes) => element.decode(bytes, tz),
+            None => Some(Value::Null),
+        };
+    };
+
+    (0..len)
+        .map(|_| decode_array_dimension(element, inner, r, tz))
+        .collect::<Option<Vec<_>>>()
+        .map(Value::Array)
+}
+
+const RANGE_EMPTY: u8 = 0x01;
+const RANGE_LB_INC: u8 = 0x02;
+const RANGE_UB_INC: u8 = 0x04;
+const RANGE_LB_INF: u8 = 0x08;
+const RANGE_UB_INF: u8 = 0x10;
+
+/// Ranges are shown in PostgreSQL's text form, e.g. `[1,10)`.
+fn decode_range(element: &Decoder, bytes: &[u8], tz: &TimeZone) -> Option<String> {
+    let mut r = WireReader::new(bytes);
+    let flags = r.u8()?;
+
+    if flags & RANGE_EMPTY != 0 {
+        return Some("empty".to_string());
+    }
+
+    let mut bound = |infinite: bool| -> Option<String> {
+        if infinite {
+            return Some(String::new());
+        }
+        let bytes = r.field()??;
+        let value = value_to_text(&element.decode(bytes, tz)?).unwrap_or_default();
+        Some(quote_if_needed(&value, "\"\\(),[]", false))
+    };
+    let lower = bound(flags & RANGE_LB_INF != 0)?;
+    let upper = bound(flags & RANGE_UB_INF != 0)?;
+
+    Some(format!(
+        "{}{},{}{}",
+        if flags & RANGE_LB_INC != 0 { '[' } else { '(' },
+        lower,
+        upper,
+        if flags & RANGE_UB_INC != 0 { ']' } else { ')' }
+    ))
+}
+
+/// A range count followed by length-prefixed ranges.
+fn decode_multirange(element: &Decoder, bytes: &[u8], tz: &TimeZone) -> Option<String> {
+    let mut r = WireReader::new(bytes);
+    let count = r.i32()?;
+    let ranges = (0..count)
+        .map(|_| decode_range(element, r.field()??, tz))
+        .collect::<Option<Vec<_>>>()?;
+    Some(format!("{{{}}}", ranges.join(",")))
+}
+
+/// Composites are shown in PostgreSQL's row text form, e.g. `(1,"a b",)`. The
+/// wire format is a field count followed by each field's type OID and
+/// length-prefixed value.
+fn decode_composite(fields: &[Decoder], bytes: &[u8], tz: &TimeZone) -> Option<String> {
+    let mut r = WireReader::new(bytes);
+    let count = usize::try_from(r.i32()?).ok()?;
+
+    let values = (0..count)
+        .map(|i| {
+            let field_oid = r.u32()?;
+            let Some(bytes) = r.field()? else {
+                return Some(String::new());
+            };
+            let value = match fields.get(i) {
+                Some(decoder) => decoder.decode(bytes, tz)?,
+                None => decode_scalar(field_oid, bytes, tz)?,
+            };
+            let text = value_to_text(&value).unwrap_or_default();
+            Some(quote_if_needed(&text, "\"\\(),", false))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(format!("({})", values.join(",")))
+}
+
+/// Whether values of the type are decoded from JSON text, so that their text
+/// form is the JSON itself even when it is a bare string.
+pub(crate) fn is_json_type(type_oid: u32) -> bool {
+    matches!(type_oid, oid::JSON | oid::JSONB)
+}
+
+/// The text form of an already decoded value, for nesting inside a range,
+/// composite or array literal. `None` stands for NULL.
+pub(crate) fn value_to_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Bool(b) => Some(if *b { "t" } else { "f" }.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        Value::Array(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| match value_to_text(item) {
+                    Some(text) if item.is_array() => text,
+                    Some(text) if text.eq_ignore_ascii_case("NULL") => format!("\"{}\"", text),
+                    Some(text) => quote_if_needed(&text, "\"\\{},", true),
+                    None => "NULL".to_string(),
+                })
+                .collect();
+            Some(format!("{{{}}}", items.join(",")))
+        }
+        Value::Object(_) => Some(value.to_string()),
+    }
+}
+
+/// Double-quotes `text` when it is empty or contains whitespace or any of
+/// `special`. Quotes and backslashes inside are escaped with a backslash for
+/// array literals and doubled otherwise.
+fn quote_if_needed(text: &str, special: &str, backslash_escapes: bool) -> String {
+    let needs_quotes = text.is_empty()
+        || text
+            .chars()
+            .any(|c| c.is_ascii_whitespace() || special.contains(c));
+    if !needs_quotes {
+        return text.to_string();
+    }
+
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push(if backslash_escapes { '\\' } else { c });
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fixtures are the server's binary send output, as hex, paired with its text
+    // output for the same value under TimeZone UTC, DateStyle ISO and
+    // IntervalStyle postgres.
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn render(decoder: &Decoder, hex: &str) -> String {
+        let value = decoder.decode(&bytes(hex), &TimeZone::Utc).unwrap();
+        value_to_text(&value).unwrap()
+    }
+
+    fn scalar(type_oid: u32, hex: &str) -> String {
+        render(&Decoder::Scalar(type_oid), hex)
+    }
+
+    #[test]
+    fn decodes_money_exactly() {
+        let cents = Decoder::Money(2);
+        assert_eq!(render(&cents, "000000000001e226"), "1234.30");
+        assert_eq!(render(&cents, "fffffffffffffff9"), "-0.07");
+        assert_eq!(render(&cents, "8000000000000000"), "-92233720368547758.08");
+        assert_eq!(render(&Decoder::Money(0), "00000000000004d2"), "1234");
+        assert_eq!(render(&Decoder::Money(3), "0000000000000005"), "0.005");
+
+        let amounts = Decoder::Array(Box::new(Decoder::Scalar(oid::MONEY)));
+        assert!(amounts.uses_money());
+        assert!(matches!(
+            amounts.with_money_scale(2),
+            Decoder::Array(element) if matches!(*element, Decoder::Money(2))
+        ));
+    }
+
+    #[test]
+    fn decodes_numeric() {
+        assert_eq!(scalar(oid::NUMERIC, "0000000000000000"), "0");
+        assert_eq!(scalar(oid::NUMERIC, "0000000000000002"), "0.00");
+        assert_eq!(scalar(oid::NUMERIC, "0002000000000001000c1388"), "12.5");
+        assert_eq!(scalar(oid::NUMERIC, "00010002000000000001"), "100000000");
+        assert_eq!(
+            scalar(oid::NUMERIC, "0001fffd0000000c04d2"),
+            "0.000000001234"
+        );
+    }
+
+    #[test]
+    fn decodes_negative_numeric() {
+        assert_eq!(
+            scalar(oid::NUMERIC, "000200004000000404d2162e"),
+            "-1234.5678"
+        );
+        assert_eq!(scalar(oid::NUMERIC, "0001ffff400000040001"), "-0.0001");
+    }
+
+    #[test]
+    fn decodes_high_weight_numeric() {
+        assert_eq!(
+            scalar(
+                oid::NUMERIC,
+                "0008000700000000000c0d801ed204d2162e23340d801ed2"
+            ),
+            "123456789012345678901234567890"
+        );
+    }
+
+    #[test]
+    fn decodes_special_numeric() {
+        assert_eq!(scalar(oid::NUMERIC, "00000000c0000000"), "NaN");
+        assert_eq!(scalar(oid::NUMERIC, "00000000d0000020"), "Infinity");
+        assert_eq!(scalar(oid::NUMERIC, "00000000f0000020"), "-Infinity");
+    }
+
+    #[test]
+    fn decodes_interval() {
+        assert_eq!(
+            scalar(oid::INTERVAL, "00000000000000000000000000000000"),
+            "00:00:00"
+        );
+        assert_eq!(
+            scalar(oid::INTERVAL, "000000036c9361a0000000030000000e"),
+            "1 year 2 mons 3 days 04:05:06.5"
+        );
+        assert_eq!(
+            scalar(oid::INTERVAL, "000000036c8bc080fffffffdfffffff6"),
+            "-10 mons -3 days +04:05:06"
+        );
+        assert_eq!(
+            scalar(oid::INTERVAL, "ffffffffffeced300000000100000000"),
+            "1 day -00:00:01.25"
+        );
+        assert_eq!(
+            scalar(oid::INTERVAL, "0000000000000000000000000000000e"),
+            "1 year 2 mons"
+        );
+    }
+
+    #[test]
+    fn decodes_dates_and_times() {
+        assert_eq!(scalar(oid::DATE, "00002279"), "2024-02-29");
+        assert_eq!(scalar(oid::DATE, "fff49d7b"), "0044-03-15 BC");
+        assert_eq!(scalar(oid::TIME, "0000000b8839b2f8"), "13:45:30.123");
+        assert_eq!(scalar(oid::TIME, "000000141dd76000"), "24:00:00");
+        // In the session's own zone a time keeps the offset it was stored with.
+        let session = TimeZone::Named("UTC".to_string());
+        let timetz = bytes("0000000b8837d280ffffb2a8");
+        assert_eq!(
+            decode_scalar(oid::TIMETZ, &timetz, &session),
+            Some(Value::String("13:45:30+05:30".to_string()))
+        );
+        assert_eq!(
+            scalar(oid::TIMETZ, "0000000b8837d280ffffb2a8"),
+            "08:15:30+00"
+        );
+        assert_eq!(
+            scalar(oid::TIMESTAMP, "0002b0ec851d9460"),
+            "2024-01-02 03:04:05.5"
+        );
+        assert_eq!(
+            scalar(oid::TIMESTAMP, "ff1fc63d1bb12000"),
+            "0001-01-01 00:00:00 BC"
+        );
+        assert_eq!(
+            scalar(oid::TIMESTAMPTZ, "0002b0e7e8edbdd0"),
+            "2024-01-01 21:34:05.25+00"
+        );
+    }
+
+    #[test]
+    fn decodes_infinite_dates_and_timestamps() {
+        assert_eq!(scalar(oid::DATE, "7fffffff"), "infinity");
+        assert_eq!(scalar(oid::DATE, "80000000"), "-infinity");
+        assert_eq!(scalar(oid::TIMESTAMP, "7fffffffffffffff"), "infinity");
+        assert_eq!(scalar(oid::TIMESTAMP, "8000000000000000"), "-infinity");
+        assert_eq!(scalar(oid::TIMESTAMPTZ, "7fffffffffffffff"), "infinity");
+    }
+
+    #[test]
+    fn decodes_network_addresses() {
+        assert_eq!(scalar(oid::INET, "02200004c0a80001"), "192.168.0.1");
+        assert_eq!(scalar(oid::INET, "020800040a010203"), "10.1.2.3/8");
+        assert_eq!(scalar(oid::CIDR, "020801040a000000"), "10.0.0.0/8");
+        assert_eq!(
+            scalar(oid::INET, "0380001020010db8000000000000000000000001"),
+            "2001:db8::1"
+        );
+        assert_eq!(
+            scalar(oid::CIDR, "0320011020010db8000000000000000000000000"),
+            "2001:db8::/32"
+        );
+        assert_eq!(scalar(oid::MACADDR, "08002b010203"), "08:00:2b:01:02:03");
+        assert_eq!(
+            scalar(oid::MACADDR8, "08002b0102030405"),
+            "08:00:2b:01:02:03:04:05"
+        );
+    }
+
+    #[test]
+    fn decodes_bit_strings() {
+        assert_eq!(scalar(oid::BIT, "0000000aa0c0"), "1010000011");
+        assert_eq!(scalar(oid::VARBIT, "00000003a0"), "101");
+        assert_eq!(scalar(oid::VARBIT, "00000000"), "");
+    }
+
+    #[test]
+    fn decodes_identifiers() {
+        assert_eq!(
+            scalar(oid::UUID, "a0eebc999c0b4ef8bb6d6bb9bd380a11"),
+            "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11"
+        );
+        assert_eq!(scalar(oid::TID, "0000002a0007"), "(42,7)");
+        assert_eq!(scalar(oid::PG_LSN, "00000016b374d848"), "16/B374D848");
+    }
+
+    #[test]
+    fn decodes_geometric_types() {
+        assert_eq!(
+            scalar(oid::POINT, "3ff8000000000000c000000000000000"),
+            "(1.5,-2)"
+        );
+        assert_eq!(
+            scalar(
+                oid::LINE,
+                "3ff0000000000000bff00000000000000000000000000000"
+            ),
+            "{1,-1,0}"
+        );
+        assert_eq!(
+            scalar(
+                oid::LSEG,
+                "000000000000000000000000000000003ff00000000000003ff0000000000000"
+            ),
+            "[(0,0),(1,1)]"
+        );
+        assert_eq!(
+            scalar(
+                oid::BOX,
+                "4000000000000000400000000000000000000000000000000000000000000000"
+            ),
+            "(2,2),(0,0)"
+        );
+        assert_eq!(
+            scalar(
+                oid::PATH,
+                "0000000003000000000000000000000000000000003ff00000000000003ff0000000000000\
+                 40000000000000000000000000000000"
+            ),
+            "[(0,0),(1,1),(2,0)]"
+        );
+        assert_eq!(
+            scalar(
+                oid::PATH,
+                "0100000002000000000000000000000000000000003ff00000000000003ff0000000000000"
+            ),
+            "((0,0),(1,1))"
+        );
+        assert_eq!(
+            scalar(
+                oid::POLYGON,
+                "00000003000000000000000000000000000000003ff00000000000003ff0000000000000\
+                 3ff00000000000000000000000000000"
+            ),
+            "((0,0),(1,1),(1,0))"
+        );
+        assert_eq!(
+            scalar(
+                oid::CIRCLE,
+                "3ff000000000000040000000000000004008000000000000"
+            ),
+            "<(1,2),3>"
+        );
+    }
+
+    #[test]
+    fn keeps_large_integers_and_special_floats_exact() {
+        let decode = |type_oid, hex| decode_scalar(type_oid, &bytes(hex), &TimeZone::Utc);
+        assert_eq!(
+            decode(oid::INT8, "0020000000000001"),
+            Some(Value::String("9007199254740993".to_string()))
+        );
+        assert_eq!(
+            decode(oid::FLOAT8, "7ff8000000000000"),
+            Some(Value::String("NaN".to_string()))
+        );
+        assert_eq!(scalar(oid::FLOAT8, "3ff8000000000000"), "1.5");
+    }
+
+    #[test]
+    fn decodes_arrays() {
+        let int4s = Decoder::Array(Box::new(Decoder::Scalar(oid::INT4)));
+        assert_eq!(
+            render(
+                &int4s,
+                "00000001000000010000001700000003000000010000000400000001ffffffff0000000400000003"
+            ),
+            "{1,NULL,3}"
+        );
+        assert_eq!(
+            render(
+                &int4s,
+                "000000020000000000000017000000020000000100000002000000010000000400000001\
+                 000000040000000200000004000000030000000400000004"
+            ),
+            "{{1,2},{3,4}}"
+        );
+        assert_eq!(render(&int4s, "000000000000000000000017"), "{}");
+
+        let texts = Decoder::Array(Box::new(Decoder::Scalar(oid::TEXT)));
+        assert_eq!(
+            render(
+                &texts,
+                "000000010000000100000019000000050000000100000003612062000000044e554c4c\
+                 ffffffff0000000371227800000005706c61696e"
+            ),
+            r#"{"a b","NULL",NULL,"q\"x",plain}"#
+        );
+    }
+
+    #[test]
+    fn decodes_bytea_by_style() {
+        assert_eq!(scalar(oid::BYTEA, "0102ff"), "<BLOB 3 bytes>");
+        assert_eq!(render(&Decoder::Bytes, "0102ff"), r"\x0102ff");
+        assert_eq!(render(&Decoder::Bytes, ""), r"\x");
+
+        let byteas = Decoder::Array(Box::new(Decoder::Bytes));
+        assert_eq!(
+            render(
+                &byteas,
+                "0000000100000001000000110000000300000001000000010100000000ffffffff"
+            ),
+            r#"{"\\x01","\\x",NULL}"#
+        );
+    }
+
+    #[test]
+    fn decodes_ranges() {
+        let range = |element| Decoder::Range(Box::new(Decoder::Scalar(element)));
+        assert_eq!(
+            render(&range(oid::INT4), "020000000400000001000000040000000a"),
+            "[1,10)"
+        );
+        assert_eq!(render(&range(oid::INT4), "01"), "empty");
+        assert_eq!(render(&range(oid::INT4), "080000000400000006"), "(,6)");
+        assert_eq!(
+            render(
+                &range(oid::NUMERIC),
+                "040000000c0002000000000001000113880000000c0002000000000002000209c4"
+            ),
+            "(1.5,2.25]"
+        );
+        assert_eq!(
+            render(
+                &range(oid::TIMESTAMP),
+                "02000000080002b0d5d4e94000000000080002b0f401ac5000"
+            ),
+            r#"["2024-01-01 00:00:00","2024-01-02 12:00:00")"#
+        );
+        assert_eq!(
+            render(&range(oid::DATE), "02000000040000223e000000047fffffff"),
+            "[2024-01-01,infinity)"
+        );
+    }
+
+    #[test]
+    fn decodes_multiranges() {
+        let multirange = Decoder::Multirange(Box::new(Decoder::Scalar(oid::INT4)));
+        assert_eq!(
+            render(
+                &multirange,
+                "00000002000000110200000004000000010000000400000003\
+                 000000110200000004000000050000000400000008"
+            ),
+            "{[1,3),[5,8)}"
+        );
+        assert_eq!(render(&multirange, "00000000"), "{}");
+    }
+
+    #[test]
+    fn decodes_composites() {
+        let pair = Decoder::Composite(vec![
+            Decoder::Scalar(oid::INT4),
+            Decoder::Scalar(oid::TEXT),
+            Decoder::Scalar(oid::NUMERIC),
+        ]);
+        assert_eq!(
+            render(
+                &pair,
+                "000000030000001700000004000000010000001900000003612062000006a4ffffffff"
+            ),
+            r#"(1,"a b",)"#
+        );
+        assert_eq!(
+            render(
+                &pair,
+                "000000030000001700000004fffffffe00000019000000087361792022686922\
+                 000006a40000000c000200000000000100011388"
+            ),
+            r#"(-2,"say ""hi""",1.5)"#
+        );
+        assert_eq!(
+            render(
+                &pair,
+                "000000030000001700000004000000000000001900000000000006a4000000080000000000000000"
+            ),
+            r#"(0,"",0)"#
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Trial runs of data-changing statements.
+//!
+//! The statement runs for real, inside a transaction of its own (or under a
+//! savepoint when the tab already has one open) that is always rolled back.
+//! Side effects PostgreSQL does not undo on rollback stay: sequences advance,
+//! and anything a trigger sends outside the database is sent.
+
+use serde_json::Value;
+use sqlx::postgres::PgPool;
+use tauri::State;
+
+use super::connection::AppState;
+use super::decode::DisplayZone;
+use super::edit::EditBatch;
+use super::error::CommandError;
+use super::jobs::{finish_job, start_job, JobConnection};
+use super::notices::ServerNotice;
+use super::params::QueryParameter;
+use super::query::{prepare_execution, ResultColumn};
+use super::script::run_statement;
+use super::session::{tab_transaction_state, TransactionState};
+use super::sql::{statement_kind, with_returning, SqlStatement, StatementKind};
+use super::timeouts::Timeouts;
+
+const DEFAULT_SAMPLE_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunOptions {
+    /// Most affected rows returned.
+    pub sample_size: Option<usize>,
+    /// Zone TIMESTAMPTZ and TIMETZ values are shown in.
+    #[serde(default)]
+    pub display_zone: DisplayZone,
+    /// Client-chosen id that `cancel_query` can refer to while this runs.
+    pub job_id: Option<String>,
+    /// Editor tab the statement was run from; it runs in the tab's session.
+    pub tab_id: Option<String>,
+    /// Values for `$1` or `:name` placeholders.
+    pub parameters: Option<Vec<QueryParameter>>,
+    /// Limits for this execution only, in place of the connection's.
+    pub timeouts: Option<Timeouts>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunResult {
+    pub statement_kind: StatementKind,
+    /// Rows the statement would insert, update or delete.
+    pub rows_affected: u64,
+    /// Columns of the sample: the statement's own `RETURNING` list, or every
+    /// column of the target table.
+    pub columns: Vec<ResultColumn>,
+    /// The first affected rows, as they would be stored; deleted rows as they
+    /// were before.
+    pub sample: Vec<Vec<Value>>,
+    /// More rows were affected than the sample holds.
+    pub sample_truncated: bool,
+    pub execution_time_ms: u64,
+    /// Transaction state of the tab's session afterwards, unchanged by the run.
+    pub transaction_state: TransactionState,
+    /// Notices the statement raised, such as those of the triggers it fired.
+    pub notices: Vec<ServerNotice>,
+}
+
+/// Runs an `INSERT`, `UPDATE`, `DELETE` or `MERGE` and rolls it back, reporting
+/// how many rows it would affect and a sample of them.
+///
+/// The statement gets `RETURNING *` unless it has a `RETURNING` clause already.
+/// `MERGE` is left as written, since it only accepts `RETURNING` from
+/// PostgreSQL 17 on; without one its sample is empty.
+#[tauri::command]
+pub async fn dry_run_query(
+    query: String,
+    options: Option<DryRunOptions>,
+    state: State<'_, AppState>,
+) -> Result<DryRunResult, CommandError> {
+    let options = options.unwrap_or_default();
+    let kind = statement_kind(&query);
+    let sql = match kind {
+        StatementKind::Insert | StatementKind::Update | StatementKind::Delete => {
+            with_returning(&query)
+        }
+        StatementKind::Merge => query.clone(),
+        _ => return Err("Only INSERT, UPDATE, DELETE and MERGE statements can be dry-run".into()),
+    };
+    let job_id = options
+        .job_id
+        .clone()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let pool = state.pool().await?;
+    let limits = state.timeouts().await.merged(options.timeouts);
+    let mut conn = start_job(&state, &job_id, options.tab_id.clone(), &query).await?;
+
+    let outcome = dry_run(&mut conn, &pool, &sql, &options).await;
+    drop(conn);
+    finish_job(&state, &job_id).await;
+
+    let mut result = outcome.map_err(|e| limits.classify(e))?;
+    result.transaction_state = tab_transaction_state(&state, options.tab_id.as_deref()).await;
+    Ok(result)
+}
+
+async fn dry_run(
+    conn: &mut JobConnection,
+    pool: &PgPool,
+    sql: &str,
+    options: &DryRunOptions,
+) -> Result<DryRunResult, CommandError> {
+    let sample_size = options.sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE);
+    let (sql, binds, saved, source) =
+        prepare_execution(conn, sql, options.parameters.as_deref(), options.timeouts).await?;
+
+    let batch = match EditBatch::begin(conn).await {
+        Ok(batch) => batch,
+        Err(e) => {
+            if let Some(saved) = saved {
+                saved.restore(conn).await;
+            }
+            return Err(e);
+        }
+    };
+    let statement = SqlStatement {
+        text: sql,
+        offset: 0,
+    };
+    let result = run_statement(
+        conn,
+        pool,
+        statement,
+        binds,
+        sample_size,
+        options.display_zone,
+    )
+    .await;
+    let rolled_back = batch.finish(conn, false).await;
+    if let Some(saved) = saved {
+        saved.restore(conn).await;
+    }
+
+    if let Some(e) = result.error {
+        return Err(e.at_source(&source).with_notices(result.notices));
+    }
+    rolled_back?;
+
+    Ok(DryRunResult {
+        statement_kind: result.statement_kind,
+        rows_affected: result.rows_affected.unwrap_or_default(),
+        columns: result.columns,
+        sample: result.rows,
+        sample_truncated: result.row_limit_reached,
+        execution_time_ms: result.execution_time_ms,
+        transaction_state: TransactionState::Idle,
+        notices: result.notices,
+    })
+}
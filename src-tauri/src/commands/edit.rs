@@ -0,0 +1,853 @@
+//! Writes changes made in the result grid back to the table the rows came from:
+//! edited cells, new and duplicated rows, and deletions.
+//!
+//! Rows are identified by the table's primary key, found through the column
+//! origins in the result's `RowDescription`. Values travel as text and are cast
+//! to each column's declared type on the server, so any type PostgreSQL can
+//! parse can be edited.
+
+use serde_json::Value;
+use sqlx::postgres::types::Oid;
+use sqlx::postgres::{PgConnection, PgPool};
+use sqlx::{Executor, Row};
+use tauri::State;
+
+use super::connection::AppState;
+use super::decode::{value_to_text, DisplayZone, RowDecoder, ValueStyle};
+use super::error::CommandError;
+use super::jobs::{finish_job, start_job, JobConnection};
+use super::query::ResultColumn;
+use super::session::{tab_transaction_state, TransactionState};
+use super::sql::quote_ident;
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditOptions {
+    /// Zone refreshed TIMESTAMPTZ and TIMETZ values are shown in.
+    #[serde(default)]
+    pub display_zone: DisplayZone,
+    /// Editor tab the result belongs to; edits run in its session.
+    pub tab_id: Option<String>,
+}
+
+/// New values for one row of a result set.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowChange {
+    /// Position of the row in the grid, echoed back in its outcome.
+    pub row_index: usize,
+    /// The row as the grid received it, one value per result column.
+    pub original: Vec<Value>,
+    pub cells: Vec<CellChange>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CellChange {
+    /// Index of the result column being changed.
+    pub column_index: usize,
+    /// New value in PostgreSQL's text format; `None` sets NULL.
+    pub value: Option<String>,
+}
+
+/// One change to a table made from the grid.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RowOperation {
+    Update(RowChange),
+    /// A new row; columns without a value take their defaults.
+    #[serde(rename_all = "camelCase")]
+    Insert {
+        row_index: usize,
+        #[serde(default)]
+        cells: Vec<CellChange>,
+    },
+    /// A copy of a stored row with `cells` overriding copied values. Generated
+    /// and identity columns, and key columns with a default, get fresh values.
+    #[serde(rename_all = "camelCase")]
+    Duplicate {
+        row_index: usize,
+        original: Vec<Value>,
+        #[serde(default)]
+        cells: Vec<CellChange>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Delete {
+        row_index: usize,
+        original: Vec<Value>,
+    },
+}
+
+impl RowOperation {
+    fn row_index(&self) -> usize {
+        match self {
+            RowOperation::Update(change) => change.row_index,
+            RowOperation::Insert { row_index, .. }
+            | RowOperation::Duplicate { row_index, .. }
+            | RowOperation::Delete { row_index, .. } => *row_index,
+        }
+    }
+
+    fn cells(&self) -> &[CellChange] {
+        match self {
+            RowOperation::Update(change) => &change.cells,
+            RowOperation::Insert { cells, .. } | RowOperation::Duplicate { cells, .. } => cells,
+            RowOperation::Delete { .. } => &[],
+        }
+    }
+
+    /// The row as the grid received it; new rows have none.
+    fn original(&self) -> &[Value] {
+        match self {
+            RowOperation::Update(change) => &change.original,
+            RowOperation::Duplicate { original, .. } | RowOperation::Delete { original, .. } => {
+                original
+            }
+            RowOperation::Insert { .. } => &[],
+        }
+    }
+
+    /// Whether the operation works on whole rows rather than single cells,
+    /// which needs results from one table only.
+    fn whole_row(&self) -> bool {
+        !matches!(self, RowOperation::Update(_))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RowStatus {
+    Applied,
+    /// The row was changed or deleted by someone else since it was read.
+    Conflict,
+    Failed,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowOutcome {
+    pub row_index: usize,
+    pub status: RowStatus,
+    /// The row as stored after the change, one value per result column;
+    /// `None` for deleted rows.
+    pub row: Option<Vec<Value>>,
+    pub error: Option<CommandError>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyChangesResult {
+    pub rows: Vec<RowOutcome>,
+    /// Whether the changes were kept. They are all or nothing: a single
+    /// conflict or failure rolls every row back.
+    pub applied: bool,
+    pub transaction_state: TransactionState,
+}
+
+/// A column of the table being edited.
+struct TableColumn {
+    name: String,
+    /// Declared type, modifier included, ready to cast to.
+    data_type: String,
+    is_key: bool,
+    is_json: bool,
+    /// Whether stored values can be checked against what the grid shows.
+    /// Byte strings are only shown as a size and `json` keeps its input text.
+    comparable: bool,
+    generated: bool,
+    /// `GENERATED ALWAYS AS IDENTITY`, which only takes a value when overridden.
+    identity_always: bool,
+    /// Whether an insert leaving the column out gives it a value of its own.
+    has_default: bool,
+}
+
+/// The table a result set's rows come from, as far as editing needs to know.
+struct EditTarget {
+    /// Schema-qualified where needed and quoted, ready for SQL.
+    table: String,
+    /// Every column of the table, in order.
+    columns: Vec<TableColumn>,
+    /// Index into `columns` of the table column behind each result column;
+    /// `None` for columns that were computed or came from another table.
+    origins: Vec<Option<usize>>,
+    /// Result columns holding the primary key.
+    key: Vec<usize>,
+}
+
+impl EditTarget {
+    /// Looks up `table_oid` and matches its columns to the result's.
+    async fn resolve(
+        conn: &mut PgConnection,
+        table_oid: u32,
+        columns: &[ResultColumn],
+    ) -> Result<Self, CommandError> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                a.attrelid::regclass::text AS table_name,
+                a.attnum,
+                a.attname,
+                format_type(a.atttypid, a.atttypmod) AS data_type,
+                COALESCE(a.attnum = ANY(i.indkey), false) AS is_key,
+                a.atttypid IN ('json'::regtype, 'jsonb'::regtype) AS is_json,
+                a.atttypid NOT IN ('json'::regtype, 'json[]'::regtype, 'bytea'::regtype, 'bytea[]'::regtype)
+                    AS comparable,
+                a.attgenerated <> '' AS generated,
+                a.attidentity = 'a' AS identity_always,
+                a.atthasdef OR a.attidentity <> '' AS has_default
+            FROM pg_attribute a
+            LEFT JOIN pg_index i ON i.indrelid = a.attrelid AND i.indisprimary
+            WHERE a.attrelid = $1 AND a.attnum > 0 AND NOT a.attisdropped
+            ORDER BY a.attnum
+            "#,
+        )
+        .bind(Oid(table_oid))
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| CommandError::sqlx("Failed to inspect table", e))?;
+
+        let Some(first) = rows.first() else {
+            return Err("The table these rows came from no longer exists".into());
+        };
+        let table: String = first.get("table_name");
+
+        let mut table_columns = Vec::with_capacity(rows.len());
+        let mut origins: Vec<Option<usize>> = columns.iter().map(|_| None).collect();
+        for row in &rows {
+            let attnum: i16 = row.get("attnum");
+            if let Some(index) = columns
+                .iter()
+                .position(|c| c.table_oid == Some(table_oid) && c.column_number == Some(attnum))
+            {
+                origins[index] = Some(table_columns.len());
+            }
+
+            table_columns.push(TableColumn {
+                name: row.get("attname"),
+                data_type: row.get("data_type"),
+                is_key: row.get("is_key"),
+                is_json: row.get("is_json"),
+                comparable: row.get("comparable"),
+                generated: row.get("generated"),
+                identity_always: row.get("identity_always"),
+                has_default: row.get("has_default"),
+            });
+        }
+
+        let key_columns = table_columns.iter().filter(|c| c.is_key).count();
+        if key_columns == 0 {
+            return Err(
+                format!("{} has no primary key, so its rows cannot be edited", table).into(),
+            );
+        }
+        let key: Vec<usize> = origins
+            .iter()
+            .enumerate()
+            .filter(|(_, origin)| origin.is_some_and(|i| table_columns[i].is_key))
+            .map(|(index, _)| index)
+            .collect();
+        if key.len() < key_columns {
+            return Err(format!(
+                "Include every primary key column of {} in the query to edit its rows",
+                table
+            )
+            .into());
+        }
+
+        Ok(Self {
+            table,
+            columns: table_columns,
+            origins,
+            key,
+        })
+    }
+
+    fn column(&self, index: usize) -> Result<&TableColumn, CommandError> {
+        self.origins
+            .get(index)
+            .and_then(|origin| origin.map(|i| &self.columns[i]))
+            .ok_or_else(|| format!("Column {} does not come from {}", index + 1, self.table).into())
+    }
+
+    /// `RETURNING` list giving back every result column that comes from the table.
+    fn returning(&self) -> String {
+        self.origins
+            .iter()
+            .flatten()
+            .map(|&i| quote_ident(&self.columns[i].name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// `WHERE` conditions matching `original` by primary key, binding its values.
+    ///
+    /// A row that does not have a value for every result column is refused
+    /// rather than matched on part of its key, which could hit other rows.
+    fn key_condition(
+        &self,
+        original: &[Value],
+        binds: &mut Vec<Option<String>>,
+    ) -> Result<Vec<String>, CommandError> {
+        if original.len() != self.origins.len() {
+            return Err(format!(
+                "The row has {} values but the result has {} columns",
+                original.len(),
+                self.origins.len()
+            )
+            .into());
+        }
+
+        self.key
+            .iter()
+            .map(|&index| {
+                let column = self.column(index)?;
+                binds.push(original_text(&original[index], column));
+                Ok(format!(
+                    "{} = ${}::{}",
+                    quote_ident(&column.name),
+                    binds.len(),
+                    column.data_type
+                ))
+            })
+            .collect()
+    }
+}
+
+/// The text form of a value as the grid received it.
+fn original_text(value: &Value, column: &TableColumn) -> Option<String> {
+    match value {
+        Value::Null => None,
+        _ if column.is_json => Some(value.to_string()),
+        _ => value_to_text(value),
+    }
+}
+
+/// The table the operations change.
+///
+/// Cell edits only need the changed columns to share a table. Inserts,
+/// duplicates and deletions work on whole rows, so every column read from a
+/// table must come from the same one.
+fn source_table(
+    columns: &[ResultColumn],
+    operations: &[RowOperation],
+) -> Result<u32, CommandError> {
+    let whole_rows = operations.iter().any(RowOperation::whole_row);
+    let mut table_oid = None;
+
+    for index in operations
+        .iter()
+        .flat_map(|op| op.cells().iter().map(|cell| cell.column_index))
+    {
+        let oid = columns
+            .get(index)
+            .and_then(|c| c.table_oid.filter(|&oid| oid != 0))
+            .ok_or("Only columns read straight from a table can be edited")?;
+        if table_oid.is_some_and(|t| t != oid) {
+            return Err("Changes must all be to columns of the same table".into());
+        }
+        table_oid = Some(oid);
+    }
+
+    if whole_rows {
+        for oid in columns
+            .iter()
+            .filter_map(|c| c.table_oid.filter(|&oid| oid != 0))
+        {
+            if table_oid.is_some_and(|t| t != oid) {
+                return Err(
+                    "Rows can only be added or deleted in results from a single table".into(),
+                );
+            }
+            table_oid = Some(oid);
+        }
+    }
+
+    table_oid.ok_or_else(|| "There are no changes to apply".into())
+}
+
+/// A group of edits applied atomically: in a transaction of its own, or under
+/// a savepoint when the tab already has a transaction open, which then decides
+/// whether the edits are committed.
+pub(crate) struct EditBatch {
+    nested: bool,
+}
+
+impl EditBatch {
+    pub(crate) async fn begin(conn: &mut JobConnection) -> Result<Self, CommandError> {
+        let nested = match conn.transaction_state() {
+            TransactionState::Idle => false,
+            TransactionState::InTransaction => true,
+            TransactionState::Failed => {
+                return Err("The tab's transaction has failed; roll it back first".into())
+            }
+        };
+        let sql = if nested {
+            "SAVEPOINT dbee_batch"
+        } else {
+            "BEGIN"
+        };
+        (&mut **conn)
+            .execute(sql)
+            .await
+            .map_err(|e| CommandError::sqlx("Failed to start transaction", e))?;
+        Ok(Self { nested })
+    }
+
+    /// Keeps or discards everything done since [`Self::begin`].
+    pub(crate) async fn finish(
+        self,
+        conn: &mut JobConnection,
+        keep: bool,
+    ) -> Result<(), CommandError> {
+        let sql = match (self.nested, keep) {
+            (false, true) => "COMMIT",
+            (false, false) => "ROLLBACK",
+            (true, true) => "RELEASE SAVEPOINT dbee_batch",
+            (true, false) => "ROLLBACK TO SAVEPOINT dbee_batch; RELEASE SAVEPOINT dbee_batch",
+        };
+        (&mut **conn)
+            .execute(sql)
+            .await
+            .map_err(|e| CommandError::sqlx("Failed to finish transaction", e))?;
+        Ok(())
+    }
+
+    /// Runs one row's statement under its own savepoint, so a failure is
+    /// reported for that row without aborting the rest of the batch. The outer
+    /// error is for the savepoint itself, after which the batch cannot go on.
+    pub(crate) async fn row(
+        conn: &mut PgConnection,
+        pool: &PgPool,
+        sql: &str,
+        binds: Vec<Option<String>>,
+        display_zone: DisplayZone,
+    ) -> Result<Result<Option<Vec<Value>>, CommandError>, CommandError> {
+        (&mut *conn)
+            .execute("SAVEPOINT dbee_row")
+            .await
+            .map_err(|e| CommandError::sqlx("Failed to set savepoint", e))?;
+
+        let outcome = fetch_row(conn, pool, sql, binds, display_zone).await;
+
+        let release = match outcome {
+            Ok(_) => "RELEASE SAVEPOINT dbee_row",
+            Err(_) => "ROLLBACK TO SAVEPOINT dbee_row; RELEASE SAVEPOINT dbee_row",
+        };
+        (&mut *conn)
+            .execute(release)
+            .await
+            .map_err(|e| CommandError::sqlx("Failed to release savepoint", e))?;
+
+        Ok(outcome)
+    }
+}
+
+/// Runs `sql` and decodes the row it returns, if any.
+async fn fetch_row(
+    conn: &mut PgConnection,
+    pool: &PgPool,
+    sql: &str,
+    binds: Vec<Option<String>>,
+    display_zone: DisplayZone,
+) -> Result<Option<Vec<Value>>, CommandError> {
+    let statement = conn
+        .prepare(sql)
+        .await
+        .map_err(|e| CommandError::sqlx("Query error", e))?;
+    let columns = sqlx::Statement::columns(&statement);
+    let decoder = RowDecoder::new(conn, columns, display_zone, ValueStyle::Grid).await;
+
+    let mut query = sqlx::Statement::query(&statement);
+    for value in binds {
+        query = query.bind(value);
+    }
+    let Some(row) = query
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| CommandError::sqlx("Query error", e))?
+    else {
+        return Ok(None);
+    };
+
+    let mut undecoded = Vec::new();
+    let mut rows = vec![decoder.decode_row(&row, 0, &mut undecoded)];
+    decoder.render_undecoded(pool, &mut rows, undecoded).await;
+    Ok(rows.pop())
+}
+
+/// Rebuilds a grid row from `original` and the table's values after a change.
+/// Columns from elsewhere keep their original values, or are NULL in new rows.
+fn refreshed_row(target: &EditTarget, original: &[Value], returned: Vec<Value>) -> Vec<Value> {
+    let mut returned = returned.into_iter();
+    target
+        .origins
+        .iter()
+        .enumerate()
+        .map(|(index, origin)| match origin {
+            Some(_) => returned.next().unwrap_or(Value::Null),
+            None => original.get(index).cloned().unwrap_or(Value::Null),
+        })
+        .collect()
+}
+
+/// The `UPDATE` for one row: changed columns are set, and the row must still
+/// match its primary key and the original values of those columns.
+fn update_statement(
+    target: &EditTarget,
+    change: &RowChange,
+) -> Result<(String, Vec<Option<String>>), CommandError> {
+    let mut binds = Vec::new();
+    let mut assignments = Vec::new();
+    let mut checks = Vec::new();
+
+    for cell in &change.cells {
+        let column = target.column(cell.column_index)?;
+        if column.generated {
+            return Err(format!("{} is a generated column", column.name).into());
+        }
+        let name = quote_ident(&column.name);
+
+        binds.push(cell.value.clone());
+        assignments.push(format!("{} = ${}::{}", name, binds.len(), column.data_type));
+
+        if column.comparable {
+            let original = change
+                .original
+                .get(cell.column_index)
+                .unwrap_or(&Value::Null);
+            binds.push(original_text(original, column));
+            checks.push(format!(
+                "{}::text IS NOT DISTINCT FROM ${}::{}::text",
+                name,
+                binds.len(),
+                column.data_type
+            ));
+        }
+    }
+
+    let mut conditions = target.key_condition(&change.original, &mut binds)?;
+    conditions.extend(checks);
+
+    let sql = format!(
+        "UPDATE {} SET {} WHERE {} RETURNING {}",
+        target.table,
+        assignments.join(", "),
+        conditions.join(" AND "),
+        target.returning()
+    );
+    Ok((sql, binds))
+}
+
+/// The `INSERT` for a new row, leaving out columns without a value so they take
+/// their defaults.
+fn insert_statement(
+    target: &EditTarget,
+    cells: &[CellChange],
+) -> Result<(String, Vec<Option<String>>), CommandError> {
+    let mut binds = Vec::new();
+    let mut names = Vec::new();
+    let mut values = Vec::new();
+    let mut overriding = false;
+
+    for cell in cells {
+        let column = target.column(cell.column_index)?;
+        if column.generated {
+            return Err(format!("{} is a generated column", column.name).into());
+        }
+        overriding |= column.identity_always;
+
+        binds.push(cell.value.clone());
+        names.push(quote_ident(&column.name));
+        values.push(format!("${}::{}", binds.len(), column.data_type));
+    }
+
+    let sql = if names.is_empty() {
+        format!(
+            "INSERT INTO {} DEFAULT VALUES RETURNING {}",
+            target.table,
+            target.returning()
+        )
+    } else {
+        format!(
+            "INSERT INTO {} ({}){} VALUES ({}) RETURNING {}",
+            target.table,
+            names.join(", "),
+            if overriding {
+                " OVERRIDING SYSTEM VALUE"
+            } else {
+                ""
+            },
+            values.join(", "),
+            target.returning()
+        )
+    };
+    Ok((sql, binds))
+}
+
+/// The `INSERT ... SELECT` copying a stored row, so columns the grid does not
+/// show, or shows only in part, are copied exactly.
+fn duplicate_statement(
+    target: &EditTarget,
+    original: &[Value],
+    cells: &[CellChange],
+) -> Result<(String, Vec<Option<String>>), CommandError> {
+    let mut binds = Vec::new();
+    let mut overrides = std::collections::HashMap::new();
+    for cell in cells {
+        let column = target.column(cell.column_index)?;
+        if column.generated {
+            return Err(format!("{} is a generated column", column.name).into());
+        }
+        overrides.insert(column.name.as_str(), cell.value.clone());
+    }
+
+    let mut names = Vec::new();
+    let mut values = Vec::new();
+    let mut overriding = false;
+    for column in &target.columns {
+        let name = quote_ident(&column.name);
+        if let Some(value) = overrides.remove(column.name.as_str()) {
+            binds.push(value);
+            values.push(format!("${}::{}", binds.len(), column.data_type));
+            overriding |= column.identity_always;
+        } else if column.generated
+            || column.has_default && (column.is_key || column.identity_always)
+        {
+            continue;
+        } else {
+            values.push(name.clone());
+        }
+        names.push(name);
+    }
+
+    let conditions = target.key_condition(original, &mut binds)?;
+    let sql = format!(
+        "INSERT INTO {table} ({}){} SELECT {} FROM {table} WHERE {} RETURNING {}",
+        names.join(", "),
+        if overriding {
+            " OVERRIDING SYSTEM VALUE"
+        } else {
+            ""
+        },
+        values.join(", "),
+        conditions.join(" AND "),
+        target.returning(),
+        table = target.table,
+    );
+    Ok((sql, binds))
+}
+
+/// The `DELETE` for a stored row, matched by primary key.
+fn delete_statement(
+    target: &EditTarget,
+    original: &[Value],
+) -> Result<(String, Vec<Option<String>>), CommandError> {
+    let mut binds = Vec::new();
+    let conditions = target.key_condition(original, &mut binds)?;
+    let sql = format!(
+        "DELETE FROM {} WHERE {} RETURNING {}",
+        target.table,
+        conditions.join(" AND "),
+        target.returning()
+    );
+    Ok((sql, binds))
+}
+
+fn operation_statement(
+    target: &EditTarget,
+    operation: &RowOperation,
+) -> Result<(String, Vec<Option<String>>), CommandError> {
+    match operation {
+        RowOperation::Update(change) => update_statement(target, change),
+        RowOperation::Insert { cells, .. } => insert_statement(target, cells),
+        RowOperation::Duplicate {
+            original, cells, ..
+        } => duplicate_statement(target, original, cells),
+        RowOperation::Delete { original, .. } => delete_statement(target, original),
+    }
+}
+
+/// Writes edited cells back to their table in one transaction.
+///
+/// Every row is updated by primary key, and only if the changed columns still
+/// hold the values the grid showed; a row that no longer matches is reported as
+/// a conflict. Unless every row applies cleanly, nothing is kept.
+#[tauri::command]
+pub async fn apply_row_changes(
+    columns: Vec<ResultColumn>,
+    changes: Vec<RowChange>,
+    options: Option<EditOptions>,
+    state: State<'_, AppState>,
+) -> Result<ApplyChangesResult, CommandError> {
+    let operations = changes.into_iter().map(RowOperation::Update).collect();
+    apply_row_operations(columns, operations, options, state).await
+}
+
+/// Applies inserts, duplicates, updates and deletions as one transaction,
+/// returning each row as stored afterwards so generated keys and defaults show
+/// up straight away. Unless every operation succeeds, nothing is kept.
+#[tauri::command]
+pub async fn apply_row_operations(
+    columns: Vec<ResultColumn>,
+    operations: Vec<RowOperation>,
+    options: Option<EditOptions>,
+    state: State<'_, AppState>,
+) -> Result<ApplyChangesResult, CommandError> {
+    let options = options.unwrap_or_default();
+    let table_oid = source_table(&columns, &operations)?;
+
+    let pool = state.pool().await?;
+    let job_id = uuid::Uuid::new_v4().to_string();
+    let mut conn = start_job(
+        &state,
+        &job_id,
+        options.tab_id.clone(),
+        "-- apply grid edits",
+    )
+    .await?;
+
+    let outcome = apply_operations(
+        &mut conn,
+        &pool,
+        table_oid,
+        &columns,
+        &operations,
+        options.display_zone,
+    )
+    .await;
+    drop(conn);
+    finish_job(&state, &job_id).await;
+
+    let (rows, applied) = outcome?;
+    Ok(ApplyChangesResult {
+        rows,
+        applied,
+        transaction_state: tab_transaction_state(&state, options.tab_id.as_deref()).await,
+    })
+}
+
+async fn apply_operations(
+    conn: &mut JobConnection,
+    pool: &PgPool,
+    table_oid: u32,
+    columns: &[ResultColumn],
+    operations: &[RowOperation],
+    display_zone: DisplayZone,
+) -> Result<(Vec<RowOutcome>, bool), CommandError> {
+    let target = EditTarget::resolve(conn, table_oid, columns).await?;
+    let batch = EditBatch::begin(conn).await?;
+
+    let outcomes = async {
+        let mut outcomes = Vec::with_capacity(operations.len());
+        for operation in operations {
+            let result = match operation_statement(&target, operation) {
+                Ok((sql, binds)) => EditBatch::row(conn, pool, &sql, binds, display_zone).await?,
+                Err(e) => Err(e),
+            };
+
+            let row_index = operation.row_index();
+            outcomes.push(match result {
+                Ok(Some(returned)) => RowOutcome {
+                    row_index,
+                    status: RowStatus::Applied,
+                    row: match operation {
+                        RowOperation::Delete { .. } => None,
+                        _ => Some(refreshed_row(&target, operation.original(), returned)),
+                    },
+                    error: None,
+                },
+                Ok(None) => RowOutcome {
+                    row_index,
+                    status: RowStatus::Conflict,
+                    row: None,
+                    error: None,
+                },
+                Err(e) => RowOutcome {
+                    row_index,
+                    status: RowStatus::Failed,
+                    row: None,
+                    error: Some(e),
+                },
+            });
+        }
+        Ok::<_, CommandError>(outcomes)
+    }
+    .await;
+
+    // Whatever happened above, the batch is ended here so a failure never
+    // leaves its transaction or savepoint open on the tab's session.
+    let applied = outcomes
+        .as_ref()
+        .is_ok_and(|outcomes| outcomes.iter().all(|o| o.status == RowStatus::Applied));
+    let finished = batch.finish(conn, applied).await;
+    let outcomes = outcomes?;
+    finished?;
+
+    Ok((outcomes, applied))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn column(name: &str, data_type: &str, is_key: bool) -> TableColumn {
+        TableColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            is_key,
+            is_json: false,
+            comparable: true,
+            generated: false,
+            identity_always: false,
+            has_default: false,
+        }
+    }
+
+    /// `orders (tenant, id, note)` keyed by `(tenant, id)`, read as
+    /// `SELECT id, tenant, note, now()`.
+    fn orders() -> EditTarget {
+        EditTarget {
+            table: "orders".to_string(),
+            columns: vec![
+                column("tenant", "integer", true),
+                column("id", "integer", true),
+                column("note", "text", false),
+            ],
+            origins: vec![Some(1), Some(0), Some(2), None],
+            key: vec![0, 1],
+        }
+    }
+
+    #[test]
+    fn matches_every_key_column() {
+        let original = [json!(7), json!(2), json!("a"), json!("2024-01-01")];
+
+        let (sql, binds) = delete_statement(&orders(), &original).unwrap();
+
+        assert_eq!(
+            sql,
+            r#"DELETE FROM orders WHERE "id" = $1::integer AND "tenant" = $2::integer RETURNING "id", "tenant", "note""#
+        );
+        assert_eq!(binds, vec![Some("7".to_string()), Some("2".to_string())]);
+    }
+
+    #[test]
+    fn refuses_rows_missing_values() {
+        let target = orders();
+        let short = [json!(7)];
+        let change = RowChange {
+            row_index: 0,
+            original: short.to_vec(),
+            cells: vec![CellChange {
+                column_index: 2,
+                value: Some("b".to_string()),
+            }],
+        };
+
+        assert!(delete_statement(&target, &short).is_err());
+        assert!(delete_statement(&target, &[]).is_err());
+        assert!(update_statement(&target, &change).is_err());
+        assert!(duplicate_statement(&target, &short, &[]).is_err());
+    }
+}
@@ -3,6 +3,8 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+use super::error::CommandError;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PersistedTab {
     pub id: String,
@@ -60,7 +62,7 @@ fn write_editor_tabs_file(file: &EditorTabsFile) -> Result<(), String> {
 #[tauri::command]
 pub async fn load_editor_tabs(
     connection_id: String,
-) -> Result<Option<PersistedEditorState>, String> {
+) -> Result<Option<PersistedEditorState>, CommandError> {
     let file = read_editor_tabs_file()?;
     Ok(file.connections.get(&connection_id).cloned())
 }
@@ -69,8 +71,9 @@ pub async fn load_editor_tabs(
 pub async fn save_editor_tabs(
     connection_id: String,
     state: PersistedEditorState,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let mut file = read_editor_tabs_file()?;
     file.connections.insert(connection_id, state);
-    write_editor_tabs_file(&file)
+    write_editor_tabs_file(&file)?;
+    Ok(())
 }
@@ -0,0 +1,232 @@
+use serde::ser::{Serialize, Serializer};
+use sqlx::postgres::{PgDatabaseError, PgErrorPosition, PgSeverity};
+
+use super::notices::ServerNotice;
+use super::sql::{Destructive, SourceMap};
+use super::timeouts::TimeoutKind;
+
+/// The error every command returns.
+///
+/// It serializes to an object with a `kind` and a `message`; errors raised by
+/// the server also carry the fields of [`DatabaseError`], so the editor can point
+/// at the failing position and show the server's hint.
+#[derive(Debug, thiserror::Error)]
+pub enum CommandError {
+    #[error("Not connected to a database")]
+    NotConnected,
+    /// An error reported by PostgreSQL.
+    #[error("{0}")]
+    Database(Box<DatabaseError>),
+    /// Tabs, by id, whose transactions would be lost by the requested action.
+    #[error("Uncommitted transactions are open in {} tab(s)", .0.len())]
+    OpenTransactions(Vec<String>),
+    /// Statements this connection only runs once the user confirms them; sending
+    /// them again with `token` goes ahead.
+    #[error("This would run {}; confirm to continue", describe_all(.reasons))]
+    ConfirmationRequired {
+        token: String,
+        reasons: Vec<Destructive>,
+    },
+    /// A statement ran into one of the connection's time limits; a timeout is
+    /// reported apart from a cancellation the user asked for.
+    #[error("{}", .kind.message(*.seconds))]
+    TimedOut {
+        kind: TimeoutKind,
+        seconds: Option<u64>,
+    },
+    /// Anything else, already described for the user.
+    #[error("{0}")]
+    Failed(String),
+}
+
+fn describe_all(reasons: &[Destructive]) -> String {
+    reasons
+        .iter()
+        .map(|reason| reason.describe())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The fields of a PostgreSQL `ErrorResponse` worth showing.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseError {
+    /// SQLSTATE, e.g. `42P01`.
+    pub code: String,
+    /// `ERROR`, `FATAL` or `PANIC`.
+    pub severity: String,
+    pub message: String,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    /// 1-based character position in the statement that failed.
+    pub position: Option<usize>,
+    /// Error context, such as the PL/pgSQL line being run.
+    pub context: Option<String>,
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub column: Option<String>,
+    pub data_type: Option<String>,
+    pub constraint: Option<String>,
+    /// Notices the statement raised before it failed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notices: Vec<ServerNotice>,
+}
+
+impl std::fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<&PgDatabaseError> for DatabaseError {
+    fn from(e: &PgDatabaseError) -> Self {
+        Self {
+            code: e.code().to_string(),
+            severity: severity_name(e.severity()).to_string(),
+            message: e.message().to_string(),
+            detail: e.detail().map(str::to_string),
+            hint: e.hint().map(str::to_string),
+            position: match e.position() {
+                Some(PgErrorPosition::Original(position)) => Some(position),
+                _ => None,
+            },
+            context: e.r#where().map(str::to_string),
+            schema: e.schema().map(str::to_string),
+            table: e.table().map(str::to_string),
+            column: e.column().map(str::to_string),
+            data_type: e.data_type().map(str::to_string),
+            constraint: e.constraint().map(str::to_string),
+            notices: Vec::new(),
+        }
+    }
+}
+
+/// Severity as the server spells it in an `ErrorResponse` or `NoticeResponse`.
+pub(crate) fn severity_name(severity: PgSeverity) -> &'static str {
+    match severity {
+        PgSeverity::Panic => "PANIC",
+        PgSeverity::Fatal => "FATAL",
+        PgSeverity::Error => "ERROR",
+        PgSeverity::Warning => "WARNING",
+        PgSeverity::Notice => "NOTICE",
+        PgSeverity::Debug => "DEBUG",
+        PgSeverity::Info => "INFO",
+        PgSeverity::Log => "LOG",
+    }
+}
+
+impl CommandError {
+    /// Wraps a driver error. Server errors keep their details; anything else,
+    /// such as a dropped connection, is described as `context: error`.
+    pub fn sqlx(context: &str, error: sqlx::Error) -> Self {
+        match error
+            .as_database_error()
+            .and_then(|e| e.try_downcast_ref::<PgDatabaseError>())
+        {
+            Some(e) => CommandError::Database(Box::new(e.into())),
+            None => CommandError::Failed(format!("{}: {}", context, error)),
+        }
+    }
+
+    /// Points a server error's position at the statement as written, for SQL
+    /// that was rewritten before it ran.
+    pub fn at_source(mut self, source: &SourceMap) -> Self {
+        if let CommandError::Database(e) = &mut self {
+            e.position = e
+                .position
+                .map(|position| source.original_position(position));
+        }
+        self
+    }
+
+    /// Attaches the notices raised before a server error; they are dropped
+    /// from any other error.
+    pub fn with_notices(mut self, notices: Vec<ServerNotice>) -> Self {
+        if let CommandError::Database(e) = &mut self {
+            e.notices = notices;
+        }
+        self
+    }
+
+    /// The server's error details, if the server raised this error.
+    pub fn database(&self) -> Option<&DatabaseError> {
+        match self {
+            CommandError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Failed(message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        CommandError::Failed(message.to_string())
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorPayload<'a> {
+    kind: &'static str,
+    /// Server errors carry their message among the flattened details.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tab_ids: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confirmation_token: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasons: Option<&'a [Destructive]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout: Option<TimeoutKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timeout_secs: Option<u64>,
+    #[serde(flatten)]
+    database: Option<&'a DatabaseError>,
+}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let kind = match self {
+            CommandError::NotConnected => "notConnected",
+            CommandError::Database(_) => "database",
+            CommandError::OpenTransactions(_) => "openTransactions",
+            CommandError::ConfirmationRequired { .. } => "confirmationRequired",
+            CommandError::TimedOut { .. } => "timedOut",
+            CommandError::Failed(_) => "failed",
+        };
+
+        let database = self.database();
+        ErrorPayload {
+            kind,
+            message: database.is_none().then(|| self.to_string()),
+            tab_ids: match self {
+                CommandError::OpenTransactions(tab_ids) => Some(tab_ids),
+                _ => None,
+            },
+            confirmation_token: match self {
+                CommandError::ConfirmationRequired { token, .. } => Some(token),
+                _ => None,
+            },
+            reasons: match self {
+                CommandError::ConfirmationRequired { reasons, .. } => Some(reasons),
+                _ => None,
+            },
+            timeout: match self {
+                CommandError::TimedOut { kind, .. } => Some(*kind),
+                _ => None,
+            },
+            timeout_secs: match self {
+                CommandError::TimedOut { seconds, .. } => *seconds,
+                _ => None,
+            },
+            database,
+        }
+        .serialize(serializer)
+    }
+}
@@ -0,0 +1,635 @@
+//! Query plans from `EXPLAIN (FORMAT JSON)`, parsed into a tree.
+//!
+//! Each node carries the planner's estimates and, with ANALYZE, what actually
+//! happened, plus flags for the usual hot spots. ANALYZE runs the statement, so
+//! it does so in a transaction of its own (or under a savepoint when the tab
+//! already has one open) that is always rolled back.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+use sqlx::Row;
+use tauri::State;
+
+use super::connection::AppState;
+use super::edit::EditBatch;
+use super::error::CommandError;
+use super::jobs::{finish_job, start_job, JobConnection};
+use super::params::QueryParameter;
+use super::query::prepare_execution;
+use super::session::{tab_transaction_state, TransactionState};
+use super::sql::{tokenize, trim_statement};
+use super::timeouts::Timeouts;
+
+/// Actual rows this many times over or under the estimate is a misestimate.
+const MISESTIMATE_FACTOR: f64 = 10.0;
+/// Misestimates where neither side reaches this many rows are not flagged.
+const MISESTIMATE_MIN_ROWS: f64 = 100.0;
+/// Sequential scans reading at least this many rows are flagged.
+const LARGE_SCAN_ROWS: f64 = 100_000.0;
+/// Nodes taking at least this share of the execution time on their own are
+/// flagged, provided that is at least [`SLOW_NODE_MIN_MS`].
+const SLOW_NODE_SHARE: f64 = 0.25;
+const SLOW_NODE_MIN_MS: f64 = 1.0;
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainOptions {
+    /// Run the statement and report actual rows and timing.
+    #[serde(default)]
+    pub analyze: bool,
+    /// Report buffer usage.
+    #[serde(default)]
+    pub buffers: bool,
+    /// Report output columns and schema-qualified names.
+    #[serde(default)]
+    pub verbose: bool,
+    /// Report planner settings that differ from their defaults.
+    #[serde(default)]
+    pub settings: bool,
+    /// Report WAL generated; needs `analyze`.
+    #[serde(default)]
+    pub wal: bool,
+    /// Client-chosen id that `cancel_query` can refer to while this runs.
+    pub job_id: Option<String>,
+    /// Editor tab the query was explained from; it runs in the tab's session.
+    pub tab_id: Option<String>,
+    /// Values for `$1` or `:name` placeholders.
+    pub parameters: Option<Vec<QueryParameter>>,
+    /// Limits for this execution only, in place of the connection's.
+    pub timeouts: Option<Timeouts>,
+}
+
+impl ExplainOptions {
+    /// The option list for `EXPLAIN (...)`.
+    fn clause(&self) -> String {
+        let mut clause = vec!["FORMAT JSON"];
+        for (enabled, option) in [
+            (self.analyze, "ANALYZE"),
+            (self.buffers, "BUFFERS"),
+            (self.verbose, "VERBOSE"),
+            (self.settings, "SETTINGS"),
+            (self.wal, "WAL"),
+        ] {
+            if enabled {
+                clause.push(option);
+            }
+        }
+        clause.join(", ")
+    }
+}
+
+/// Block counts from the `BUFFERS` option.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Buffers {
+    pub shared_hit: u64,
+    pub shared_read: u64,
+    pub shared_dirtied: u64,
+    pub shared_written: u64,
+    pub local_hit: u64,
+    pub local_read: u64,
+    pub local_dirtied: u64,
+    pub local_written: u64,
+    pub temp_read: u64,
+    pub temp_written: u64,
+}
+
+impl Buffers {
+    fn parse(node: &Value) -> Option<Self> {
+        node.get("Shared Hit Blocks")?;
+        let blocks = |key: &str| node.get(key).and_then(Value::as_u64).unwrap_or_default();
+        Some(Self {
+            shared_hit: blocks("Shared Hit Blocks"),
+            shared_read: blocks("Shared Read Blocks"),
+            shared_dirtied: blocks("Shared Dirtied Blocks"),
+            shared_written: blocks("Shared Written Blocks"),
+            local_hit: blocks("Local Hit Blocks"),
+            local_read: blocks("Local Read Blocks"),
+            local_dirtied: blocks("Local Dirtied Blocks"),
+            local_written: blocks("Local Written Blocks"),
+            temp_read: blocks("Temp Read Blocks"),
+            temp_written: blocks("Temp Written Blocks"),
+        })
+    }
+}
+
+/// WAL generated, from the `WAL` option.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Wal {
+    pub records: u64,
+    /// Full-page images.
+    pub full_page_images: u64,
+    pub bytes: u64,
+}
+
+impl Wal {
+    fn parse(node: &Value) -> Option<Self> {
+        let count = |key: &str| node.get(key).and_then(Value::as_u64);
+        Some(Self {
+            records: count("WAL Records")?,
+            full_page_images: count("WAL FPI").unwrap_or_default(),
+            bytes: count("WAL Bytes").unwrap_or_default(),
+        })
+    }
+}
+
+/// Something about a node worth looking at first.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum HotSpot {
+    /// Rows per loop came out `factor` times off the estimate, either way.
+    Misestimate {
+        estimated: f64,
+        actual: f64,
+        factor: f64,
+    },
+    /// A sequential scan reading `rows` rows: actual ones with ANALYZE,
+    /// estimated ones without.
+    LargeSeqScan { rows: f64 },
+    /// The node alone took `share` of the execution time.
+    Slow { share: f64 },
+    /// A sort or hash outgrew `work_mem` and used temporary files.
+    Spill,
+}
+
+/// One node of a plan.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanNode {
+    /// e.g. `Seq Scan`, `Hash Join` or `Sort`.
+    pub node_type: String,
+    /// How the node feeds its parent, e.g. `Outer`, `Inner` or `InitPlan`.
+    pub parent_relationship: Option<String>,
+    /// Table, CTE or function scanned; schema-qualified with VERBOSE.
+    pub relation: Option<String>,
+    pub alias: Option<String>,
+    pub index_name: Option<String>,
+    pub join_type: Option<String>,
+    /// Index, hash or merge condition.
+    pub condition: Option<String>,
+    pub filter: Option<String>,
+    pub startup_cost: f64,
+    pub total_cost: f64,
+    /// Rows the planner expects per loop.
+    pub estimated_rows: f64,
+    /// Rows actually returned per loop.
+    pub actual_rows: Option<f64>,
+    /// How often the node ran; `Some(0)` when it never did.
+    pub loops: Option<u64>,
+    /// Milliseconds to the first row, per loop.
+    pub actual_startup_time_ms: Option<f64>,
+    /// Milliseconds to the last row, per loop.
+    pub actual_total_time_ms: Option<f64>,
+    /// Milliseconds spent in this node alone over all loops, children excluded.
+    pub exclusive_time_ms: Option<f64>,
+    /// Rows per loop read and then discarded by `filter`.
+    pub rows_removed_by_filter: Option<f64>,
+    pub buffers: Option<Buffers>,
+    pub wal: Option<Wal>,
+    pub hot_spots: Vec<HotSpot>,
+    pub children: Vec<PlanNode>,
+}
+
+fn text(node: &Value, key: &str) -> Option<String> {
+    node.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn number(node: &Value, key: &str) -> Option<f64> {
+    node.get(key).and_then(Value::as_f64)
+}
+
+impl PlanNode {
+    /// Parses a node of `EXPLAIN (FORMAT JSON)` output and everything under it.
+    /// `execution_time_ms` is the whole statement's, for judging slow nodes.
+    pub(crate) fn parse(node: &Value, execution_time_ms: Option<f64>) -> Self {
+        let children: Vec<PlanNode> = node
+            .get("Plans")
+            .and_then(Value::as_array)
+            .map(|plans| {
+                plans
+                    .iter()
+                    .map(|child| Self::parse(child, execution_time_ms))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let relation = text(node, "Relation Name")
+            .map(|name| match text(node, "Schema") {
+                Some(schema) => format!("{schema}.{name}"),
+                None => name,
+            })
+            .or_else(|| text(node, "CTE Name"))
+            .or_else(|| text(node, "Function Name"));
+        let condition = ["Index Cond", "Hash Cond", "Merge Cond", "Recheck Cond"]
+            .iter()
+            .find_map(|key| text(node, key));
+
+        let loops = node.get("Actual Loops").and_then(Value::as_u64);
+        let actual_total_time_ms = number(node, "Actual Total Time");
+        let exclusive_time_ms = actual_total_time_ms.zip(loops).map(|(time, loops)| {
+            let own = time * loops as f64;
+            let below: f64 = children
+                .iter()
+                .filter_map(|child| child.actual_total_time_ms.zip(child.loops))
+                .map(|(time, loops)| time * loops as f64)
+                .sum();
+            (own - below).max(0.0)
+        });
+
+        let mut plan = Self {
+            node_type: text(node, "Node Type").unwrap_or_default(),
+            parent_relationship: text(node, "Parent Relationship"),
+            relation,
+            alias: text(node, "Alias"),
+            index_name: text(node, "Index Name"),
+            join_type: text(node, "Join Type"),
+            condition,
+            filter: text(node, "Filter").or_else(|| text(node, "Join Filter")),
+            startup_cost: number(node, "Startup Cost").unwrap_or_default(),
+            total_cost: number(node, "Total Cost").unwrap_or_default(),
+            estimated_rows: number(node, "Plan Rows").unwrap_or_default(),
+            actual_rows: number(node, "Actual Rows"),
+            loops,
+            actual_startup_time_ms: number(node, "Actual Startup Time"),
+            actual_total_time_ms,
+            exclusive_time_ms,
+            rows_removed_by_filter: number(node, "Rows Removed by Filter"),
+            buffers: Buffers::parse(node),
+            wal: Wal::parse(node),
+            hot_spots: Vec::new(),
+            children,
+        };
+        plan.hot_spots = plan.find_hot_spots(node, execution_time_ms);
+        plan
+    }
+
+    fn find_hot_spots(&self, node: &Value, execution_time_ms: Option<f64>) -> Vec<HotSpot> {
+        let mut hot_spots = Vec::new();
+        let ran = self.loops.filter(|&loops| loops > 0);
+
+        if let (Some(actual), Some(_)) = (self.actual_rows, ran) {
+            let estimated = self.estimated_rows;
+            let factor = actual.max(estimated) / actual.min(estimated).max(1.0);
+            if factor >= MISESTIMATE_FACTOR && actual.max(estimated) >= MISESTIMATE_MIN_ROWS {
+                hot_spots.push(HotSpot::Misestimate {
+                    estimated,
+                    actual,
+                    factor,
+                });
+            }
+        }
+
+        if self.node_type == "Seq Scan" {
+            let rows = match (self.actual_rows, ran) {
+                (Some(actual), Some(loops)) => {
+                    (actual + self.rows_removed_by_filter.unwrap_or_default()) * loops as f64
+                }
+                _ => self.estimated_rows,
+            };
+            if rows >= LARGE_SCAN_ROWS {
+                hot_spots.push(HotSpot::LargeSeqScan { rows });
+            }
+        }
+
+        if let (Some(exclusive), Some(total)) = (self.exclusive_time_ms, execution_time_ms) {
+            let share = if total > 0.0 { exclusive / total } else { 0.0 };
+            if share >= SLOW_NODE_SHARE && exclusive >= SLOW_NODE_MIN_MS {
+                hot_spots.push(HotSpot::Slow { share });
+            }
+        }
+
+        let spilled = text(node, "Sort Space Type").as_deref() == Some("Disk")
+            || number(node, "Hash Batches").is_some_and(|batches| batches > 1.0)
+            || self.buffers.as_ref().is_some_and(|b| b.temp_written > 0);
+        if spilled {
+            hot_spots.push(HotSpot::Spill);
+        }
+
+        hot_spots
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainResult {
+    pub plan: PlanNode,
+    pub planning_time_ms: Option<f64>,
+    /// Reported with ANALYZE only.
+    pub execution_time_ms: Option<f64>,
+    /// Buffers used while planning, with BUFFERS.
+    pub planning_buffers: Option<Buffers>,
+    /// Planner settings that differ from their defaults, with SETTINGS.
+    pub settings: BTreeMap<String, String>,
+    /// The statement ran and was rolled back, as it does with ANALYZE.
+    pub rolled_back: bool,
+    /// The output as PostgreSQL returned it, to save and compare later.
+    pub raw: Value,
+    /// Transaction state of the tab's session afterwards, unchanged by the run.
+    pub transaction_state: TransactionState,
+}
+
+impl ExplainResult {
+    /// Parses the output of `EXPLAIN (FORMAT JSON)`.
+    pub(crate) fn parse(raw: Value) -> Result<Self, CommandError> {
+        let top = raw
+            .get(0)
+            .filter(|top| top.get("Plan").is_some())
+            .ok_or("EXPLAIN did not return a plan")?;
+
+        let execution_time_ms = number(top, "Execution Time");
+        let settings = top
+            .get("Settings")
+            .and_then(Value::as_object)
+            .map(|settings| {
+                settings
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = match value {
+                            Value::String(value) => value.clone(),
+                            value => value.to_string(),
+                        };
+                        (name.clone(), value)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            plan: PlanNode::parse(&top["Plan"], execution_time_ms),
+            planning_time_ms: number(top, "Planning Time"),
+            execution_time_ms,
+            planning_buffers: top.get("Planning").and_then(Buffers::parse),
+            settings,
+            rolled_back: false,
+            transaction_state: TransactionState::Idle,
+            raw,
+        })
+    }
+}
+
+/// Explains a statement, parsing the plan PostgreSQL returns as JSON.
+///
+/// With ANALYZE the statement runs and is rolled back afterwards, whatever it
+/// is: a `SELECT` can change data too, through data-modifying CTEs or
+/// functions. Sequences still advance.
+#[tauri::command]
+pub async fn explain_query(
+    query: String,
+    options: Option<ExplainOptions>,
+    state: State<'_, AppState>,
+) -> Result<ExplainResult, CommandError> {
+    let options = options.unwrap_or_default();
+    let statement = trim_statement(&query);
+    let first = tokenize(statement).into_iter().find(|t| !t.is_trivia());
+    match first {
+        None => return Err("There is no statement to explain".into()),
+        Some(token) if token.is_keyword("EXPLAIN") => {
+            return Err("Leave out EXPLAIN and choose its options instead".into())
+        }
+        Some(_) => {}
+    }
+    let job_id = options
+        .job_id
+        .clone()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let limits = state.timeouts().await.merged(options.timeouts);
+    let mut conn = start_job(&state, &job_id, options.tab_id.clone(), &query).await?;
+
+    let outcome = explain(&mut conn, statement, &options).await;
+    drop(conn);
+    finish_job(&state, &job_id).await;
+
+    let mut result = outcome.map_err(|e| limits.classify(e))?;
+    result.transaction_state = tab_transaction_state(&state, options.tab_id.as_deref()).await;
+    Ok(result)
+}
+
+async fn explain(
+    conn: &mut JobConnection,
+    statement: &str,
+    options: &ExplainOptions,
+) -> Result<ExplainResult, CommandError> {
+    let (sql, binds, saved, source) = prepare_execution(
+        conn,
+        statement,
+        options.parameters.as_deref(),
+        options.timeouts,
+    )
+    .await?;
+    let prefix = format!("EXPLAIN ({}) ", options.clause());
+    let sql = format!("{prefix}{sql}");
+
+    let batch = if options.analyze {
+        match EditBatch::begin(conn).await {
+            Ok(batch) => Some(batch),
+            Err(e) => {
+                if let Some(saved) = saved {
+                    saved.restore(conn).await;
+                }
+                return Err(e);
+            }
+        }
+    } else {
+        None
+    };
+
+    let fetched = binds
+        .into_iter()
+        .fold(sqlx::query(&sql), |query, value| query.bind(value))
+        .fetch_one(&mut **conn)
+        .await;
+    let rolled_back = match batch {
+        Some(batch) => batch.finish(conn, false).await.map(|_| true),
+        None => Ok(false),
+    };
+    if let Some(saved) = saved {
+        saved.restore(conn).await;
+    }
+
+    let row = fetched.map_err(|e| {
+        strip_prefix_position(CommandError::sqlx("Query error", e), &prefix).at_source(&source)
+    })?;
+    let rolled_back = rolled_back?;
+    let raw: Value = row
+        .try_get(0)
+        .map_err(|e| CommandError::sqlx("Failed to read plan", e))?;
+
+    let mut result = ExplainResult::parse(raw)?;
+    result.rolled_back = rolled_back;
+    Ok(result)
+}
+
+/// Points an error's position at the statement as written rather than at the
+/// `EXPLAIN` wrapped around it.
+fn strip_prefix_position(error: CommandError, prefix: &str) -> CommandError {
+    match error {
+        CommandError::Database(mut e) => {
+            e.position = e
+                .position
+                .and_then(|position| position.checked_sub(prefix.chars().count()))
+                .filter(|&position| position > 0);
+            CommandError::Database(e)
+        }
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// `EXPLAIN (ANALYZE, BUFFERS, SETTINGS, FORMAT JSON)` of a join whose
+    /// scan of `orders` was badly misestimated.
+    fn analyzed() -> Value {
+        let customers = json!({
+            "Node Type": "Seq Scan",
+            "Parent Relationship": "Outer",
+            "Relation Name": "customers", "Alias": "c",
+            "Startup Cost": 0.0, "Total Cost": 20.0,
+            "Plan Rows": 1000, "Actual Rows": 1000, "Actual Loops": 1,
+            "Actual Startup Time": 0.01, "Actual Total Time": 5.0,
+        });
+        let hash = json!({
+            "Node Type": "Hash",
+            "Parent Relationship": "Inner",
+            "Startup Cost": 20.0, "Total Cost": 20.0,
+            "Plan Rows": 1000, "Actual Rows": 1000, "Actual Loops": 1,
+            "Actual Startup Time": 8.0, "Actual Total Time": 8.0,
+            "Hash Batches": 4,
+            "Plans": [customers],
+        });
+        let orders = json!({
+            "Node Type": "Seq Scan",
+            "Parent Relationship": "Outer",
+            "Relation Name": "orders", "Schema": "public", "Alias": "o",
+            "Startup Cost": 0.0, "Total Cost": 2000.0,
+            "Plan Rows": 10, "Actual Rows": 200000, "Actual Loops": 1,
+            "Actual Startup Time": 0.01, "Actual Total Time": 30.0,
+            "Filter": "(total > 0)", "Rows Removed by Filter": 50000,
+        });
+        let join = json!({
+            "Node Type": "Hash Join",
+            "Join Type": "Inner",
+            "Startup Cost": 30.0, "Total Cost": 2300.5,
+            "Plan Rows": 10, "Actual Rows": 200000, "Actual Loops": 1,
+            "Actual Startup Time": 1.5, "Actual Total Time": 50.0,
+            "Hash Cond": "(o.customer_id = c.id)",
+            "Shared Hit Blocks": 120, "Shared Read Blocks": 30,
+            "Plans": [orders, hash],
+        });
+        json!([{
+            "Plan": join,
+            "Planning": { "Shared Hit Blocks": 12, "Shared Read Blocks": 0 },
+            "Planning Time": 0.25,
+            "Execution Time": 52.0,
+            "Settings": { "work_mem": "64kB", "jit": false },
+        }])
+    }
+
+    #[test]
+    fn parses_the_plan_tree() {
+        let result = ExplainResult::parse(analyzed()).unwrap();
+
+        assert_eq!(result.planning_time_ms, Some(0.25));
+        assert_eq!(result.execution_time_ms, Some(52.0));
+        assert_eq!(result.planning_buffers.map(|b| b.shared_hit), Some(12));
+        assert_eq!(result.settings["work_mem"], "64kB");
+        assert_eq!(result.settings["jit"], "false");
+
+        let join = &result.plan;
+        assert_eq!(join.node_type, "Hash Join");
+        assert_eq!(join.condition.as_deref(), Some("(o.customer_id = c.id)"));
+        assert_eq!(join.buffers.as_ref().map(|b| b.shared_read), Some(30));
+        assert_eq!(join.exclusive_time_ms, Some(12.0));
+        assert_eq!(join.children.len(), 2);
+
+        let orders = &join.children[0];
+        assert_eq!(orders.relation.as_deref(), Some("public.orders"));
+        assert_eq!(orders.parent_relationship.as_deref(), Some("Outer"));
+        assert_eq!(orders.filter.as_deref(), Some("(total > 0)"));
+        assert_eq!(orders.rows_removed_by_filter, Some(50000.0));
+        assert_eq!(orders.actual_rows, Some(200000.0));
+        assert_eq!(orders.loops, Some(1));
+        assert!(orders.buffers.is_none());
+
+        let hash = &join.children[1];
+        assert_eq!(hash.exclusive_time_ms, Some(3.0));
+        assert_eq!(hash.children[0].relation.as_deref(), Some("customers"));
+    }
+
+    #[test]
+    fn flags_hot_spots() {
+        let result = ExplainResult::parse(analyzed()).unwrap();
+        let join = &result.plan;
+        let (orders, hash) = (&join.children[0], &join.children[1]);
+
+        assert_eq!(
+            orders.hot_spots,
+            vec![
+                HotSpot::Misestimate {
+                    estimated: 10.0,
+                    actual: 200000.0,
+                    factor: 20000.0,
+                },
+                HotSpot::LargeSeqScan { rows: 250000.0 },
+                HotSpot::Slow { share: 30.0 / 52.0 },
+            ]
+        );
+        assert_eq!(
+            join.hot_spots,
+            vec![HotSpot::Misestimate {
+                estimated: 10.0,
+                actual: 200000.0,
+                factor: 20000.0,
+            }]
+        );
+        assert_eq!(hash.hot_spots, vec![HotSpot::Spill]);
+        assert_eq!(hash.children[0].hot_spots, vec![]);
+    }
+
+    #[test]
+    fn judges_plans_without_analyze_on_estimates() {
+        let result = ExplainResult::parse(json!([{
+            "Plan": {
+                "Node Type": "Seq Scan",
+                "Relation Name": "events",
+                "Startup Cost": 0.0, "Total Cost": 9000.0, "Plan Rows": 500000,
+            },
+        }]))
+        .unwrap();
+
+        assert_eq!(result.execution_time_ms, None);
+        assert_eq!(result.plan.actual_rows, None);
+        assert_eq!(result.plan.exclusive_time_ms, None);
+        assert_eq!(
+            result.plan.hot_spots,
+            vec![HotSpot::LargeSeqScan { rows: 500000.0 }]
+        );
+    }
+
+    #[test]
+    fn does_not_flag_nodes_that_never_ran() {
+        let node = PlanNode::parse(
+            &json!({
+                "Node Type": "Index Scan",
+                "Startup Cost": 0.0, "Total Cost": 8.0,
+                "Plan Rows": 5000, "Actual Rows": 0, "Actual Loops": 0,
+                "Actual Startup Time": 0.0, "Actual Total Time": 0.0,
+            }),
+            Some(10.0),
+        );
+
+        assert_eq!(node.loops, Some(0));
+        assert_eq!(node.hot_spots, vec![]);
+    }
+
+    #[test]
+    fn refuses_output_without_a_plan() {
+        assert!(ExplainResult::parse(json!([{ "Planning Time": 1.0 }])).is_err());
+        assert!(ExplainResult::parse(json!({ "Plan": {} })).is_err());
+    }
+}
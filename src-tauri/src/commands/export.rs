@@ -0,0 +1,635 @@
+//! Writing a query's rows to a file.
+//!
+//! Rows are pulled from the server a page at a time and written as they come,
+//! so an export never holds more than a page in memory and never passes
+//! through the webview. Progress is reported as [`EXPORT_PROGRESS_EVENT`]s.
+//! Values are written as the result grid decodes them, except that `bytea`
+//! is written in full as `\x` hex rather than by its size.
+
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+use super::connection::AppState;
+use super::cursor::RowCursor;
+use super::decode::{is_json_type, value_to_text, DisplayZone, ValueStyle};
+use super::error::CommandError;
+use super::export_parquet::ParquetExport;
+use super::export_xlsx::XlsxExport;
+use super::jobs::{finish_job, start_job};
+use super::params::QueryParameter;
+use super::query::{prepare_execution, ResultColumn};
+use super::safety::check_destructive;
+use super::sql::quote_ident;
+use super::timeouts::Timeouts;
+
+/// Event reporting how far an export has got, with an [`ExportProgress`]
+/// payload.
+pub const EXPORT_PROGRESS_EVENT: &str = "export-progress";
+const EXPORT_PAGE_SIZE: usize = 1000;
+/// Least time between two progress events.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const DEFAULT_ROWS_PER_INSERT: usize = 100;
+
+/// The rows to export.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ExportSource {
+    /// Runs `query`, in the tab's session when a tab is given.
+    #[serde(rename_all = "camelCase")]
+    Query {
+        query: String,
+        parameters: Option<Vec<QueryParameter>>,
+        tab_id: Option<String>,
+    },
+    /// Runs the query behind a result set `execute_query` left open again,
+    /// from its first row. Like any statement run in the tab, this closes the
+    /// result set. Results of statements other than queries, such as
+    /// `INSERT ... RETURNING`, are refused rather than run twice.
+    #[serde(rename_all = "camelCase")]
+    Result { result_id: String },
+}
+
+/// How CSV values are quoted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QuoteStyle {
+    /// Only values holding the delimiter, the quote, a line break, or that
+    /// would read as NULL.
+    #[default]
+    Necessary,
+    /// Every value but NULL.
+    Always,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "format", rename_all = "camelCase")]
+pub enum ExportFormat {
+    #[serde(rename_all = "camelCase")]
+    Csv {
+        /// Defaults to a comma.
+        delimiter: Option<char>,
+        /// Defaults to a double quote, which is escaped by doubling it.
+        quote: Option<char>,
+        #[serde(default)]
+        quote_style: QuoteStyle,
+        /// Written unquoted for NULL; defaults to nothing, in which case empty
+        /// strings are quoted to tell them apart.
+        #[serde(default)]
+        null_marker: String,
+        /// Whether to start with a row of column names; defaults to `true`.
+        header: Option<bool>,
+    },
+    /// One JSON array of objects keyed by column name.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    /// `INSERT` statements adding the rows to `table`, which is used as
+    /// written, so it may be schema-qualified and must be quoted as needed.
+    #[serde(rename_all = "camelCase")]
+    Insert {
+        table: String,
+        /// Rows listed in each statement; defaults to 100.
+        rows_per_statement: Option<usize>,
+    },
+    /// A GitHub-flavored Markdown table.
+    Markdown,
+    /// An Excel workbook, with numbers, booleans, dates and times as native
+    /// cells.
+    Xlsx,
+    /// A Parquet file, with a schema derived from the columns' types.
+    Parquet,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    #[serde(flatten)]
+    pub format: ExportFormat,
+    /// Zone TIMESTAMPTZ and TIMETZ values are written in.
+    #[serde(default)]
+    pub display_zone: DisplayZone,
+    /// Client-chosen id that `cancel_query` can refer to and progress events
+    /// carry.
+    pub job_id: Option<String>,
+    /// Limits for this export only, in place of the connection's.
+    pub timeouts: Option<Timeouts>,
+    /// Token from a `confirmationRequired` error, to run the destructive
+    /// statement it was issued for.
+    pub confirmation_token: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProgress {
+    pub job_id: String,
+    pub rows_written: u64,
+    /// Bytes in the file so far; Parquet rows only count once their row group
+    /// is complete. `None` for XLSX, whose file is only written once every
+    /// row is in.
+    pub bytes_written: Option<u64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub path: String,
+    pub rows_written: u64,
+    pub bytes_written: u64,
+    pub execution_time_ms: u64,
+}
+
+/// Formats rows for one export, keeping what must carry over between pages.
+struct RowWriter {
+    format: ExportFormat,
+    columns: Vec<ResultColumn>,
+    /// Columns holding `json` or `jsonb`, written as JSON text rather than as
+    /// PostgreSQL literals.
+    json: Vec<bool>,
+    /// Column names made unique, for JSON objects.
+    keys: Vec<String>,
+    rows: u64,
+    /// Rows in the `INSERT` statement still open.
+    open_insert_rows: usize,
+}
+
+impl RowWriter {
+    fn new(format: ExportFormat, columns: Vec<ResultColumn>) -> Self {
+        let json = columns
+            .iter()
+            .map(|c| c.type_oid.is_some_and(is_json_type))
+            .collect();
+        let mut keys: Vec<String> = Vec::with_capacity(columns.len());
+        for column in &columns {
+            let mut key = column.name.clone();
+            let mut n = 1;
+            while keys.contains(&key) {
+                n += 1;
+                key = format!("{}_{}", column.name, n);
+            }
+            keys.push(key);
+        }
+
+        Self {
+            format,
+            columns,
+            json,
+            keys,
+            rows: 0,
+            open_insert_rows: 0,
+        }
+    }
+
+    /// The text form of a non-null value.
+    fn text(&self, column: usize, value: &Value) -> Option<String> {
+        match value {
+            Value::Null => None,
+            _ if self.json[column] => Some(value.to_string()),
+            _ => value_to_text(value),
+        }
+    }
+
+    fn begin(&self, out: &mut String) {
+        match &self.format {
+            ExportFormat::Csv {
+                header: Some(false),
+                ..
+            } => {}
+            ExportFormat::Csv { .. } => {
+                let names: Vec<Value> = self
+                    .columns
+                    .iter()
+                    .map(|c| Value::String(c.name.clone()))
+                    .collect();
+                self.write_csv_row(&names, false, out);
+            }
+            ExportFormat::Json => out.push('['),
+            ExportFormat::Markdown => {
+                out.push('|');
+                for column in &self.columns {
+                    out.push(' ');
+                    out.push_str(&markdown_cell(&column.name));
+                    out.push_str(" |");
+                }
+                out.push_str("\n|");
+                for _ in &self.columns {
+                    out.push_str(" --- |");
+                }
+                out.push('\n');
+            }
+            ExportFormat::Ndjson
+            | ExportFormat::Insert { .. }
+            | ExportFormat::Xlsx
+            | ExportFormat::Parquet => {}
+        }
+    }
+
+    fn write_row(&mut self, row: &[Value], out: &mut String) {
+        match &self.format {
+            ExportFormat::Csv { .. } => self.write_csv_row(row, true, out),
+            ExportFormat::Json => {
+                out.push_str(if self.rows == 0 { "\n" } else { ",\n" });
+                out.push_str(&self.json_object(row));
+            }
+            ExportFormat::Ndjson => {
+                out.push_str(&self.json_object(row));
+                out.push('\n');
+            }
+            ExportFormat::Insert {
+                table,
+                rows_per_statement,
+            } => {
+                let per_statement = rows_per_statement.unwrap_or(DEFAULT_ROWS_PER_INSERT).max(1);
+                if self.open_insert_rows == 0 {
+                    let names: Vec<String> =
+                        self.columns.iter().map(|c| quote_ident(&c.name)).collect();
+                    out.push_str(&format!(
+                        "INSERT INTO {} ({}) VALUES\n  (",
+                        table,
+                        names.join(", ")
+                    ));
+                } else {
+                    out.push_str(",\n  (");
+                }
+                let values: Vec<String> = row
+                    .iter()
+                    .enumerate()
+                    .map(|(column, value)| self.sql_literal(column, value))
+                    .collect();
+                out.push_str(&values.join(", "));
+                out.push(')');
+
+                self.open_insert_rows += 1;
+                if self.open_insert_rows == per_statement {
+                    out.push_str(";\n");
+                    self.open_insert_rows = 0;
+                }
+            }
+            ExportFormat::Markdown => {
+                out.push('|');
+                for (column, value) in row.iter().enumerate() {
+                    out.push(' ');
+                    match self.text(column, value) {
+                        Some(text) => out.push_str(&markdown_cell(&text)),
+                        None => out.push_str("NULL"),
+                    }
+                    out.push_str(" |");
+                }
+                out.push('\n');
+            }
+            // Written by `XlsxExport` and `ParquetExport` instead.
+            ExportFormat::Xlsx | ExportFormat::Parquet => {}
+        }
+        self.rows += 1;
+    }
+
+    fn finish(&mut self, out: &mut String) {
+        match &self.format {
+            ExportFormat::Json if self.rows == 0 => out.push_str("]\n"),
+            ExportFormat::Json => out.push_str("\n]\n"),
+            ExportFormat::Insert { .. } if self.open_insert_rows > 0 => {
+                out.push_str(";\n");
+                self.open_insert_rows = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Values are written in their text form, except when `typed` is false,
+    /// as for the header, where every value is a plain string.
+    fn write_csv_row(&self, row: &[Value], typed: bool, out: &mut String) {
+        let ExportFormat::Csv {
+            delimiter,
+            quote,
+            quote_style,
+            null_marker,
+            ..
+        } = &self.format
+        else {
+            return;
+        };
+        let delimiter = delimiter.unwrap_or(',');
+        let quote = quote.unwrap_or('"');
+
+        for (column, value) in row.iter().enumerate() {
+            if column > 0 {
+                out.push(delimiter);
+            }
+            let text = match value {
+                Value::String(name) if !typed => Some(name.clone()),
+                _ => self.text(column, value),
+            };
+            let Some(text) = text else {
+                out.push_str(null_marker);
+                continue;
+            };
+
+            let needs_quotes = *quote_style == QuoteStyle::Always
+                || text == *null_marker
+                || text
+                    .chars()
+                    .any(|c| c == delimiter || c == quote || c == '\n' || c == '\r');
+            if !needs_quotes {
+                out.push_str(&text);
+                continue;
+            }
+            out.push(quote);
+            for c in text.chars() {
+                if c == quote {
+                    out.push(quote);
+                }
+                out.push(c);
+            }
+            out.push(quote);
+        }
+        out.push_str("\r\n");
+    }
+
+    /// Written by hand rather than through a map, which would sort the keys
+    /// instead of keeping the columns' order.
+    fn json_object(&self, row: &[Value]) -> String {
+        let fields: Vec<String> = self
+            .keys
+            .iter()
+            .zip(row)
+            .map(|(key, value)| format!("{}:{}", Value::String(key.clone()), value))
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+
+    /// Numbers and booleans are written bare, unless they are JSON, which has
+    /// no cast from either; everything else as a quoted literal the target
+    /// column's type will parse.
+    fn sql_literal(&self, column: usize, value: &Value) -> String {
+        match value {
+            Value::Null => "NULL".to_string(),
+            Value::Bool(b) if !self.json[column] => if *b { "TRUE" } else { "FALSE" }.to_string(),
+            Value::Number(n) if !self.json[column] => n.to_string(),
+            _ => {
+                let text = self.text(column, value).unwrap_or_default();
+                format!("'{}'", text.replace('\'', "''"))
+            }
+        }
+    }
+}
+
+/// Pipes would end the cell and line breaks the row, so they are escaped.
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
+
+/// Runs a query and writes every row it returns to `path`, replacing the file.
+/// A file left incomplete by an error or a cancellation is removed.
+#[tauri::command]
+pub async fn export_query(
+    source: ExportSource,
+    path: String,
+    options: ExportOptions,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ExportSummary, CommandError> {
+    let (query, parameters, tab_id) = match source {
+        ExportSource::Query {
+            query,
+            parameters,
+            tab_id,
+        } => (query, parameters, tab_id),
+        ExportSource::Result { result_id } => state
+            .results
+            .lock()
+            .await
+            .get(&result_id)
+            .ok_or("Result set is no longer available")?
+            .rerun()?,
+    };
+    let job_id = options
+        .job_id
+        .clone()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let pool = state.pool().await?;
+    check_destructive(
+        &state,
+        &query,
+        [query.as_str()],
+        options.confirmation_token.as_deref(),
+    )
+    .await?;
+
+    let file = tokio::fs::File::create(&path)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", path, e))?;
+
+    let limits = state.timeouts().await.merged(options.timeouts);
+    let start = Instant::now();
+    let mut conn = match start_job(&state, &job_id, tab_id, &query).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e);
+        }
+    };
+
+    let prepared =
+        prepare_execution(&mut conn, &query, parameters.as_deref(), options.timeouts).await;
+    let written = match prepared {
+        Ok((sql, binds, saved, source)) => {
+            let cursor = RowCursor::open(
+                conn,
+                pool,
+                sql,
+                binds,
+                options.display_zone,
+                ValueStyle::Export,
+                saved,
+            );
+            write_rows(&cursor, file, options.format, &app, &job_id)
+                .await
+                .map_err(|e| e.at_source(&source))
+        }
+        Err(e) => {
+            conn.statement_finished(&query, true).await;
+            drop(conn);
+            Err(e)
+        }
+    };
+    let cancelled = finish_job(&state, &job_id).await;
+
+    match written {
+        Ok((rows_written, bytes_written)) => Ok(ExportSummary {
+            path,
+            rows_written,
+            bytes_written,
+            execution_time_ms: start.elapsed().as_millis() as u64,
+        }),
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&path).await;
+            if cancelled {
+                return Err("Export cancelled".into());
+            }
+            Err(limits.classify(e))
+        }
+    }
+}
+
+/// Where an export's rows go.
+enum Sink {
+    Text {
+        file: BufWriter<tokio::fs::File>,
+        writer: RowWriter,
+        bytes: u64,
+    },
+    // Boxed as the workbook is large to move between threads every page.
+    Xlsx(Box<XlsxExport>),
+    Parquet(ParquetExport),
+}
+
+impl Sink {
+    async fn open(
+        file: tokio::fs::File,
+        format: ExportFormat,
+        columns: Vec<ResultColumn>,
+    ) -> Result<Self, CommandError> {
+        match format {
+            ExportFormat::Xlsx => Ok(Self::Xlsx(Box::new(XlsxExport::new(
+                file.into_std().await,
+                &columns,
+            )?))),
+            ExportFormat::Parquet => Ok(Self::Parquet(ParquetExport::new(
+                file.into_std().await,
+                &columns,
+            )?)),
+            format => {
+                let writer = RowWriter::new(format, columns);
+                let mut out = String::new();
+                writer.begin(&mut out);
+                let mut file = BufWriter::new(file);
+                write_text(&mut file, &out).await?;
+                Ok(Self::Text {
+                    file,
+                    writer,
+                    bytes: out.len() as u64,
+                })
+            }
+        }
+    }
+
+    /// Writes a page of rows, returning the sink and the bytes in the file so
+    /// far, if it has any yet. The binary formats encode on a blocking thread,
+    /// which is handed the sink for the page.
+    async fn write(self, rows: Vec<Vec<Value>>) -> Result<(Self, Option<u64>), CommandError> {
+        match self {
+            Self::Text {
+                mut file,
+                mut writer,
+                bytes,
+            } => {
+                let mut out = String::new();
+                for row in &rows {
+                    writer.write_row(row, &mut out);
+                }
+                write_text(&mut file, &out).await?;
+                let bytes = bytes + out.len() as u64;
+                Ok((
+                    Self::Text {
+                        file,
+                        writer,
+                        bytes,
+                    },
+                    Some(bytes),
+                ))
+            }
+            Self::Xlsx(mut export) => {
+                let export = blocking(move || export.write_rows(&rows).map(|()| export)).await?;
+                Ok((Self::Xlsx(export), None))
+            }
+            Self::Parquet(mut export) => {
+                let export = blocking(move || export.write_rows(&rows).map(|()| export)).await?;
+                let bytes = export.bytes_written();
+                Ok((Self::Parquet(export), Some(bytes)))
+            }
+        }
+    }
+
+    /// Completes the file, returning its size.
+    async fn finish(self) -> Result<u64, CommandError> {
+        match self {
+            Self::Text {
+                mut file,
+                mut writer,
+                bytes,
+            } => {
+                let mut out = String::new();
+                writer.finish(&mut out);
+                write_text(&mut file, &out).await?;
+                file.flush()
+                    .await
+                    .map_err(|e| format!("Failed to write export: {}", e))?;
+                Ok(bytes + out.len() as u64)
+            }
+            Self::Xlsx(export) => blocking(move || export.finish()).await,
+            Self::Parquet(export) => blocking(move || export.finish()).await,
+        }
+    }
+}
+
+async fn write_text(file: &mut BufWriter<tokio::fs::File>, out: &str) -> Result<(), CommandError> {
+    file.write_all(out.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write export: {}", e).into())
+}
+
+async fn blocking<T: Send + 'static>(
+    task: impl FnOnce() -> Result<T, CommandError> + Send + 'static,
+) -> Result<T, CommandError> {
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|e| format!("Failed to write export: {}", e))?
+}
+
+/// Drains `cursor` into `file`, returning the rows and bytes written.
+async fn write_rows(
+    cursor: &RowCursor,
+    file: tokio::fs::File,
+    format: ExportFormat,
+    app: &AppHandle,
+    job_id: &str,
+) -> Result<(u64, u64), CommandError> {
+    let mut page = cursor.fetch(EXPORT_PAGE_SIZE).await?;
+    let mut sink = Sink::open(file, format, std::mem::take(&mut page.columns)).await?;
+
+    let mut rows: u64 = 0;
+    let mut reported = Instant::now();
+    loop {
+        rows += page.rows.len() as u64;
+        let (next, bytes) = sink.write(page.rows).await?;
+        sink = next;
+
+        if page.exhausted {
+            break;
+        }
+        if reported.elapsed() >= PROGRESS_INTERVAL {
+            reported = Instant::now();
+            report_progress(app, job_id, rows, bytes);
+        }
+        page = cursor.fetch(EXPORT_PAGE_SIZE).await?;
+    }
+
+    let bytes = sink.finish().await?;
+    report_progress(app, job_id, rows, Some(bytes));
+    Ok((rows, bytes))
+}
+
+fn report_progress(app: &AppHandle, job_id: &str, rows_written: u64, bytes_written: Option<u64>) {
+    let _ = app.emit(
+        EXPORT_PROGRESS_EVENT,
+        ExportProgress {
+            job_id: job_id.to_string(),
+            rows_written,
+            bytes_written,
+        },
+    );
+}
@@ -0,0 +1,377 @@
+//! Writing an export as a Parquet file.
+//!
+//! The schema is derived from the columns' PostgreSQL types, with every field
+//! nullable since computed columns may hold NULL. Rows are encoded a page at a
+//! time and flushed every [`ROW_GROUP_SIZE`] rows as a row group, so no more
+//! than one row group is held in memory.
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Date32Array, Decimal128Array, Float32Array, Float64Array, Int16Array,
+    Int32Array, Int64Array, RecordBatch, StringArray, Time64MicrosecondArray,
+    TimestampMicrosecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use serde_json::Value;
+use std::fs::File;
+use std::sync::Arc;
+
+use super::decode::{is_json_type, oid, parse_date, parse_time, parse_timestamp, value_to_text};
+use super::error::CommandError;
+use super::query::ResultColumn;
+
+const ROW_GROUP_SIZE: usize = 65_536;
+/// Most digits a `Decimal128` holds.
+const MAX_DECIMAL_PRECISION: i32 = 38;
+/// `atttypmod` counts the varlena header PostgreSQL stores type modifiers with.
+const VARHDRSZ: i32 = 4;
+
+/// The Parquet type a column is written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Boolean,
+    Int16,
+    Int32,
+    Int64,
+    Float32,
+    Float64,
+    /// `numeric` with a declared precision and scale; without one it has no
+    /// fixed scale and is written as text.
+    Decimal {
+        precision: u8,
+        scale: i8,
+    },
+    Date,
+    Time,
+    Timestamp,
+    /// An instant, stored in UTC.
+    TimestampTz,
+    Json,
+    Text,
+}
+
+impl ColumnType {
+    fn for_column(column: &ResultColumn) -> Self {
+        match column.type_oid {
+            Some(oid::BOOL) => Self::Boolean,
+            Some(oid::INT2) => Self::Int16,
+            Some(oid::INT4) => Self::Int32,
+            Some(oid::INT8) => Self::Int64,
+            Some(oid::FLOAT4) => Self::Float32,
+            Some(oid::FLOAT8) => Self::Float64,
+            Some(oid::NUMERIC) => numeric_type(column.type_modifier),
+            Some(oid::DATE) => Self::Date,
+            Some(oid::TIME) => Self::Time,
+            Some(oid::TIMESTAMP) => Self::Timestamp,
+            Some(oid::TIMESTAMPTZ) => Self::TimestampTz,
+            Some(type_oid) if is_json_type(type_oid) => Self::Json,
+            _ => Self::Text,
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            Self::Boolean => DataType::Boolean,
+            Self::Int16 => DataType::Int16,
+            Self::Int32 => DataType::Int32,
+            Self::Int64 => DataType::Int64,
+            Self::Float32 => DataType::Float32,
+            Self::Float64 => DataType::Float64,
+            Self::Decimal { precision, scale } => DataType::Decimal128(precision, scale),
+            Self::Date => DataType::Date32,
+            Self::Time => DataType::Time64(TimeUnit::Microsecond),
+            Self::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+            Self::TimestampTz => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            Self::Json | Self::Text => DataType::Utf8,
+        }
+    }
+}
+
+/// Reads `numeric(precision, scale)` out of its type modifier. Scales below
+/// zero or above the precision, allowed since PostgreSQL 15, are left as text.
+fn numeric_type(type_modifier: i32) -> ColumnType {
+    if type_modifier < VARHDRSZ {
+        return ColumnType::Text;
+    }
+    let modifier = type_modifier - VARHDRSZ;
+    let precision = (modifier >> 16) & 0xFFFF;
+    let scale = ((modifier & 0x7FF) ^ 1024) - 1024;
+
+    if (1..=MAX_DECIMAL_PRECISION).contains(&precision) && (0..=precision).contains(&scale) {
+        ColumnType::Decimal {
+            precision: precision as u8,
+            scale: scale as i8,
+        }
+    } else {
+        ColumnType::Text
+    }
+}
+
+pub(crate) struct ParquetExport {
+    writer: ArrowWriter<File>,
+    schema: SchemaRef,
+    names: Vec<String>,
+    types: Vec<ColumnType>,
+}
+
+impl ParquetExport {
+    pub(crate) fn new(file: File, columns: &[ResultColumn]) -> Result<Self, CommandError> {
+        let types: Vec<ColumnType> = columns.iter().map(ColumnType::for_column).collect();
+        let schema = Arc::new(Schema::new(
+            columns
+                .iter()
+                .zip(&types)
+                .map(|(column, ty)| Field::new(&column.name, ty.data_type(), true))
+                .collect::<Vec<_>>(),
+        ));
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .build();
+        let writer =
+            ArrowWriter::try_new(file, schema.clone(), Some(properties)).map_err(parquet_error)?;
+
+        Ok(Self {
+            writer,
+            schema,
+            names: columns.iter().map(|c| c.name.clone()).collect(),
+            types,
+        })
+    }
+
+    pub(crate) fn write_rows(&mut self, rows: &[Vec<Value>]) -> Result<(), CommandError> {
+        let arrays = (0..self.types.len())
+            .map(|index| self.build_array(index, rows))
+            .collect::<Result<Vec<_>, _>>()?;
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays)
+            .map_err(|e| CommandError::from(format!("Failed to write Parquet: {}", e)))?;
+        self.writer.write(&batch).map_err(parquet_error)
+    }
+
+    /// Bytes in the file so far; rows still buffered for the current row group
+    /// are not counted.
+    pub(crate) fn bytes_written(&self) -> u64 {
+        self.writer.bytes_written() as u64
+    }
+
+    /// Flushes the last row group and writes the footer, returning the file's
+    /// size.
+    pub(crate) fn finish(mut self) -> Result<u64, CommandError> {
+        self.writer.finish().map_err(parquet_error)?;
+        Ok(self.bytes_written())
+    }
+
+    fn build_array(&self, index: usize, rows: &[Vec<Value>]) -> Result<ArrayRef, CommandError> {
+        let array: ArrayRef = match self.types[index] {
+            ColumnType::Boolean => Arc::new(BooleanArray::from(self.column(
+                index,
+                rows,
+                Value::as_bool,
+            )?)),
+            ColumnType::Int16 => Arc::new(Int16Array::from(self.column(index, rows, |v| {
+                integer(v).and_then(|n| i16::try_from(n).ok())
+            })?)),
+            ColumnType::Int32 => Arc::new(Int32Array::from(self.column(index, rows, |v| {
+                integer(v).and_then(|n| i32::try_from(n).ok())
+            })?)),
+            ColumnType::Int64 => Arc::new(Int64Array::from(self.column(index, rows, integer)?)),
+            ColumnType::Float32 => Arc::new(Float32Array::from(
+                self.column(index, rows, |v| float(v).map(|n| n as f32))?,
+            )),
+            ColumnType::Float64 => Arc::new(Float64Array::from(self.column(index, rows, float)?)),
+            ColumnType::Decimal { precision, scale } => Arc::new(
+                Decimal128Array::from(self.column(index, rows, |v| {
+                    v.as_str().and_then(|text| parse_decimal(text, scale))
+                })?)
+                .with_precision_and_scale(precision, scale)
+                .map_err(|e| CommandError::from(format!("Failed to write Parquet: {}", e)))?,
+            ),
+            ColumnType::Date => Arc::new(Date32Array::from(self.column(index, rows, |v| {
+                let date = parse_date(v.as_str()?)?;
+                let epoch = chrono::DateTime::UNIX_EPOCH.date_naive();
+                let days = date.signed_duration_since(epoch);
+                i32::try_from(days.num_days()).ok()
+            })?)),
+            ColumnType::Time => Arc::new(Time64MicrosecondArray::from(self.column(
+                index,
+                rows,
+                |v| {
+                    let time = parse_time(v.as_str()?)?;
+                    let midnight = chrono::NaiveTime::MIN;
+                    time.signed_duration_since(midnight).num_microseconds()
+                },
+            )?)),
+            ColumnType::Timestamp | ColumnType::TimestampTz => {
+                let micros = self.column(index, rows, |v| {
+                    let (wall_clock, east) = parse_timestamp(v.as_str()?)?;
+                    let utc = wall_clock
+                        .checked_sub_signed(chrono::TimeDelta::seconds(east.unwrap_or(0).into()))?;
+                    Some(utc.and_utc().timestamp_micros())
+                })?;
+                match self.types[index] {
+                    ColumnType::TimestampTz => {
+                        Arc::new(TimestampMicrosecondArray::from(micros).with_timezone("UTC"))
+                    }
+                    _ => Arc::new(TimestampMicrosecondArray::from(micros)),
+                }
+            }
+            ColumnType::Json => Arc::new(StringArray::from(
+                self.column(index, rows, |v| Some(v.to_string()))?,
+            )),
+            ColumnType::Text => Arc::new(StringArray::from(self.column(
+                index,
+                rows,
+                value_to_text,
+            )?)),
+        };
+        Ok(array)
+    }
+
+    /// Converts column `index` of every row with `convert`, failing on a value
+    /// it cannot store rather than losing it.
+    fn column<T>(
+        &self,
+        index: usize,
+        rows: &[Vec<Value>],
+        convert: impl Fn(&Value) -> Option<T>,
+    ) -> Result<Vec<Option<T>>, CommandError> {
+        rows.iter()
+            .map(|row| match &row[index] {
+                Value::Null => Ok(None),
+                value => convert(value).map(Some).ok_or_else(|| {
+                    CommandError::from(format!(
+                        "Column \"{}\" holds {}, which a Parquet {} column cannot store; \
+                         cast it to text to export it",
+                        self.names[index],
+                        value_to_text(value).unwrap_or_default(),
+                        self.types[index].data_type()
+                    ))
+                }),
+            })
+            .collect()
+    }
+}
+
+/// Integers arrive as JSON numbers, or as strings when too large for a
+/// JavaScript number.
+fn integer(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64(),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+/// NaN and the infinities arrive as the strings PostgreSQL spells them with,
+/// which Rust parses too.
+fn float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+/// The unscaled integer of a decimal string such as `-12.50` at `scale`.
+/// `NaN` and the infinities give `None`.
+fn parse_decimal(text: &str, scale: i8) -> Option<i128> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let scale = scale as usize;
+    if fraction.len() > scale
+        || !whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let mut unscaled: i128 = 0;
+    let padding = std::iter::repeat_n(b'0', scale - fraction.len());
+    for digit in whole.bytes().chain(fraction.bytes()).chain(padding) {
+        unscaled = unscaled
+            .checked_mul(10)?
+            .checked_add(i128::from(digit - b'0'))?;
+    }
+    Some(if negative { -unscaled } else { unscaled })
+}
+
+fn parquet_error(e: ParquetError) -> CommandError {
+    format!("Failed to write Parquet: {}", e).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Type modifiers are `atttypmod` as PostgreSQL 15 stores it for each
+    // declaration.
+
+    fn decimal(precision: u8, scale: i8) -> ColumnType {
+        ColumnType::Decimal { precision, scale }
+    }
+
+    #[test]
+    fn reads_declared_numeric_precision_and_scale() {
+        assert_eq!(numeric_type(655366), decimal(10, 2)); // numeric(10,2)
+        assert_eq!(numeric_type(65540), decimal(1, 0)); // numeric(1,0)
+        assert_eq!(numeric_type(2490372), decimal(38, 0)); // numeric(38,0)
+    }
+
+    #[test]
+    fn writes_unrepresentable_numerics_as_text() {
+        assert_eq!(numeric_type(-1), ColumnType::Text); // numeric
+        assert_eq!(numeric_type(329730), ColumnType::Text); // numeric(5,-2)
+        assert_eq!(numeric_type(197660), ColumnType::Text); // numeric(3,-1000)
+        assert_eq!(numeric_type(131081), ColumnType::Text); // numeric(2,5)
+        assert_eq!(numeric_type(2555910), ColumnType::Text); // numeric(39,2)
+        assert_eq!(numeric_type(65536004), ColumnType::Text); // numeric(1000,0)
+    }
+
+    #[test]
+    fn parses_decimals_at_scale() {
+        assert_eq!(parse_decimal("12.50", 2), Some(1250));
+        assert_eq!(parse_decimal("12.5", 2), Some(1250));
+        assert_eq!(parse_decimal("12", 2), Some(1200));
+        assert_eq!(parse_decimal("0.07", 2), Some(7));
+        assert_eq!(parse_decimal("42", 0), Some(42));
+        assert_eq!(parse_decimal("0", 0), Some(0));
+    }
+
+    #[test]
+    fn parses_negative_decimals() {
+        assert_eq!(parse_decimal("-12.50", 2), Some(-1250));
+        assert_eq!(parse_decimal("-0.01", 2), Some(-1));
+        assert_eq!(parse_decimal("-7", 3), Some(-7000));
+    }
+
+    #[test]
+    fn rejects_more_fractional_digits_than_the_scale() {
+        assert_eq!(parse_decimal("1.234", 2), None);
+        assert_eq!(parse_decimal("-1.5", 0), None);
+    }
+
+    #[test]
+    fn rejects_special_values_and_non_numbers() {
+        assert_eq!(parse_decimal("NaN", 2), None);
+        assert_eq!(parse_decimal("Infinity", 2), None);
+        assert_eq!(parse_decimal("-Infinity", 2), None);
+        assert_eq!(parse_decimal("1e5", 0), None);
+        assert_eq!(parse_decimal("--1", 0), None);
+        assert_eq!(parse_decimal("1.2.3", 2), None);
+    }
+
+    #[test]
+    fn rejects_decimals_that_overflow() {
+        let max = "9".repeat(38);
+        assert_eq!(parse_decimal(&max, 0), Some(10i128.pow(38) - 1));
+        assert_eq!(parse_decimal(&"9".repeat(39), 38), None);
+    }
+}
@@ -0,0 +1,237 @@
+//! Writing an export as an Excel workbook.
+//!
+//! Worksheets are written in constant memory mode: each row goes to a
+//! temporary file as soon as the next one starts, and the workbook is zipped
+//! into the output file once every row is in. A result with more rows than a
+//! worksheet holds continues on further sheets, each with its own header.
+
+use chrono::Datelike;
+use rust_xlsxwriter::{ColNum, Format, RowNum, Workbook, Worksheet, XlsxError};
+use serde_json::Value;
+use std::fs::File;
+
+use super::decode::{is_json_type, oid, parse_date, parse_time, parse_timestamp, value_to_text};
+use super::error::CommandError;
+use super::query::ResultColumn;
+
+/// Rows in a worksheet, the header included.
+const MAX_SHEET_ROWS: RowNum = 1_048_576;
+const MAX_COLUMNS: usize = 16_384;
+/// Years Excel can show as dates; others are written as text.
+const EXCEL_YEARS: std::ops::RangeInclusive<i32> = 1900..=9999;
+
+/// How a column's values are written as cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellKind {
+    /// Integers and floats, which arrive as JSON numbers; values too large for
+    /// a JavaScript number, and NaN or infinities, arrive as strings and stay
+    /// text.
+    Number,
+    /// `numeric` and `money`, which arrive as strings so as not to lose
+    /// digits. Excel keeps 15 significant digits of them.
+    Decimal,
+    Boolean,
+    Date,
+    Time,
+    /// `timestamp` and `timestamptz`; Excel has no time zones, so the latter
+    /// is written as wall-clock time in the display zone, as the grid shows it.
+    Timestamp,
+    Json,
+    Text,
+}
+
+impl CellKind {
+    fn for_type(type_oid: Option<u32>) -> Self {
+        match type_oid {
+            Some(oid::INT2 | oid::INT4 | oid::INT8 | oid::OID | oid::FLOAT4 | oid::FLOAT8) => {
+                Self::Number
+            }
+            Some(oid::NUMERIC | oid::MONEY) => Self::Decimal,
+            Some(oid::BOOL) => Self::Boolean,
+            Some(oid::DATE) => Self::Date,
+            Some(oid::TIME) => Self::Time,
+            Some(oid::TIMESTAMP | oid::TIMESTAMPTZ) => Self::Timestamp,
+            Some(type_oid) if is_json_type(type_oid) => Self::Json,
+            _ => Self::Text,
+        }
+    }
+
+    /// Wide enough that dates do not show as `####`.
+    fn width(self) -> Option<f64> {
+        match self {
+            Self::Date => Some(11.0),
+            Self::Time => Some(9.0),
+            Self::Timestamp => Some(20.0),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) struct XlsxExport {
+    workbook: Workbook,
+    file: File,
+    names: Vec<String>,
+    kinds: Vec<CellKind>,
+    header: Format,
+    formats: CellFormats,
+    /// Index of the sheet being written.
+    sheet: usize,
+    /// Next row to write on it.
+    row: RowNum,
+}
+
+impl XlsxExport {
+    pub(crate) fn new(file: File, columns: &[ResultColumn]) -> Result<Self, CommandError> {
+        if columns.len() > MAX_COLUMNS {
+            return Err(format!(
+                "A worksheet holds at most {} columns; the query returns {}",
+                MAX_COLUMNS,
+                columns.len()
+            )
+            .into());
+        }
+
+        let mut export = Self {
+            workbook: Workbook::new(),
+            file,
+            names: columns.iter().map(|c| c.name.clone()).collect(),
+            kinds: columns
+                .iter()
+                .map(|c| CellKind::for_type(c.type_oid))
+                .collect(),
+            header: Format::new().set_bold(),
+            formats: CellFormats {
+                date: Format::new().set_num_format("yyyy-mm-dd"),
+                time: Format::new().set_num_format("hh:mm:ss"),
+                timestamp: Format::new().set_num_format("yyyy-mm-dd hh:mm:ss"),
+            },
+            sheet: 0,
+            row: 0,
+        };
+        export.add_sheet().map_err(xlsx_error)?;
+        Ok(export)
+    }
+
+    /// Starts a new worksheet with a frozen header row.
+    fn add_sheet(&mut self) -> Result<(), XlsxError> {
+        let sheet = self.workbook.add_worksheet_with_constant_memory();
+        sheet.set_freeze_panes(1, 0)?;
+        for (col, (name, kind)) in self.names.iter().zip(&self.kinds).enumerate() {
+            if let Some(width) = kind.width() {
+                sheet.set_column_width(col as ColNum, width)?;
+            }
+            sheet.write_string_with_format(0, col as ColNum, name, &self.header)?;
+        }
+
+        self.sheet = self.workbook.worksheets().len() - 1;
+        self.row = 1;
+        Ok(())
+    }
+
+    pub(crate) fn write_rows(&mut self, rows: &[Vec<Value>]) -> Result<(), CommandError> {
+        for row in rows {
+            if self.row == MAX_SHEET_ROWS {
+                self.add_sheet().map_err(xlsx_error)?;
+            }
+            let sheet = self
+                .workbook
+                .worksheet_from_index(self.sheet)
+                .map_err(xlsx_error)?;
+            for (col, value) in row.iter().enumerate() {
+                let col = col as ColNum;
+                write_cell(
+                    sheet,
+                    self.row,
+                    col,
+                    self.kinds[col as usize],
+                    value,
+                    &self.formats,
+                )
+                .map_err(xlsx_error)?;
+            }
+            self.row += 1;
+        }
+        Ok(())
+    }
+
+    /// Zips the workbook into the output file, returning its size.
+    pub(crate) fn finish(mut self) -> Result<u64, CommandError> {
+        self.workbook
+            .save_to_writer(&mut self.file)
+            .map_err(xlsx_error)?;
+        self.file
+            .metadata()
+            .map(|metadata| metadata.len())
+            .map_err(|e| format!("Failed to write XLSX: {}", e).into())
+    }
+}
+
+struct CellFormats {
+    date: Format,
+    time: Format,
+    timestamp: Format,
+}
+
+/// NULL leaves the cell empty. A value the column's cell type cannot hold, such
+/// as an infinite date, is written as text.
+fn write_cell(
+    sheet: &mut Worksheet,
+    row: RowNum,
+    col: ColNum,
+    kind: CellKind,
+    value: &Value,
+    formats: &CellFormats,
+) -> Result<(), XlsxError> {
+    match (kind, value) {
+        (_, Value::Null) => return Ok(()),
+        (CellKind::Number, Value::Number(n)) => {
+            if let Some(n) = n.as_f64() {
+                sheet.write_number(row, col, n)?;
+                return Ok(());
+            }
+        }
+        (CellKind::Decimal, Value::String(text)) => {
+            if let Some(n) = text.parse::<f64>().ok().filter(|n| n.is_finite()) {
+                sheet.write_number(row, col, n)?;
+                return Ok(());
+            }
+        }
+        (CellKind::Boolean, Value::Bool(b)) => {
+            sheet.write_boolean(row, col, *b)?;
+            return Ok(());
+        }
+        (CellKind::Date, Value::String(text)) => {
+            if let Some(date) = parse_date(text).filter(|d| EXCEL_YEARS.contains(&d.year())) {
+                sheet.write_datetime_with_format(row, col, date, &formats.date)?;
+                return Ok(());
+            }
+        }
+        (CellKind::Time, Value::String(text)) => {
+            if let Some(time) = parse_time(text) {
+                sheet.write_datetime_with_format(row, col, time, &formats.time)?;
+                return Ok(());
+            }
+        }
+        (CellKind::Timestamp, Value::String(text)) => {
+            let timestamp = parse_timestamp(text)
+                .map(|(timestamp, _)| timestamp)
+                .filter(|ts| EXCEL_YEARS.contains(&ts.year()));
+            if let Some(timestamp) = timestamp {
+                sheet.write_datetime_with_format(row, col, timestamp, &formats.timestamp)?;
+                return Ok(());
+            }
+        }
+        (CellKind::Json, value) => {
+            sheet.write_string(row, col, value.to_string())?;
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    sheet.write_string(row, col, value_to_text(value).unwrap_or_default())?;
+    Ok(())
+}
+
+fn xlsx_error(e: XlsxError) -> CommandError {
+    format!("Failed to write XLSX: {}", e).into()
+}
@@ -0,0 +1,312 @@
+//! A log of the queries run, kept in `~/.dbee/history.jsonl` with one entry per
+//! line, newest last.
+//!
+//! Appending never rewrites the file, except when it outgrows
+//! [`MAX_HISTORY_BYTES`]: the oldest half of the entries is then dropped.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use super::connection::AppState;
+use super::editor_tabs::PersistedTab;
+use super::error::CommandError;
+use super::query::QueryResult;
+use super::script::ScriptResult;
+use super::sql::StatementKind;
+
+const MAX_HISTORY_BYTES: u64 = 20 * 1024 * 1024;
+const DEFAULT_SEARCH_LIMIT: usize = 200;
+
+/// Serializes access to the history file between concurrent queries.
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryStatus {
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: String,
+    pub connection_id: String,
+    pub tab_id: Option<String>,
+    pub sql: String,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// Rows a query returned before its first page was sent; more may have
+    /// followed.
+    pub rows_returned: Option<u64>,
+    pub rows_affected: Option<u64>,
+    pub status: HistoryStatus,
+    pub error: Option<String>,
+}
+
+/// Which entries `search_history` returns. Every criterion given must match.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryFilter {
+    /// Words that must all appear in the SQL, in any case and order.
+    pub text: Option<String>,
+    pub connection_id: Option<String>,
+    /// Entries started at or after this time.
+    pub from: Option<DateTime<Utc>>,
+    /// Entries started before this time.
+    pub to: Option<DateTime<Utc>>,
+    pub status: Option<HistoryStatus>,
+    /// Most entries returned, newest first.
+    pub limit: Option<usize>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, entry: &HistoryEntry, words: &[String]) -> bool {
+        let sql = entry.sql.to_lowercase();
+        words.iter().all(|word| sql.contains(word))
+            && self
+                .connection_id
+                .as_ref()
+                .is_none_or(|id| *id == entry.connection_id)
+            && self.from.is_none_or(|from| entry.started_at >= from)
+            && self.to.is_none_or(|to| entry.started_at < to)
+            && self.status.is_none_or(|status| status == entry.status)
+    }
+}
+
+fn get_history_file_path() -> Result<PathBuf, String> {
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let dbee_dir = home_dir.join(".dbee");
+
+    if !dbee_dir.exists() {
+        fs::create_dir_all(&dbee_dir)
+            .map_err(|e| format!("Failed to create .dbee directory: {}", e))?;
+    }
+
+    Ok(dbee_dir.join("history.jsonl"))
+}
+
+/// Every readable entry, oldest first. Lines that do not parse, such as one
+/// cut short by a crash, are skipped.
+fn read_history_file() -> Result<Vec<HistoryEntry>, String> {
+    let path = get_history_file_path()?;
+
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read history file: {}", e))?;
+
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// [`read_history_file`] on the blocking thread pool, as the file can grow to
+/// [`MAX_HISTORY_BYTES`].
+async fn load_history() -> Result<Vec<HistoryEntry>, CommandError> {
+    let entries = tokio::task::spawn_blocking(read_history_file)
+        .await
+        .map_err(|e| format!("Failed to read history file: {}", e))??;
+    Ok(entries)
+}
+
+fn append_history_entry(entry: &HistoryEntry) -> Result<(), String> {
+    let path = get_history_file_path()?;
+    let mut line = serde_json::to_string(entry)
+        .map_err(|e| format!("Failed to serialize history entry: {}", e))?;
+    line.push('\n');
+
+    let _guard = HISTORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open history file: {}", e))?;
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to write history file: {}", e))?;
+
+    let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+    drop(file);
+    if size > MAX_HISTORY_BYTES {
+        let entries = read_history_file()?;
+        let kept = &entries[entries.len() / 2..];
+        let mut content = String::new();
+        for entry in kept {
+            if let Ok(line) = serde_json::to_string(entry) {
+                content.push_str(&line);
+                content.push('\n');
+            }
+        }
+        fs::write(&path, content).map_err(|e| format!("Failed to write history file: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Logs one `execute_query` call. Statements held back for confirmation never
+/// ran and are not logged, and failing to log never fails the query.
+pub async fn record_query(
+    state: &AppState,
+    sql: &str,
+    tab_id: Option<String>,
+    started_at: DateTime<Utc>,
+    outcome: &Result<QueryResult, CommandError>,
+) {
+    let Some(connection_id) = state.connection_id().await else {
+        return;
+    };
+
+    let (status, rows_returned, rows_affected, error) = match outcome {
+        Err(CommandError::ConfirmationRequired { .. }) => return,
+        Err(e) => (HistoryStatus::Failed, None, None, Some(e.to_string())),
+        Ok(result) if result.cancelled => (HistoryStatus::Cancelled, None, None, None),
+        Ok(result) => (
+            HistoryStatus::Succeeded,
+            (result.statement_kind == StatementKind::Query).then_some(result.rows.len() as u64),
+            result.rows_affected,
+            None,
+        ),
+    };
+
+    let entry = HistoryEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        connection_id,
+        tab_id,
+        sql: sql.to_string(),
+        started_at,
+        duration_ms: (Utc::now() - started_at).num_milliseconds().max(0) as u64,
+        rows_returned,
+        rows_affected,
+        status,
+        error,
+    };
+    append_history_entries(vec![entry]).await;
+}
+
+/// Logs one `execute_script` call with an entry for each statement that ran.
+/// A script that failed before any statement ran is logged whole.
+pub async fn record_script(
+    state: &AppState,
+    script: &str,
+    tab_id: Option<String>,
+    started_at: DateTime<Utc>,
+    outcome: &Result<ScriptResult, CommandError>,
+) {
+    let Some(connection_id) = state.connection_id().await else {
+        return;
+    };
+
+    let entry = |sql: &str, started_at: DateTime<Utc>, duration_ms: u64| HistoryEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        connection_id: connection_id.clone(),
+        tab_id: tab_id.clone(),
+        sql: sql.to_string(),
+        started_at,
+        duration_ms,
+        rows_returned: None,
+        rows_affected: None,
+        status: HistoryStatus::Succeeded,
+        error: None,
+    };
+
+    let entries = match outcome {
+        Err(CommandError::ConfirmationRequired { .. }) => return,
+        Err(e) => {
+            let duration_ms = (Utc::now() - started_at).num_milliseconds().max(0) as u64;
+            vec![HistoryEntry {
+                status: HistoryStatus::Failed,
+                error: Some(e.to_string()),
+                ..entry(script, started_at, duration_ms)
+            }]
+        }
+        Ok(result) => {
+            let last = result.statements.len().saturating_sub(1);
+            result
+                .statements
+                .iter()
+                .enumerate()
+                .map(|(index, statement)| {
+                    let (status, error) = match &statement.error {
+                        None => (HistoryStatus::Succeeded, None),
+                        Some(_) if result.cancelled && index == last => {
+                            (HistoryStatus::Cancelled, None)
+                        }
+                        Some(e) => (HistoryStatus::Failed, Some(e.to_string())),
+                    };
+                    let returned_rows = status == HistoryStatus::Succeeded
+                        && statement.statement_kind == StatementKind::Query;
+                    HistoryEntry {
+                        rows_returned: returned_rows.then_some(statement.rows.len() as u64),
+                        rows_affected: statement.rows_affected,
+                        status,
+                        error,
+                        ..entry(
+                            &statement.statement,
+                            statement.started_at,
+                            statement.execution_time_ms,
+                        )
+                    }
+                })
+                .collect()
+        }
+    };
+    append_history_entries(entries).await;
+}
+
+/// Appends entries on the blocking thread pool, ignoring failures.
+async fn append_history_entries(entries: Vec<HistoryEntry>) {
+    let _ = tokio::task::spawn_blocking(move || entries.iter().try_for_each(append_history_entry))
+        .await;
+}
+
+/// Entries matching `filter`, newest first.
+#[tauri::command]
+pub async fn search_history(
+    filter: Option<HistoryFilter>,
+) -> Result<Vec<HistoryEntry>, CommandError> {
+    let filter = filter.unwrap_or_default();
+    let words: Vec<String> = filter
+        .text
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect();
+    let limit = filter.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+
+    let entries = load_history().await?;
+    Ok(entries
+        .into_iter()
+        .rev()
+        .filter(|entry| filter.matches(entry, &words))
+        .take(limit)
+        .collect())
+}
+
+/// A new editor tab holding an entry's SQL.
+#[tauri::command]
+pub async fn open_history_entry(entry_id: String) -> Result<PersistedTab, CommandError> {
+    let entry = load_history()
+        .await?
+        .into_iter()
+        .rfind(|entry| entry.id == entry_id)
+        .ok_or("History entry not found")?;
+
+    let started = entry
+        .started_at
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M");
+    Ok(PersistedTab {
+        id: format!("tab-{}", uuid::Uuid::new_v4()),
+        name: format!("History {started}"),
+        content: entry.sql,
+    })
+}
@@ -0,0 +1,256 @@
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgConnection, PgPool};
+use sqlx::{Connection, Postgres};
+use std::ops::{Deref, DerefMut};
+use tauri::State;
+
+use super::connection::AppState;
+use super::error::CommandError;
+use super::session::{lock_tab_session, open_tab_session, SessionGuard, TransactionState};
+use super::sql::discards_prepared_statements;
+
+/// A statement currently executing on a known backend.
+pub struct QueryJob {
+    pub backend_pid: i32,
+    pub tab_id: Option<String>,
+    pub query: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pool: PgPool,
+    cancel_requested: bool,
+}
+
+/// What is needed to signal a job's backend once the jobs lock is released.
+pub struct CancelHandle {
+    pool: PgPool,
+    backend_pid: i32,
+}
+
+impl CancelHandle {
+    /// Sends `pg_cancel_backend` from a separate pooled connection.
+    pub async fn send(self) -> Result<bool, CommandError> {
+        sqlx::query_scalar::<_, bool>("SELECT pg_cancel_backend($1)")
+            .bind(self.backend_pid)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| CommandError::sqlx("Failed to cancel query", e))
+    }
+}
+
+impl QueryJob {
+    pub fn cancel_requested(&self) -> bool {
+        self.cancel_requested
+    }
+
+    pub fn request_cancel(&mut self) -> CancelHandle {
+        self.cancel_requested = true;
+        CancelHandle {
+            pool: self.pool.clone(),
+            backend_pid: self.backend_pid,
+        }
+    }
+}
+
+/// The connection a job runs on.
+pub enum JobConnection {
+    /// Checked out of the pool for this job alone, with its backend's
+    /// process id.
+    Pooled(PoolConnection<Postgres>, i32),
+    /// The tab's pinned session, held until the job lets go of it.
+    Session(SessionGuard),
+}
+
+impl JobConnection {
+    /// Records that `sql` finished, keeping a session's transaction state current.
+    ///
+    /// `DISCARD ALL` and `DEALLOCATE ALL` also drop the statements the driver
+    /// prepared, so its cache is cleared to match.
+    pub async fn statement_finished(&mut self, sql: &str, failed: bool) {
+        if !failed && discards_prepared_statements(sql) {
+            let _ = self.clear_cached_statements().await;
+        }
+        if let JobConnection::Session(session) = self {
+            session.statement_finished(sql, failed);
+        }
+    }
+
+    /// Process id of the backend the connection talks to.
+    pub fn backend_pid(&self) -> i32 {
+        match self {
+            JobConnection::Pooled(_, backend_pid) => *backend_pid,
+            JobConnection::Session(session) => session.backend_pid,
+        }
+    }
+
+    /// The transaction state of the session; pooled connections are always idle.
+    pub fn transaction_state(&self) -> TransactionState {
+        match self {
+            JobConnection::Pooled(..) => TransactionState::Idle,
+            JobConnection::Session(session) => session.transaction_state(),
+        }
+    }
+
+    /// Cancels the session's statement from a connection out of `pool`.
+    /// Pooled connections have no handle, as [`abandon`](Self::abandon) closes
+    /// them instead.
+    pub fn session_cancel(&self, pool: &PgPool) -> Option<CancelHandle> {
+        match self {
+            JobConnection::Pooled(..) => None,
+            JobConnection::Session(session) => Some(CancelHandle {
+                pool: pool.clone(),
+                backend_pid: session.backend_pid,
+            }),
+        }
+    }
+
+    /// Lets go of a connection whose statement still has unread rows.
+    ///
+    /// A pooled connection is closed rather than drained. A session has to
+    /// survive, so whatever rows its statement has left are drained before its
+    /// next statement; see [`RowCursor`](super::cursor::RowCursor) for how
+    /// that is kept short.
+    pub fn abandon(self) {
+        if let JobConnection::Pooled(mut conn, _) = self {
+            conn.close_on_drop();
+        }
+    }
+}
+
+impl Deref for JobConnection {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        match self {
+            JobConnection::Pooled(conn, _) => conn,
+            JobConnection::Session(session) => session,
+        }
+    }
+}
+
+impl DerefMut for JobConnection {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        match self {
+            JobConnection::Pooled(conn, _) => conn,
+            JobConnection::Session(session) => session,
+        }
+    }
+}
+
+/// Finds a connection for `query` and registers it as job `job_id`, so
+/// `cancel_query` can reach its backend until [`finish_job`] is called.
+///
+/// Queries from a tab run in the tab's session, which is opened on first use.
+/// Anything else checks out a pooled connection.
+pub async fn start_job(
+    state: &AppState,
+    job_id: &str,
+    tab_id: Option<String>,
+    query: &str,
+) -> Result<JobConnection, CommandError> {
+    let pool = state.pool().await?;
+
+    let (conn, backend_pid) = match tab_id.as_deref() {
+        Some(tab_id) => {
+            let session = open_tab_session(state, tab_id).await?;
+            let guard = lock_tab_session(state, tab_id, &session).await;
+            (JobConnection::Session(guard), session.backend_pid)
+        }
+        None => {
+            let mut conn = pool
+                .acquire()
+                .await
+                .map_err(|e| CommandError::sqlx("Failed to acquire connection", e))?;
+
+            let backend_pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(|e| CommandError::sqlx("Failed to get backend PID", e))?;
+
+            (JobConnection::Pooled(conn, backend_pid), backend_pid)
+        }
+    };
+
+    let job = QueryJob {
+        backend_pid,
+        tab_id,
+        query: query.to_string(),
+        started_at: chrono::Utc::now(),
+        pool,
+        cancel_requested: false,
+    };
+    state.jobs.lock().await.insert(job_id.to_string(), job);
+
+    Ok(conn)
+}
+
+/// Whether `cancel_query` has been called for a job that is still running.
+pub async fn job_cancel_requested(state: &AppState, job_id: &str) -> bool {
+    state
+        .jobs
+        .lock()
+        .await
+        .get(job_id)
+        .is_some_and(|job| job.cancel_requested())
+}
+
+/// Unregisters a job, returning whether it was cancelled while running.
+pub async fn finish_job(state: &AppState, job_id: &str) -> bool {
+    state
+        .jobs
+        .lock()
+        .await
+        .remove(job_id)
+        .is_some_and(|job| job.cancel_requested())
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryJobInfo {
+    pub job_id: String,
+    pub tab_id: Option<String>,
+    pub backend_pid: i32,
+    pub query: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[tauri::command]
+pub async fn list_running_queries(
+    state: State<'_, AppState>,
+) -> Result<Vec<QueryJobInfo>, CommandError> {
+    let jobs = state.jobs.lock().await;
+
+    let mut running: Vec<QueryJobInfo> = jobs
+        .iter()
+        .map(|(job_id, job)| QueryJobInfo {
+            job_id: job_id.clone(),
+            tab_id: job.tab_id.clone(),
+            backend_pid: job.backend_pid,
+            query: job.query.clone(),
+            started_at: job.started_at,
+        })
+        .collect();
+    running.sort_by_key(|job| job.started_at);
+
+    Ok(running)
+}
+
+/// Asks the server to cancel the statement running under `job_id`, returning
+/// `false` when the job has already finished.
+pub async fn cancel_job(state: &AppState, job_id: &str) -> Result<bool, CommandError> {
+    let handle = match state.jobs.lock().await.get_mut(job_id) {
+        Some(job) => job.request_cancel(),
+        None => return Ok(false),
+    };
+
+    handle.send().await
+}
+
+/// Asks the server to cancel the statement running under `job_id`.
+///
+/// Returns `false` when the job has already finished.
+#[tauri::command]
+pub async fn cancel_query(
+    job_id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, CommandError> {
+    cancel_job(&state, &job_id).await
+}
@@ -1,13 +1,44 @@
 pub mod ai;
 pub mod connection;
 pub mod connections;
+pub mod cursor;
+pub mod decode;
+pub mod dry_run;
+pub mod edit;
 pub mod editor_tabs;
+pub mod error;
+pub mod explain;
+pub mod export;
+pub mod export_parquet;
+pub mod export_xlsx;
+pub mod history;
+pub mod jobs;
+pub mod notices;
+pub mod notifications;
+pub mod params;
+pub mod plan_compare;
 pub mod query;
+pub mod relay;
+pub mod safety;
 pub mod schema;
+pub mod script;
+pub mod session;
+pub mod sql;
+pub mod timeouts;
 
 pub use ai::*;
 pub use connection::*;
 pub use connections::*;
+pub use dry_run::*;
+pub use edit::*;
 pub use editor_tabs::*;
+pub use explain::*;
+pub use export::*;
+pub use history::*;
+pub use jobs::*;
+pub use notifications::*;
+pub use plan_compare::*;
 pub use query::*;
 pub use schema::*;
+pub use script::*;
+pub use session::*;
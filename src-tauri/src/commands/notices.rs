@@ -0,0 +1,164 @@
+//! Messages the server sends while a statement runs without failing it, such
+//! as `RAISE NOTICE` output or the warning "there is no transaction in
+//! progress".
+//!
+//! sqlx reads past these, keeping nothing but the message for a tracing event.
+//! They are picked out of the connection by the [`relay`](super::relay)
+//! instead, whole, and held here under the backend process that raised them
+//! until the statement running there takes them.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+/// Most notices held for one backend; a statement raising more in a single
+/// page only gets the first ones.
+const MAX_HELD_NOTICES: usize = 1000;
+
+static HELD: LazyLock<Mutex<HashMap<i32, Vec<ServerNotice>>>> = LazyLock::new(Default::default);
+
+fn held() -> MutexGuard<'static, HashMap<i32, Vec<ServerNotice>>> {
+    HELD.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A server notice, reported with the result of the statement that raised it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerNotice {
+    /// `WARNING`, `NOTICE`, `INFO`, `LOG` or `DEBUG`, never translated.
+    pub severity: String,
+    pub message: String,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+}
+
+impl ServerNotice {
+    /// Reads the fields of a `NoticeResponse` body: a type byte and a
+    /// NUL-terminated value each, ended by a NUL.
+    pub(crate) fn parse(body: &[u8]) -> Self {
+        let mut notice = ServerNotice::default();
+        let mut localized_severity = None;
+
+        for field in body.split(|&b| b == 0) {
+            let Some((&code, value)) = field.split_first() else {
+                continue;
+            };
+            let value = String::from_utf8_lossy(value).into_owned();
+            match code {
+                b'V' => notice.severity = value,
+                b'S' => localized_severity = Some(value),
+                b'M' => notice.message = value,
+                b'D' => notice.detail = Some(value),
+                b'H' => notice.hint = Some(value),
+                _ => {}
+            }
+        }
+
+        // Servers before 9.6 only send the severity in the session's language.
+        if notice.severity.is_empty() {
+            notice.severity = localized_severity.unwrap_or_default();
+        }
+        notice
+    }
+}
+
+/// Holds a notice the relay read from `backend_pid`.
+pub(crate) fn receive(backend_pid: i32, notice: ServerNotice) {
+    let mut held = held();
+    let notices = held.entry(backend_pid).or_default();
+    if notices.len() < MAX_HELD_NOTICES {
+        notices.push(notice);
+    }
+}
+
+/// Drops what is held for a backend whose connection has closed.
+pub(crate) fn forget(backend_pid: i32) {
+    held().remove(&backend_pid);
+}
+
+/// The notices of the statement running on one backend.
+#[derive(Debug)]
+pub struct NoticeSink {
+    backend_pid: i32,
+}
+
+impl NoticeSink {
+    /// Starts collecting for a statement about to run on `backend_pid`, dropping
+    /// anything earlier statements there left behind.
+    pub fn new(backend_pid: i32) -> Self {
+        forget(backend_pid);
+        Self { backend_pid }
+    }
+
+    /// The notices received since the last call, oldest first.
+    ///
+    /// The relay holds a notice before passing on the bytes that complete it,
+    /// so every notice sent ahead of the rows read so far is here.
+    pub fn take(&self) -> Vec<ServerNotice> {
+        held().remove(&self.backend_pid).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(fields: &[(u8, &str)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (code, value) in fields {
+            body.push(*code);
+            body.extend_from_slice(value.as_bytes());
+            body.push(0);
+        }
+        body.push(0);
+        body
+    }
+
+    #[test]
+    fn parses_every_field() {
+        let notice = ServerNotice::parse(&body(&[
+            (b'S', "HINWEIS"),
+            (b'V', "NOTICE"),
+            (b'C', "00000"),
+            (b'M', "checking"),
+            (b'D', "3 rows"),
+            (b'H', "run it again"),
+            (b'W', "PL/pgSQL function inline_code_block line 1 at RAISE"),
+        ]));
+
+        assert_eq!(
+            notice,
+            ServerNotice {
+                severity: "NOTICE".to_string(),
+                message: "checking".to_string(),
+                detail: Some("3 rows".to_string()),
+                hint: Some("run it again".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_localized_severity() {
+        let notice = ServerNotice::parse(&body(&[(b'S', "LOG"), (b'M', "hello")]));
+
+        assert_eq!(notice.severity, "LOG");
+        assert_eq!(notice.detail, None);
+        assert_eq!(notice.hint, None);
+    }
+
+    #[test]
+    fn takes_only_its_own_backends_notices() {
+        let notice = |message: &str| ServerNotice {
+            severity: "NOTICE".to_string(),
+            message: message.to_string(),
+            ..Default::default()
+        };
+        receive(-10, notice("left over"));
+        let (first, second) = (NoticeSink::new(-10), NoticeSink::new(-11));
+        receive(-10, notice("first"));
+        receive(-11, notice("second"));
+
+        assert_eq!(first.take(), vec![notice("first")]);
+        assert_eq!(first.take(), vec![]);
+        assert_eq!(second.take(), vec![notice("second")]);
+    }
+}
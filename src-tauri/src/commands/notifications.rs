@@ -0,0 +1,268 @@
+//! `LISTEN`/`NOTIFY` on the current connection.
+//!
+//! Channels are listened to on a connection of their own, taken from the pool
+//! on the first [`listen_channel`] and kept until the app disconnects. Each
+//! notification is sent to the frontend as a [`NOTIFICATION_EVENT`] and kept
+//! in a log of the last [`MAX_LOGGED_NOTIFICATIONS`], so ones that arrived
+//! while nobody was watching can still be looked up.
+
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgListener, PgNotification, PgPool};
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::mpsc::{self, error::TryRecvError};
+use tokio::sync::{oneshot, Mutex};
+
+use super::connection::AppState;
+use super::error::CommandError;
+
+/// Event every notification is emitted as, with a [`Notification`] payload.
+pub const NOTIFICATION_EVENT: &str = "db-notification";
+const MAX_LOGGED_NOTIFICATIONS: usize = 1000;
+/// How long to wait before listening again after the connection failed and
+/// could not be replaced.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub channel: String,
+    pub payload: String,
+    /// Backend process of the session that sent it.
+    pub pid: u32,
+    /// When the app received it; the server does not say when it was sent.
+    pub received_at: DateTime<Utc>,
+}
+
+impl From<&PgNotification> for Notification {
+    fn from(notification: &PgNotification) -> Self {
+        Self {
+            channel: notification.channel().to_string(),
+            payload: notification.payload().to_string(),
+            pid: notification.process_id(),
+            received_at: Utc::now(),
+        }
+    }
+}
+
+/// The most recent notifications, oldest first.
+pub type NotificationLog = Arc<Mutex<VecDeque<Notification>>>;
+
+enum ListenAction {
+    Listen,
+    Unlisten,
+}
+
+struct ListenRequest {
+    action: ListenAction,
+    channel: String,
+    reply: oneshot::Sender<Result<(), CommandError>>,
+}
+
+/// The connection channels are listened to on.
+///
+/// It is owned by a background task that waits for notifications and, between
+/// waits, takes `LISTEN`/`UNLISTEN` requests. A wait is never cut short, as
+/// that could lose a notification or leave a reconnection half done; instead a
+/// request is followed by a notification on a channel private to the task,
+/// which ends the wait. Dropping this ends the task when its wait does, and
+/// closing the pool on disconnect ends the wait at once; the connection then
+/// closes, which stops every listen.
+pub struct NotificationListener {
+    requests: mpsc::Sender<ListenRequest>,
+    channels: BTreeSet<String>,
+    pool: PgPool,
+    wake_channel: String,
+}
+
+impl NotificationListener {
+    async fn start(
+        pool: &PgPool,
+        app: AppHandle,
+        log: NotificationLog,
+    ) -> Result<Self, CommandError> {
+        let mut listener = PgListener::connect_with(pool)
+            .await
+            .map_err(|e| CommandError::sqlx("Failed to open a listening connection", e))?;
+        let wake_channel = format!("dbee_wake_{}", uuid::Uuid::new_v4().simple());
+        listener
+            .listen(&wake_channel)
+            .await
+            .map_err(|e| CommandError::sqlx("Failed to open a listening connection", e))?;
+        let (requests, mut receiver) = mpsc::channel::<ListenRequest>(1);
+
+        let wake = wake_channel.clone();
+        tokio::spawn(async move {
+            loop {
+                loop {
+                    match receiver.try_recv() {
+                        Ok(request) => handle_request(&mut listener, request).await,
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => return,
+                    }
+                }
+
+                match listener.try_recv().await {
+                    Ok(Some(notification)) if notification.channel() == wake => {}
+                    Ok(Some(notification)) => {
+                        record_notification(&log, &app, Notification::from(&notification)).await
+                    }
+                    // The connection was lost and has been replaced, listening
+                    // again; notifications sent meanwhile are lost.
+                    Ok(None) => {}
+                    // The pool is closing, which only happens on disconnect.
+                    Err(sqlx::Error::PoolClosed) => break,
+                    // Replacing the connection failed; it is tried again on
+                    // the next wait.
+                    Err(_) => tokio::time::sleep(RETRY_DELAY).await,
+                }
+            }
+        });
+
+        Ok(Self {
+            requests,
+            channels: BTreeSet::new(),
+            pool: pool.clone(),
+            wake_channel,
+        })
+    }
+
+    async fn send(&self, action: ListenAction, channel: &str) -> Result<(), CommandError> {
+        let (reply, response) = oneshot::channel();
+        let request = ListenRequest {
+            action,
+            channel: channel.to_string(),
+            reply,
+        };
+
+        self.requests
+            .send(request)
+            .await
+            .map_err(|_| CommandError::from("The listening connection is closed"))?;
+        sqlx::query("SELECT pg_notify($1, '')")
+            .bind(&self.wake_channel)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| CommandError::sqlx("Failed to reach the listening connection", e))?;
+        response
+            .await
+            .map_err(|_| CommandError::from("The listening connection is closed"))?
+    }
+
+    fn channels(&self) -> Vec<String> {
+        self.channels.iter().cloned().collect()
+    }
+}
+
+/// Runs a request unless its caller already gave up on it.
+async fn handle_request(listener: &mut PgListener, request: ListenRequest) {
+    if request.reply.is_closed() {
+        return;
+    }
+    let done = match request.action {
+        ListenAction::Listen => listener.listen(&request.channel).await,
+        ListenAction::Unlisten => listener.unlisten(&request.channel).await,
+    };
+    let _ = request
+        .reply
+        .send(done.map_err(|e| CommandError::sqlx("Query error", e)));
+}
+
+/// Logs a notification and sends it to the frontend.
+async fn record_notification(log: &NotificationLog, app: &AppHandle, notification: Notification) {
+    let mut logged = log.lock().await;
+    if logged.len() == MAX_LOGGED_NOTIFICATIONS {
+        logged.pop_front();
+    }
+    logged.push_back(notification.clone());
+    drop(logged);
+    let _ = app.emit(NOTIFICATION_EVENT, notification);
+}
+
+/// Starts listening on `channel`, opening the listening connection if this is
+/// the first. The name is used as written, case included. Returns every
+/// channel now listened to.
+#[tauri::command]
+pub async fn listen_channel(
+    channel: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, CommandError> {
+    if channel.is_empty() {
+        return Err("Channel name cannot be empty".into());
+    }
+
+    let pool = state.pool().await?;
+    let mut current = state.listener.lock().await;
+    let listener = match &mut *current {
+        Some(listener) => listener,
+        slot => {
+            slot.insert(NotificationListener::start(&pool, app, state.notifications.clone()).await?)
+        }
+    };
+
+    listener.send(ListenAction::Listen, &channel).await?;
+    listener.channels.insert(channel);
+    Ok(listener.channels())
+}
+
+/// Stops listening on `channel`. Returns every channel still listened to.
+#[tauri::command]
+pub async fn unlisten_channel(
+    channel: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, CommandError> {
+    let mut listener = state.listener.lock().await;
+    let Some(listener) = listener.as_mut() else {
+        return Ok(Vec::new());
+    };
+
+    if listener.channels.contains(&channel) {
+        listener.send(ListenAction::Unlisten, &channel).await?;
+        listener.channels.remove(&channel);
+    }
+    Ok(listener.channels())
+}
+
+/// The channels listened to on the current connection.
+#[tauri::command]
+pub async fn get_listened_channels(
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, CommandError> {
+    Ok(state
+        .listener
+        .lock()
+        .await
+        .as_ref()
+        .map(NotificationListener::channels)
+        .unwrap_or_default())
+}
+
+/// Logged notifications, newest first, optionally only those on `channel`.
+#[tauri::command]
+pub async fn list_notifications(
+    channel: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Notification>, CommandError> {
+    let limit = limit.unwrap_or(MAX_LOGGED_NOTIFICATIONS);
+    Ok(state
+        .notifications
+        .lock()
+        .await
+        .iter()
+        .rev()
+        .filter(|n| channel.as_ref().is_none_or(|channel| *channel == n.channel))
+        .take(limit)
+        .cloned()
+        .collect())
+}
+
+/// Empties the notification log.
+#[tauri::command]
+pub async fn clear_notifications(state: State<'_, AppState>) -> Result<(), CommandError> {
+    state.notifications.lock().await.clear();
+    Ok(())
+}
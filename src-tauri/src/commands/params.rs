@@ -0,0 +1,280 @@
+//! Bound values for `$1` and `:name` placeholders.
+//!
+//! Values travel as text, and every placeholder is rewritten to convert it to
+//! its type on the server, e.g. `($1::text::integer)`. Any type with a text
+//! form can be bound that way, and NULL is simply a missing value.
+
+use sqlx::postgres::types::Oid;
+use sqlx::postgres::PgConnection;
+use sqlx::{Either, Executor, Row, Statement, TypeInfo};
+
+use super::error::CommandError;
+use super::query::ResultColumn;
+use super::sql::{cast_placeholders, number_placeholders, NumberedSql, SourceMap};
+
+/// A value for one placeholder.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryParameter {
+    /// Placeholder name without the colon. Values without one fill `$1`, `$2`
+    /// and so on in order.
+    pub name: Option<String>,
+    /// Type to bind as, e.g. `integer` or `timestamptz`; when left out, the
+    /// type PostgreSQL infers from the statement. A type modifier such as the
+    /// `(10,2)` of `numeric(10,2)` is ignored, as it is for any parameter.
+    #[serde(rename = "type")]
+    pub data_type: Option<String>,
+    /// Text form of the value; `None` binds NULL.
+    pub value: Option<String>,
+}
+
+/// A placeholder as PostgreSQL understood it.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParameterDescription {
+    /// 1-based position, as in `$1`.
+    pub position: usize,
+    /// Set for placeholders written as `:name`.
+    pub name: Option<String>,
+    /// Inferred type as PostgreSQL prints it, e.g. `timestamp with time zone`.
+    pub data_type: String,
+    pub type_oid: Option<u32>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryDescription {
+    pub parameters: Vec<ParameterDescription>,
+    pub columns: Vec<ResultColumn>,
+}
+
+/// The types PostgreSQL infers for a statement's placeholders, in order.
+pub(crate) async fn parameter_types(
+    conn: &mut PgConnection,
+    sql: &str,
+) -> Result<Vec<(String, Option<u32>)>, sqlx::Error> {
+    let statement = conn.prepare(sql).await?;
+    let Some(Either::Left(parameters)) = statement.parameters() else {
+        return Ok(Vec::new());
+    };
+
+    let oids: Vec<Oid> = parameters
+        .iter()
+        .map(|t| t.oid().unwrap_or(Oid(0)))
+        .collect();
+    let rows = sqlx::query(
+        r#"
+        SELECT format_type(t, NULL) AS data_type
+        FROM unnest($1::oid[]) WITH ORDINALITY AS p(t, n)
+        ORDER BY n
+        "#,
+    )
+    .bind(&oids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(parameters
+        .iter()
+        .zip(rows)
+        .map(|(info, row)| {
+            let data_type = row
+                .try_get::<Option<String>, _>("data_type")
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| info.name().to_lowercase());
+            (data_type, info.oid().map(|oid| oid.0))
+        })
+        .collect())
+}
+
+/// Describes the placeholders of `numbered`.
+pub(crate) async fn describe_parameters(
+    conn: &mut PgConnection,
+    numbered: &NumberedSql,
+) -> Result<Vec<ParameterDescription>, sqlx::Error> {
+    let types = parameter_types(conn, &numbered.sql).await?;
+    Ok(types
+        .into_iter()
+        .enumerate()
+        .map(|(index, (data_type, type_oid))| ParameterDescription {
+            position: index + 1,
+            name: numbered.names.get(index).cloned().flatten(),
+            data_type,
+            type_oid,
+        })
+        .collect())
+}
+
+fn placeholder_label(position: usize, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!(":{name}"),
+        None => format!("${position}"),
+    }
+}
+
+/// Matches `parameters` to the placeholders of `numbered`: named values by
+/// name, the rest by position.
+fn match_parameters<'a>(
+    numbered: &NumberedSql,
+    parameters: &'a [QueryParameter],
+) -> Result<Vec<&'a QueryParameter>, CommandError> {
+    let mut positional = parameters.iter().filter(|p| p.name.is_none());
+    let mut matched = Vec::with_capacity(numbered.names.len());
+
+    for (index, name) in numbered.names.iter().enumerate() {
+        let parameter = match name {
+            Some(name) => parameters
+                .iter()
+                .find(|p| p.name.as_deref() == Some(name.as_str())),
+            None => positional.next(),
+        };
+        matched.push(parameter.ok_or_else(|| {
+            format!(
+                "No value given for {}",
+                placeholder_label(index + 1, name.as_deref())
+            )
+        })?);
+    }
+
+    if positional.next().is_some() {
+        return Err(format!(
+            "{} values given for {} positional placeholder(s)",
+            parameters.iter().filter(|p| p.name.is_none()).count(),
+            numbered.names.iter().filter(|n| n.is_none()).count()
+        )
+        .into());
+    }
+    if let Some(unknown) = parameters
+        .iter()
+        .filter_map(|p| p.name.as_deref())
+        .find(|name| !numbered.names.iter().any(|n| n.as_deref() == Some(*name)))
+    {
+        return Err(format!("The query has no placeholder :{unknown}").into());
+    }
+
+    Ok(matched)
+}
+
+/// The name the server prints for the type `name` stands for, which is safe to
+/// splice into SQL. Unknown types and anything that does not parse as a type
+/// name are refused.
+async fn resolve_type(
+    conn: &mut PgConnection,
+    name: &str,
+    label: &str,
+) -> Result<String, CommandError> {
+    let resolved: Option<String> = sqlx::query_scalar("SELECT format_type(to_regtype($1), NULL)")
+        .bind(name)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(_) => format!("\"{name}\" given for {label} is not a type name").into(),
+            None => CommandError::sqlx("Query error", e),
+        })?;
+    resolved.ok_or_else(|| format!("Type \"{name}\" given for {label} does not exist").into())
+}
+
+/// Rewrites `sql` so every placeholder takes a text value, returning the
+/// statement, the values to bind in order and a map from positions in the
+/// statement back to `sql`. Types that were not declared are inferred from
+/// the statement first.
+pub(crate) async fn bind_parameters(
+    conn: &mut PgConnection,
+    sql: &str,
+    parameters: &[QueryParameter],
+) -> Result<(String, Vec<Option<String>>, SourceMap), CommandError> {
+    let numbered = number_placeholders(sql);
+    let matched = match_parameters(&numbered, parameters)?;
+    if matched.is_empty() {
+        return Ok((sql.to_string(), Vec::new(), SourceMap::default()));
+    }
+
+    let inferred = if matched.iter().any(|p| p.data_type.is_none()) {
+        parameter_types(conn, &numbered.sql)
+            .await
+            .map_err(|e| CommandError::sqlx("Query error", e).at_source(&numbered.source))?
+    } else {
+        Vec::new()
+    };
+
+    let mut types = Vec::with_capacity(matched.len());
+    for (index, parameter) in matched.iter().enumerate() {
+        let data_type = match &parameter.data_type {
+            Some(name) => {
+                let label = placeholder_label(index + 1, numbered.names[index].as_deref());
+                resolve_type(conn, name, &label).await?
+            }
+            None => inferred
+                .get(index)
+                .map(|(data_type, _)| data_type.clone())
+                .unwrap_or_else(|| "text".to_string()),
+        };
+        types.push(data_type);
+    }
+    let binds = matched.iter().map(|p| p.value.clone()).collect();
+
+    let (sql, source) = cast_placeholders(&numbered, &types);
+    Ok((sql, binds, source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(name: Option<&str>, value: &str) -> QueryParameter {
+        QueryParameter {
+            name: name.map(str::to_string),
+            value: Some(value.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn matched(sql: &str, parameters: &[QueryParameter]) -> Result<Vec<String>, String> {
+        match_parameters(&number_placeholders(sql), parameters)
+            .map(|matched| {
+                matched
+                    .into_iter()
+                    .map(|p| p.value.clone().unwrap_or_default())
+                    .collect()
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn matches_names_and_positions() {
+        let parameters = [
+            value(Some("b"), "B"),
+            value(None, "first"),
+            value(Some("a"), "A"),
+            value(None, "second"),
+        ];
+
+        assert_eq!(
+            matched("SELECT $2, :a, $1, :b, :a", &parameters),
+            Ok(vec!["first", "second", "A", "B"]
+                .into_iter()
+                .map(str::to_string)
+                .collect())
+        );
+    }
+
+    #[test]
+    fn reports_missing_and_unused_values() {
+        assert_eq!(
+            matched("SELECT :a, :b", &[value(Some("a"), "A")]),
+            Err("No value given for :b".to_string())
+        );
+        assert_eq!(
+            matched("SELECT $1, $2", &[value(None, "1")]),
+            Err("No value given for $2".to_string())
+        );
+        assert_eq!(
+            matched("SELECT $1", &[value(None, "1"), value(None, "2")]),
+            Err("2 values given for 1 positional placeholder(s)".to_string())
+        );
+        assert_eq!(
+            matched("SELECT ':a'", &[value(Some("a"), "A")]),
+            Err("The query has no placeholder :a".to_string())
+        );
+    }
+}
@@ -0,0 +1,609 @@
+//! Side-by-side comparison of two saved plans, e.g. before and after adding an
+//! index.
+//!
+//! The trees are aligned node by node: children are paired with the node on
+//! the other side that scans the same relation with the same node type, then
+//! the same relation, then the same node type, and the rest in order. Whatever
+//! has no counterpart is reported as added or removed.
+
+use serde_json::Value;
+
+use super::error::CommandError;
+use super::explain::{ExplainResult, PlanNode};
+
+/// Changes within this share of the old value count as unchanged.
+const UNCHANGED_MARGIN: f64 = 0.05;
+/// How many nodes the summary names as the main changes.
+const MAIN_CHANGES: usize = 3;
+
+/// One figure in both plans.
+#[derive(Debug, Clone, Copy, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Delta {
+    pub before: Option<f64>,
+    pub after: Option<f64>,
+    /// `after - before`, when both are known.
+    pub change: Option<f64>,
+}
+
+impl Delta {
+    fn new(before: Option<f64>, after: Option<f64>) -> Self {
+        Self {
+            before,
+            after,
+            change: before.zip(after).map(|(before, after)| after - before),
+        }
+    }
+
+    fn of(
+        before: Option<&PlanNode>,
+        after: Option<&PlanNode>,
+        value: impl Fn(&PlanNode) -> Option<f64>,
+    ) -> Self {
+        Self::new(before.and_then(&value), after.and_then(&value))
+    }
+
+    /// How far the figure moved, counting one only a single plan has as
+    /// appearing from or disappearing to nothing.
+    fn shift(&self) -> Option<f64> {
+        self.change
+            .or(self.after)
+            .or(self.before.map(|before| -before))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NodeStatus {
+    /// The same kind of node in both plans.
+    Matched,
+    /// The node's place is taken by a different node type, e.g. a
+    /// `Seq Scan` that became an `Index Scan`.
+    TypeChanged,
+    /// Only in the second plan.
+    Added,
+    /// Only in the first plan.
+    Removed,
+}
+
+/// A pair of aligned nodes, or a node without a counterpart.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeDiff {
+    /// Depth in the aligned tree, for indenting.
+    pub depth: usize,
+    pub status: NodeStatus,
+    pub before_node_type: Option<String>,
+    pub after_node_type: Option<String>,
+    pub relation: Option<String>,
+    pub before_index_name: Option<String>,
+    pub after_index_name: Option<String>,
+    pub total_cost: Delta,
+    /// Cost of the node alone, children excluded.
+    pub own_cost: Delta,
+    pub estimated_rows: Delta,
+    /// Rows per loop, with ANALYZE.
+    pub actual_rows: Delta,
+    /// Milliseconds over all loops, children included.
+    pub time_ms: Delta,
+    /// Milliseconds over all loops, children excluded.
+    pub exclusive_time_ms: Delta,
+    pub shared_hit: Delta,
+    pub shared_read: Delta,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Verdict {
+    Faster,
+    Slower,
+    Unchanged,
+}
+
+/// What the verdict was judged on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VerdictBasis {
+    /// Both plans were run with ANALYZE.
+    ExecutionTime,
+    /// At least one plan has estimates only.
+    EstimatedCost,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanComparison {
+    /// Aligned nodes, in tree order.
+    pub nodes: Vec<NodeDiff>,
+    pub planning_time_ms: Delta,
+    pub execution_time_ms: Delta,
+    pub total_cost: Delta,
+    pub verdict: Verdict,
+    pub basis: VerdictBasis,
+    /// Old over new: `2.0` is twice as fast, `0.5` twice as slow.
+    pub speedup: Option<f64>,
+    /// Indexes into `nodes` of the biggest changes in the figure the verdict
+    /// is based on, biggest first.
+    pub main_changes: Vec<usize>,
+    /// The verdict in a sentence, naming where it came from.
+    pub summary: String,
+}
+
+/// Reads a saved plan: `EXPLAIN (FORMAT JSON)` output, as a value or as text,
+/// or a saved `explain_query` result.
+fn read_plan(plan: Value) -> Result<ExplainResult, CommandError> {
+    let plan = match plan {
+        Value::String(text) => {
+            serde_json::from_str(&text).map_err(|e| format!("The plan is not valid JSON: {e}"))?
+        }
+        plan => plan,
+    };
+    let plan = match plan {
+        Value::Object(mut object) if object.contains_key("raw") => {
+            object.remove("raw").unwrap_or_default()
+        }
+        Value::Object(object) => Value::Array(vec![Value::Object(object)]),
+        plan => plan,
+    };
+    ExplainResult::parse(plan)
+}
+
+/// How alike two nodes are, for pairing children; `0` is not alike at all.
+fn likeness(before: &PlanNode, after: &PlanNode) -> u8 {
+    let same_type = before.node_type == after.node_type;
+    let same_relation = before.relation.is_some() && before.relation == after.relation;
+    match (same_relation, same_type) {
+        (true, true) => 4,
+        (true, false) => 3,
+        (false, true) => 2,
+        _ if before.parent_relationship.is_some()
+            && before.parent_relationship == after.parent_relationship =>
+        {
+            1
+        }
+        _ => 0,
+    }
+}
+
+/// Pairs up two lists of children: the most alike pairs first, then the
+/// leftovers in order.
+fn pair_children<'a>(
+    before: &'a [PlanNode],
+    after: &'a [PlanNode],
+) -> Vec<(Option<&'a PlanNode>, Option<&'a PlanNode>)> {
+    let mut candidates: Vec<(u8, usize, usize)> = before
+        .iter()
+        .enumerate()
+        .flat_map(|(i, b)| {
+            after
+                .iter()
+                .enumerate()
+                .map(move |(j, a)| (likeness(b, a), i, j))
+        })
+        .filter(|&(likeness, _, _)| likeness > 0)
+        .collect();
+    candidates.sort_by_key(|&(likeness, i, j)| (std::cmp::Reverse(likeness), i.abs_diff(j), i));
+
+    let mut partner: Vec<Option<usize>> = vec![None; before.len()];
+    let mut taken = vec![false; after.len()];
+    for (_, i, j) in candidates {
+        if partner[i].is_none() && !taken[j] {
+            partner[i] = Some(j);
+            taken[j] = true;
+        }
+    }
+    let leftovers: Vec<usize> = (0..after.len()).filter(|&j| !taken[j]).collect();
+    let mut leftovers = leftovers.into_iter();
+    for slot in partner.iter_mut().filter(|slot| slot.is_none()) {
+        if let Some(j) = leftovers.next() {
+            *slot = Some(j);
+            taken[j] = true;
+        }
+    }
+
+    let mut pairs: Vec<_> = before
+        .iter()
+        .zip(&partner)
+        .map(|(b, j)| (Some(b), j.map(|j| &after[j])))
+        .collect();
+    pairs.extend(
+        after
+            .iter()
+            .zip(&taken)
+            .filter(|(_, &taken)| !taken)
+            .map(|(a, _)| (None, Some(a))),
+    );
+    pairs
+}
+
+fn own_cost(node: &PlanNode) -> Option<f64> {
+    let below: f64 = node.children.iter().map(|child| child.total_cost).sum();
+    Some((node.total_cost - below).max(0.0))
+}
+
+fn node_time(node: &PlanNode) -> Option<f64> {
+    node.actual_total_time_ms
+        .zip(node.loops)
+        .map(|(time, loops)| time * loops as f64)
+}
+
+fn align(
+    before: Option<&PlanNode>,
+    after: Option<&PlanNode>,
+    depth: usize,
+    nodes: &mut Vec<NodeDiff>,
+) {
+    let status = match (before, after) {
+        (Some(b), Some(a)) if b.node_type == a.node_type => NodeStatus::Matched,
+        (Some(_), Some(_)) => NodeStatus::TypeChanged,
+        (None, _) => NodeStatus::Added,
+        (Some(_), None) => NodeStatus::Removed,
+    };
+
+    nodes.push(NodeDiff {
+        depth,
+        status,
+        before_node_type: before.map(|n| n.node_type.clone()),
+        after_node_type: after.map(|n| n.node_type.clone()),
+        relation: before
+            .and_then(|n| n.relation.clone())
+            .or_else(|| after.and_then(|n| n.relation.clone())),
+        before_index_name: before.and_then(|n| n.index_name.clone()),
+        after_index_name: after.and_then(|n| n.index_name.clone()),
+        total_cost: Delta::of(before, after, |n| Some(n.total_cost)),
+        own_cost: Delta::of(before, after, own_cost),
+        estimated_rows: Delta::of(before, after, |n| Some(n.estimated_rows)),
+        actual_rows: Delta::of(before, after, |n| n.actual_rows),
+        time_ms: Delta::of(before, after, node_time),
+        exclusive_time_ms: Delta::of(before, after, |n| n.exclusive_time_ms),
+        shared_hit: Delta::of(before, after, |n| {
+            n.buffers.as_ref().map(|b| b.shared_hit as f64)
+        }),
+        shared_read: Delta::of(before, after, |n| {
+            n.buffers.as_ref().map(|b| b.shared_read as f64)
+        }),
+    });
+
+    let no_children: &[PlanNode] = &[];
+    let pairs = pair_children(
+        before.map_or(no_children, |n| &n.children),
+        after.map_or(no_children, |n| &n.children),
+    );
+    for (b, a) in pairs {
+        align(b, a, depth + 1, nodes);
+    }
+}
+
+/// Names a node and what became of it, e.g. `Seq Scan on orders became Index
+/// Scan using orders_customer_idx`.
+fn describe_node(node: &NodeDiff) -> String {
+    let on = node
+        .relation
+        .as_ref()
+        .map(|relation| format!(" on {relation}"))
+        .unwrap_or_default();
+    let using = |index: &Option<String>| {
+        index
+            .as_ref()
+            .map(|index| format!(" using {index}"))
+            .unwrap_or_default()
+    };
+    let before = node.before_node_type.as_deref().unwrap_or_default();
+    let after = node.after_node_type.as_deref().unwrap_or_default();
+
+    match node.status {
+        NodeStatus::Matched => format!("{after}{on}{}", using(&node.after_index_name)),
+        NodeStatus::TypeChanged => format!(
+            "{before}{on}{} became {after}{}",
+            using(&node.before_index_name),
+            using(&node.after_index_name)
+        ),
+        NodeStatus::Added => format!("new {after}{on}{}", using(&node.after_index_name)),
+        NodeStatus::Removed => format!("{before}{on} is gone"),
+    }
+}
+
+/// Compares two saved plans node by node and judges whether the query got
+/// faster: on execution time when both were run with ANALYZE, otherwise on
+/// the planner's estimated cost.
+#[tauri::command]
+pub async fn compare_plans(before: Value, after: Value) -> Result<PlanComparison, CommandError> {
+    let before = read_plan(before)?;
+    let after = read_plan(after)?;
+
+    let mut nodes = Vec::new();
+    align(Some(&before.plan), Some(&after.plan), 0, &mut nodes);
+
+    let execution_time_ms = Delta::new(before.execution_time_ms, after.execution_time_ms);
+    let total_cost = Delta::new(Some(before.plan.total_cost), Some(after.plan.total_cost));
+    let (basis, measure) = match execution_time_ms.change {
+        Some(_) => (VerdictBasis::ExecutionTime, execution_time_ms),
+        None => (VerdictBasis::EstimatedCost, total_cost),
+    };
+
+    let (old, new) = (
+        measure.before.unwrap_or_default(),
+        measure.after.unwrap_or_default(),
+    );
+    let speedup = (new > 0.0).then(|| old / new);
+    let slowdown = (old > 0.0).then(|| new / old);
+    let verdict = if (new - old).abs() <= old * UNCHANGED_MARGIN {
+        Verdict::Unchanged
+    } else if new < old {
+        Verdict::Faster
+    } else {
+        Verdict::Slower
+    };
+
+    let node_change = |node: &NodeDiff| match basis {
+        VerdictBasis::ExecutionTime => node.exclusive_time_ms.shift(),
+        VerdictBasis::EstimatedCost => node.own_cost.shift(),
+    };
+    let mut ranked: Vec<(usize, f64)> = nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, node)| node_change(node).map(|change| (index, change)))
+        .filter(|&(_, change)| change != 0.0)
+        .collect();
+    ranked.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+    let main_changes: Vec<usize> = ranked
+        .iter()
+        .take(MAIN_CHANGES)
+        .map(|&(index, _)| index)
+        .collect();
+
+    let unit = match basis {
+        VerdictBasis::ExecutionTime => " ms",
+        VerdictBasis::EstimatedCost => "",
+    };
+    let what = match basis {
+        VerdictBasis::ExecutionTime => "Execution time",
+        VerdictBasis::EstimatedCost => "Estimated cost",
+    };
+    let headline = match (verdict, speedup, slowdown) {
+        (Verdict::Unchanged, _, _) => {
+            format!("{what} is about the same ({old:.2}{unit} → {new:.2}{unit})")
+        }
+        (Verdict::Faster, Some(speedup), _) => {
+            format!("{speedup:.1}x faster: {what} {old:.2}{unit} → {new:.2}{unit}")
+        }
+        (Verdict::Faster, None, _) => format!("Faster: {what} {old:.2}{unit} → {new:.2}{unit}"),
+        (Verdict::Slower, _, Some(slowdown)) => {
+            format!("{slowdown:.1}x slower: {what} {old:.2}{unit} → {new:.2}{unit}")
+        }
+        (Verdict::Slower, _, None) => format!("Slower: {what} {old:.2}{unit} → {new:.2}{unit}"),
+    };
+    let mut summary = headline;
+    if !main_changes.is_empty() {
+        let changes: Vec<String> = ranked
+            .iter()
+            .take(MAIN_CHANGES)
+            .map(|&(index, change)| {
+                format!("{} ({change:+.2}{unit})", describe_node(&nodes[index]))
+            })
+            .collect();
+        summary.push_str(&format!(". Biggest changes: {}", changes.join("; ")));
+    }
+    if basis == VerdictBasis::EstimatedCost {
+        summary.push_str(". Run both with ANALYZE to compare actual timings");
+    }
+
+    Ok(PlanComparison {
+        nodes,
+        planning_time_ms: Delta::new(before.planning_time_ms, after.planning_time_ms),
+        execution_time_ms,
+        total_cost,
+        verdict,
+        basis,
+        speedup,
+        main_changes,
+        summary,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn node(node_type: &str, relation: Option<&str>, cost: f64, children: Vec<Value>) -> Value {
+        let mut node = json!({
+            "Node Type": node_type,
+            "Startup Cost": 0.0,
+            "Total Cost": cost,
+            "Plan Rows": 100,
+        });
+        if let Some(relation) = relation {
+            node["Relation Name"] = json!(relation);
+        }
+        if !children.is_empty() {
+            node["Plans"] = json!(children);
+        }
+        node
+    }
+
+    fn leaf(node_type: &str, relation: Option<&str>) -> PlanNode {
+        PlanNode::parse(&node(node_type, relation, 1.0, vec![]), None)
+    }
+
+    fn plan(root: Value, execution_time_ms: Option<f64>) -> Value {
+        match execution_time_ms {
+            Some(time) => json!([{ "Plan": root, "Execution Time": time }]),
+            None => json!([{ "Plan": root }]),
+        }
+    }
+
+    fn types<'a>(
+        pairs: &[(Option<&'a PlanNode>, Option<&'a PlanNode>)],
+    ) -> Vec<(&'a str, &'a str)> {
+        let name = |node: Option<&'a PlanNode>| node.map_or("-", |n| n.node_type.as_str());
+        pairs.iter().map(|&(b, a)| (name(b), name(a))).collect()
+    }
+
+    #[test]
+    fn pairs_children_on_the_same_relation_first() {
+        let before = [
+            leaf("Seq Scan", Some("orders")),
+            leaf("Seq Scan", Some("customers")),
+        ];
+        let after = [
+            leaf("Index Scan", Some("customers")),
+            leaf("Seq Scan", Some("orders")),
+        ];
+
+        let pairs = pair_children(&before, &after);
+
+        assert_eq!(
+            types(&pairs),
+            vec![("Seq Scan", "Seq Scan"), ("Seq Scan", "Index Scan")]
+        );
+        assert_eq!(pairs[1].1.unwrap().relation.as_deref(), Some("customers"));
+    }
+
+    #[test]
+    fn leaves_children_without_a_counterpart_unpaired() {
+        let orders = leaf("Seq Scan", Some("orders"));
+        let customers = leaf("Seq Scan", Some("customers"));
+
+        let removed = pair_children(std::slice::from_ref(&customers), &[]);
+        assert_eq!(types(&removed), vec![("Seq Scan", "-")]);
+
+        let before = [orders.clone()];
+        let after = [orders, customers];
+        let added = pair_children(&before, &after);
+        assert_eq!(
+            types(&added),
+            vec![("Seq Scan", "Seq Scan"), ("-", "Seq Scan")]
+        );
+        assert_eq!(added[1].1.unwrap().relation.as_deref(), Some("customers"));
+    }
+
+    #[test]
+    fn pairs_unlike_leftovers_in_order() {
+        let (before, after) = ([leaf("Sort", None)], [leaf("Materialize", None)]);
+        let pairs = pair_children(&before, &after);
+        assert_eq!(types(&pairs), vec![("Sort", "Materialize")]);
+    }
+
+    #[tokio::test]
+    async fn aligns_changed_added_and_removed_nodes() {
+        let before = plan(
+            node(
+                "Hash Join",
+                None,
+                300.0,
+                vec![
+                    node("Seq Scan", Some("orders"), 200.0, vec![]),
+                    node(
+                        "Hash",
+                        None,
+                        50.0,
+                        vec![node("Seq Scan", Some("customers"), 50.0, vec![])],
+                    ),
+                ],
+            ),
+            None,
+        );
+        let after = plan(
+            node(
+                "Hash Join",
+                None,
+                120.0,
+                vec![
+                    node("Index Scan", Some("orders"), 40.0, vec![]),
+                    node(
+                        "Hash",
+                        None,
+                        30.0,
+                        vec![node(
+                            "Bitmap Heap Scan",
+                            Some("customers"),
+                            30.0,
+                            vec![node("Bitmap Index Scan", None, 5.0, vec![])],
+                        )],
+                    ),
+                ],
+            ),
+            None,
+        );
+        let statuses = |comparison: &PlanComparison| {
+            comparison
+                .nodes
+                .iter()
+                .map(|n| (n.depth, n.status))
+                .collect::<Vec<_>>()
+        };
+
+        let forward = compare_plans(before.clone(), after.clone()).await.unwrap();
+        assert_eq!(
+            statuses(&forward),
+            vec![
+                (0, NodeStatus::Matched),
+                (1, NodeStatus::TypeChanged),
+                (1, NodeStatus::Matched),
+                (2, NodeStatus::TypeChanged),
+                (3, NodeStatus::Added),
+            ]
+        );
+        assert_eq!(forward.nodes[1].total_cost.change, Some(-160.0));
+        assert_eq!(forward.nodes[4].total_cost.before, None);
+
+        let backward = compare_plans(after, before).await.unwrap();
+        assert_eq!(backward.nodes[4].status, NodeStatus::Removed);
+        assert!(backward.summary.contains("Bitmap Index Scan is gone"));
+    }
+
+    #[tokio::test]
+    async fn judges_on_execution_time_when_both_plans_ran() {
+        let before = plan(node("Seq Scan", Some("t"), 100.0, vec![]), Some(100.0));
+        let after = plan(node("Index Scan", Some("t"), 400.0, vec![]), Some(25.0));
+
+        let comparison = compare_plans(before, after).await.unwrap();
+
+        assert_eq!(comparison.basis, VerdictBasis::ExecutionTime);
+        assert_eq!(comparison.verdict, Verdict::Faster);
+        assert_eq!(comparison.speedup, Some(4.0));
+        assert!(comparison
+            .summary
+            .starts_with("4.0x faster: Execution time"));
+    }
+
+    #[tokio::test]
+    async fn judges_on_estimated_cost_unless_both_plans_ran() {
+        let before = plan(node("Seq Scan", Some("t"), 100.0, vec![]), Some(10.0));
+        let after = plan(node("Seq Scan", Some("t"), 300.0, vec![]), None);
+
+        let comparison = compare_plans(before, after).await.unwrap();
+
+        assert_eq!(comparison.basis, VerdictBasis::EstimatedCost);
+        assert_eq!(comparison.verdict, Verdict::Slower);
+        assert!(comparison
+            .summary
+            .starts_with("3.0x slower: Estimated cost"));
+        assert!(comparison.summary.ends_with("to compare actual timings"));
+    }
+
+    #[tokio::test]
+    async fn treats_small_changes_as_unchanged() {
+        let before = plan(node("Seq Scan", Some("t"), 100.0, vec![]), None);
+        let after = plan(node("Seq Scan", Some("t"), 104.0, vec![]), None);
+
+        let comparison = compare_plans(before, after).await.unwrap();
+
+        assert_eq!(comparison.verdict, Verdict::Unchanged);
+    }
+
+    #[tokio::test]
+    async fn does_not_divide_by_a_zero_before() {
+        let before = plan(node("Result", None, 0.0, vec![]), Some(0.0));
+        let after = plan(node("Result", None, 0.0, vec![]), Some(5.0));
+
+        let comparison = compare_plans(before, after).await.unwrap();
+
+        assert_eq!(comparison.verdict, Verdict::Slower);
+        assert_eq!(comparison.speedup, Some(0.0));
+        assert!(comparison
+            .summary
+            .starts_with("Slower: Execution time 0.00 ms → 5.00 ms"));
+    }
+}
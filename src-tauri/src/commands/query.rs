@@ -1,196 +1,694 @@
-use sqlx::postgres::PgRow;
-use sqlx::{Column, Row, TypeInfo};
+use sqlx::postgres::types::Oid;
+use sqlx::postgres::{PgColumn, PgConnection, PgStatement};
+use sqlx::{Column, Executor, Row, Statement, TypeInfo};
+use std::time::Duration;
 use tauri::State;
 
 use super::connection::AppState;
+use super::cursor::RowCursor;
+use super::decode::{DisplayZone, ValueStyle};
+use super::error::CommandError;
+use super::history::record_query;
+use super::jobs::{cancel_job, finish_job, start_job, JobConnection};
+use super::notices::ServerNotice;
+use super::params::{bind_parameters, describe_parameters, QueryDescription, QueryParameter};
+use super::safety::check_destructive;
+use super::session::{discard_tab_session, tab_transaction_state, TransactionState};
+use super::sql::{number_placeholders, statement_kind, SourceMap, StatementKind};
+use super::timeouts::{override_timeouts, SavedSettings, TimeoutKind, Timeouts};
+
+const DEFAULT_PAGE_SIZE: usize = 500;
+pub(crate) const DEFAULT_MAX_ROWS: usize = 100_000;
+/// How long past its statement timeout a query is given before the app stops
+/// waiting for the server to enforce it.
+const CLIENT_TIMEOUT_GRACE: Duration = Duration::from_secs(5);
+
+/// A result column as described by the server's `RowDescription`, enriched
+/// from `pg_attribute` when the column comes straight from a table.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultColumn {
+    pub name: String,
+    /// Type name as used for decoding, e.g. `INT4` or a user type's name.
+    pub type_name: String,
+    pub type_oid: Option<u32>,
+    /// Type as PostgreSQL prints it, modifier included, e.g. `numeric(12,2)`.
+    pub data_type: String,
+    /// Raw `atttypmod`; `-1` when there is none or it is unknown.
+    pub type_modifier: i32,
+    /// `None` for computed columns, whose nullability cannot be known.
+    pub nullable: Option<bool>,
+    pub table_oid: Option<u32>,
+    /// Schema-qualified name of the source table, when visible.
+    pub table_name: Option<String>,
+    pub column_number: Option<i16>,
+}
 
 #[derive(Debug, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QueryResult {
-    pub columns: Vec<String>,
+    pub columns: Vec<ResultColumn>,
     pub rows: Vec<Vec<serde_json::Value>>,
     pub execution_time_ms: u64,
+    pub statement_kind: StatementKind,
+    pub rows_affected: Option<u64>,
+    pub result_id: Option<String>,
+    /// Job the open result set stays registered as while it has rows left, so
+    /// `cancel_query` can stop a page that is slow to arrive.
+    pub job_id: Option<String>,
+    pub has_more: bool,
+    pub row_limit_reached: bool,
+    pub cancelled: bool,
+    /// Transaction state of the tab's session once the statement has run.
+    pub transaction_state: TransactionState,
+    /// Notices the statement raised while its first page was read.
+    pub notices: Vec<ServerNotice>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryPage {
+    pub rows: Vec<Vec<serde_json::Value>>,
     pub rows_affected: Option<u64>,
+    pub has_more: bool,
+    pub row_limit_reached: bool,
+    /// Set when `cancel_query` stopped the page; the result set is closed.
+    pub cancelled: bool,
+    /// Notices the statement raised while this page was read.
+    pub notices: Vec<ServerNotice>,
 }
 
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryOptions {
+    pub page_size: Option<usize>,
+    pub max_rows: Option<usize>,
+    /// Zone TIMESTAMPTZ and TIMETZ values are shown in.
+    #[serde(default)]
+    pub display_zone: DisplayZone,
+    /// Client-chosen id that `cancel_query` can refer to while this runs.
+    pub job_id: Option<String>,
+    /// Editor tab the query was run from.
+    pub tab_id: Option<String>,
+    /// Values for `$1` or `:name` placeholders. Without them the query is sent
+    /// as written.
+    pub parameters: Option<Vec<QueryParameter>>,
+    /// Limits for this execution only, in place of the connection's.
+    pub timeouts: Option<Timeouts>,
+    /// Token from a `confirmationRequired` error, to run the destructive
+    /// statement it was issued for.
+    pub confirmation_token: Option<String>,
+}
+
+/// A statement to run again: its text, parameters and tab.
+pub(crate) type Rerun = (String, Option<Vec<QueryParameter>>, Option<String>);
+
+/// A result set that still has rows waiting on the server.
+///
+/// The job that produced it stays registered until the result set is exhausted
+/// or closed, so fetching a later page can be cancelled as well.
+pub struct OpenResult {
+    cursor: RowCursor,
+    job_id: String,
+    /// The statement as run, for running it again from the start.
+    query: String,
+    parameters: Option<Vec<QueryParameter>>,
+    tab_id: Option<String>,
+    kind: StatementKind,
+    /// Limits the statement runs under, for describing a timeout.
+    limits: Timeouts,
+    page_size: usize,
+    max_rows: usize,
+    fetched: usize,
+}
+
+impl OpenResult {
+    /// The query, parameters and tab that produced this result, for reading
+    /// its rows again. Only a query can be run twice without doing its work
+    /// twice, so other statements are refused.
+    pub(crate) fn rerun(&self) -> Result<Rerun, CommandError> {
+        if self.kind != StatementKind::Query {
+            return Err("Only the results of a query can be run again;                  this statement would change data a second time"
+                .into());
+        }
+        Ok((
+            self.query.clone(),
+            self.parameters.clone(),
+            self.tab_id.clone(),
+        ))
+    }
+
+    /// Row counts are only reported for statements other than plain queries,
+    /// where the grid already shows how many rows came back.
+    fn rows_affected(&self, completed: Option<u64>) -> Option<u64> {
+        match self.kind {
+            StatementKind::Query => None,
+            _ => completed,
+        }
+    }
+
+    async fn next_page(&mut self, page_size: usize) -> Result<QueryPage, CommandError> {
+        let limit = page_size.min(self.max_rows - self.fetched);
+        let page = self
+            .cursor
+            .fetch(limit)
+            .await
+            .map_err(|e| self.limits.classify(e))?;
+        self.fetched += page.rows.len();
+
+        let row_limit_reached = !page.exhausted && self.fetched >= self.max_rows;
+
+        Ok(QueryPage {
+            rows: page.rows,
+            rows_affected: self.rows_affected(page.rows_affected),
+            has_more: !page.exhausted && !row_limit_reached,
+            row_limit_reached,
+            cancelled: false,
+            notices: page.notices,
+        })
+    }
+}
+
+/// Runs a query and logs it to the query history.
 #[tauri::command]
 pub async fn execute_query(
     query: String,
+    options: Option<QueryOptions>,
     state: State<'_, AppState>,
-) -> Result<QueryResult, String> {
-    let guard = state.pool.lock().await;
-    let pool = guard.as_ref().ok_or("Not connected to a database")?;
+) -> Result<QueryResult, CommandError> {
+    let options = options.unwrap_or_default();
+    let tab_id = options.tab_id.clone();
+    let started_at = chrono::Utc::now();
 
-    let start = std::time::Instant::now();
-    let trimmed = query.trim().to_uppercase();
+    let outcome = execute(&query, options, &state).await;
+    record_query(&state, &query, tab_id, started_at, &outcome).await;
+    outcome
+}
 
-    if trimmed.starts_with("SELECT")
-        || trimmed.starts_with("WITH")
-        || trimmed.starts_with("SHOW")
-        || trimmed.starts_with("EXPLAIN")
-    {
-        let rows: Vec<PgRow> = sqlx::query(&query)
-            .fetch_all(pool)
-            .await
-            .map_err(|e| format!("Query error: {}", e))?;
+async fn execute(
+    query: &str,
+    options: QueryOptions,
+    state: &AppState,
+) -> Result<QueryResult, CommandError> {
+    let page_size = options.page_size.unwrap_or(DEFAULT_PAGE_SIZE).max(1);
+    let max_rows = options.max_rows.unwrap_or(DEFAULT_MAX_ROWS).max(1);
+    let job_id = options
+        .job_id
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
-        let execution_time_ms = start.elapsed().as_millis() as u64;
+    let pool = state.pool().await?;
+    check_destructive(state, query, [query], options.confirmation_token.as_deref()).await?;
 
-        if rows.is_empty() {
-            return Ok(QueryResult {
-                columns: vec![],
-                rows: vec![],
-                execution_time_ms,
-                rows_affected: None,
-            });
-        }
+    let limits = state.timeouts().await.merged(options.timeouts);
+    let tab_id = options.tab_id;
+    let mut conn = start_job(state, &job_id, tab_id.clone(), query).await?;
 
-        let columns: Vec<String> = rows[0]
-            .columns()
-            .iter()
-            .map(|c| c.name().to_string())
-            .collect();
-
-        let result_rows: Vec<Vec<serde_json::Value>> = rows
-            .iter()
-            .map(|row| {
-                (0..row.columns().len())
-                    .map(|i| pg_value_to_json(row, i))
-                    .collect()
-            })
-            .collect();
+    let start = std::time::Instant::now();
+    let kind = statement_kind(query);
+    let (sql, binds, saved, source) = match prepare_execution(
+        &mut conn,
+        query,
+        options.parameters.as_deref(),
+        options.timeouts,
+    )
+    .await
+    {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            conn.statement_finished(query, true).await;
+            drop(conn);
+            finish_job(state, &job_id).await;
+            return Err(e);
+        }
+    };
+    let cursor = RowCursor::open(
+        conn,
+        pool,
+        sql,
+        binds,
+        options.display_zone,
+        ValueStyle::Grid,
+        saved,
+    );
+    let open = OpenResult {
+        cursor,
+        job_id: job_id.clone(),
+        query: query.to_string(),
+        parameters: options.parameters,
+        tab_id: tab_id.clone(),
+        kind,
+        limits,
+        page_size,
+        max_rows,
+        fetched: 0,
+    };
 
+    // The server enforces the statement timeout; this only covers a server
+    // that cannot be reached to do so.
+    let outcome = match limits.statement_secs.filter(|&secs| secs > 0) {
+        Some(secs) => {
+            let wait = Duration::from_secs(secs) + CLIENT_TIMEOUT_GRACE;
+            match tokio::time::timeout(wait, run_query(open, state)).await {
+                Ok(outcome) => outcome,
+                Err(_) => {
+                    let _ = cancel_job(state, &job_id).await;
+                    finish_job(state, &job_id).await;
+                    return Err(CommandError::TimedOut {
+                        kind: TimeoutKind::Statement,
+                        seconds: Some(secs),
+                    });
+                }
+            }
+        }
+        None => run_query(open, state).await,
+    };
+    let outcome = outcome.map_err(|e| limits.classify(e.at_source(&source)));
+    // A result set left open keeps the job until it is read to the end or closed.
+    let cancelled = match &outcome {
         Ok(QueryResult {
-            columns,
-            rows: result_rows,
-            execution_time_ms,
+            result_id: Some(_), ..
+        }) => false,
+        _ => finish_job(state, &job_id).await,
+    };
+
+    if let (
+        Err(CommandError::TimedOut {
+            kind: TimeoutKind::IdleInTransaction,
+            ..
+        }),
+        Some(tab_id),
+    ) = (&outcome, &tab_id)
+    {
+        discard_tab_session(state, tab_id).await;
+    }
+
+    match outcome {
+        Err(_) if cancelled => Ok(QueryResult {
+            columns: vec![],
+            rows: vec![],
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            statement_kind: kind,
             rows_affected: None,
-        })
+            result_id: None,
+            job_id: None,
+            has_more: false,
+            row_limit_reached: false,
+            cancelled: true,
+            transaction_state: tab_transaction_state(state, tab_id.as_deref()).await,
+            notices: Vec::new(),
+        }),
+        outcome => outcome,
+    }
+}
+
+/// Binds the query's parameters and applies its timeouts, returning the SQL to
+/// run, its bound values, the settings to restore once it is done and a map
+/// from error positions in the SQL back to `query`.
+pub(crate) async fn prepare_execution(
+    conn: &mut JobConnection,
+    query: &str,
+    parameters: Option<&[QueryParameter]>,
+    timeouts: Option<Timeouts>,
+) -> Result<
+    (
+        String,
+        Vec<Option<String>>,
+        Option<SavedSettings>,
+        SourceMap,
+    ),
+    CommandError,
+> {
+    let (sql, binds, source) = match parameters {
+        Some(parameters) => bind_parameters(conn, query, parameters).await?,
+        None => (query.to_string(), Vec::new(), SourceMap::default()),
+    };
+
+    // A failed transaction refuses every statement, so the query itself is
+    // left to report that.
+    let saved = if conn.transaction_state() == TransactionState::Failed {
+        None
+    } else {
+        override_timeouts(conn, timeouts).await?
+    };
+
+    Ok((sql, binds, saved, source))
+}
+
+async fn run_query(mut open: OpenResult, state: &AppState) -> Result<QueryResult, CommandError> {
+    let start = std::time::Instant::now();
+
+    let first = open.cursor.fetch(open.page_size.min(open.max_rows)).await?;
+
+    let execution_time_ms = start.elapsed().as_millis() as u64;
+
+    open.fetched = first.rows.len();
+    let row_limit_reached = !first.exhausted && open.fetched >= open.max_rows;
+    let has_more = !first.exhausted && !row_limit_reached;
+    let rows_affected = open.rows_affected(first.rows_affected);
+    let statement_kind = open.kind;
+    let transaction_state = tab_transaction_state(state, open.tab_id.as_deref()).await;
+
+    let (result_id, job_id) = if has_more {
+        let id = uuid::Uuid::new_v4().to_string();
+        let job_id = open.job_id.clone();
+        state.results.lock().await.insert(id.clone(), open);
+        (Some(id), Some(job_id))
     } else {
-        let result = sqlx::query(&query)
-            .execute(pool)
+        (None, None)
+    };
+
+    Ok(QueryResult {
+        columns: first.columns,
+        rows: first.rows,
+        execution_time_ms,
+        statement_kind,
+        rows_affected,
+        result_id,
+        job_id,
+        has_more,
+        row_limit_reached,
+        cancelled: false,
+        transaction_state,
+        notices: first.notices,
+    })
+}
+
+/// Reports the parameter types PostgreSQL infers for a query, and the columns
+/// it would return, without running it.
+///
+/// `:name` placeholders are numbered the way `execute_query` numbers them.
+/// With a tab id the query is described in the tab's session, so its
+/// temporary tables and search path apply.
+#[tauri::command]
+pub async fn describe_query(
+    query: String,
+    tab_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<QueryDescription, CommandError> {
+    const LABEL: &str = "-- describe query";
+
+    let job_id = uuid::Uuid::new_v4().to_string();
+    let mut conn = start_job(&state, &job_id, tab_id, LABEL).await?;
+
+    let numbered = number_placeholders(&query);
+    let described = match describe_parameters(&mut conn, &numbered).await {
+        Ok(parameters) => prepare_statement(&mut conn, &numbered.sql)
             .await
-            .map_err(|e| format!("Query error: {}", e))?;
+            .map(|(_, columns)| QueryDescription {
+                parameters,
+                columns,
+            }),
+        Err(e) => Err(e),
+    };
+    conn.statement_finished(LABEL, described.is_err()).await;
+    drop(conn);
+    finish_job(&state, &job_id).await;
 
-        let execution_time_ms = start.elapsed().as_millis() as u64;
+    described.map_err(|e| CommandError::sqlx("Query error", e).at_source(&numbered.source))
+}
 
-        Ok(QueryResult {
-            columns: vec![],
-            rows: vec![],
-            execution_time_ms,
-            rows_affected: Some(result.rows_affected()),
+/// Prepares `sql` on `conn` and describes the columns it will return.
+///
+/// Preparing first means columns are known even when no rows come back. The
+/// statement is cached on the connection, so executing it afterwards does not
+/// parse it again.
+pub(crate) async fn prepare_statement<'q>(
+    conn: &mut PgConnection,
+    sql: &'q str,
+) -> Result<(PgStatement<'q>, Vec<ResultColumn>), sqlx::Error> {
+    let statement = conn.prepare(sql).await?;
+    let columns = describe_columns(conn, statement.columns()).await;
+    Ok((statement, columns))
+}
+
+async fn describe_columns(conn: &mut PgConnection, columns: &[PgColumn]) -> Vec<ResultColumn> {
+    let mut described: Vec<ResultColumn> = columns
+        .iter()
+        .map(|column| ResultColumn {
+            name: column.name().to_string(),
+            type_name: column.type_info().name().to_string(),
+            type_oid: column.type_info().oid().map(|oid| oid.0),
+            data_type: column.type_info().name().to_lowercase(),
+            type_modifier: -1,
+            nullable: None,
+            table_oid: column.relation_id().map(|oid| oid.0),
+            table_name: None,
+            column_number: column.relation_attribute_no(),
         })
+        .collect();
+
+    if described.is_empty() {
+        return described;
     }
-}
 
-fn pg_value_to_json(row: &PgRow, index: usize) -> serde_json::Value {
-    let column = &row.columns()[index];
-    let type_info = column.type_info();
-    let type_name = type_info.name();
+    let type_oids: Vec<Oid> = columns
+        .iter()
+        .map(|c| c.type_info().oid().unwrap_or(Oid(0)))
+        .collect();
+    let table_oids: Vec<Oid> = columns
+        .iter()
+        .map(|c| c.relation_id().unwrap_or(Oid(0)))
+        .collect();
+    let column_numbers: Vec<i16> = columns
+        .iter()
+        .map(|c| c.relation_attribute_no().unwrap_or(0))
+        .collect();
 
-    match type_name {
-        "INT2" | "INT4" | "INT8" | "SERIAL" | "BIGSERIAL" => {
-            if let Ok(v) = row.try_get::<i64, _>(index) {
-                serde_json::Value::Number(v.into())
-            } else if let Ok(v) = row.try_get::<i32, _>(index) {
-                serde_json::Value::Number(v.into())
-            } else if let Ok(v) = row.try_get::<i16, _>(index) {
-                serde_json::Value::Number(v.into())
-            } else {
-                serde_json::Value::Null
-            }
-        }
-        "FLOAT4" | "FLOAT8" | "NUMERIC" => {
-            if let Ok(v) = row.try_get::<f64, _>(index) {
-                serde_json::Number::from_f64(v)
-                    .map(serde_json::Value::Number)
-                    .unwrap_or(serde_json::Value::Null)
-            } else if let Ok(v) = row.try_get::<f32, _>(index) {
-                serde_json::Number::from_f64(v as f64)
-                    .map(serde_json::Value::Number)
-                    .unwrap_or(serde_json::Value::Null)
-            } else {
-                serde_json::Value::Null
-            }
-        }
-        "BOOL" => {
-            if let Ok(v) = row.try_get::<bool, _>(index) {
-                serde_json::Value::Bool(v)
-            } else {
-                serde_json::Value::Null
-            }
-        }
-        "TEXT" | "VARCHAR" | "CHAR" | "BPCHAR" | "NAME" => {
-            if let Ok(v) = row.try_get::<String, _>(index) {
-                serde_json::Value::String(v)
-            } else {
-                serde_json::Value::Null
-            }
-        }
-        "BYTEA" => {
-            if let Ok(v) = row.try_get::<Vec<u8>, _>(index) {
-                serde_json::Value::String(format!("<BLOB {} bytes>", v.len()))
-            } else {
-                serde_json::Value::Null
-            }
-        }
-        "JSON" | "JSONB" => {
-            if let Ok(v) = row.try_get::<serde_json::Value, _>(index) {
-                v
-            } else {
-                serde_json::Value::Null
-            }
-        }
-        "UUID" => {
-            if let Ok(v) = row.try_get::<uuid::Uuid, _>(index) {
-                serde_json::Value::String(v.to_string())
-            } else {
-                serde_json::Value::Null
-            }
-        }
-        "TIMESTAMP" | "TIMESTAMPTZ" => {
-            if let Ok(v) = row.try_get::<chrono::NaiveDateTime, _>(index) {
-                serde_json::Value::String(v.to_string())
-            } else if let Ok(v) = row.try_get::<chrono::DateTime<chrono::Utc>, _>(index) {
-                serde_json::Value::String(v.to_string())
-            } else if let Ok(v) = row.try_get::<String, _>(index) {
-                serde_json::Value::String(v)
-            } else {
-                serde_json::Value::Null
-            }
-        }
-        "DATE" => {
-            if let Ok(v) = row.try_get::<chrono::NaiveDate, _>(index) {
-                serde_json::Value::String(v.to_string())
-            } else if let Ok(v) = row.try_get::<String, _>(index) {
-                serde_json::Value::String(v)
-            } else {
-                serde_json::Value::Null
-            }
-        }
-        "TIME" | "TIMETZ" => {
-            if let Ok(v) = row.try_get::<chrono::NaiveTime, _>(index) {
-                serde_json::Value::String(v.to_string())
-            } else if let Ok(v) = row.try_get::<String, _>(index) {
-                serde_json::Value::String(v)
-            } else {
-                serde_json::Value::Null
+    let lookup = sqlx::query(
+        r#"
+        SELECT
+            format_type(c.type_oid, COALESCE(a.atttypmod, -1)) AS data_type,
+            COALESCE(a.atttypmod, -1) AS type_modifier,
+            CASE WHEN a.attnum IS NULL THEN NULL ELSE NOT a.attnotnull END AS nullable,
+            CASE WHEN a.attnum IS NULL THEN NULL ELSE c.table_oid::regclass::text END AS table_name
+        FROM unnest($1::oid[], $2::oid[], $3::int2[]) WITH ORDINALITY
+            AS c(type_oid, table_oid, column_number, ordinal)
+        LEFT JOIN pg_attribute a
+            ON a.attrelid = c.table_oid
+            AND a.attnum = c.column_number
+            AND NOT a.attisdropped
+        ORDER BY c.ordinal
+        "#,
+    )
+    .bind(type_oids)
+    .bind(table_oids)
+    .bind(column_numbers)
+    .fetch_all(&mut *conn)
+    .await;
+
+    // Metadata is a nicety; fall back to what RowDescription said on failure.
+    if let Ok(rows) = lookup {
+        for (column, row) in described.iter_mut().zip(rows) {
+            if let Ok(Some(data_type)) = row.try_get::<Option<String>, _>("data_type") {
+                column.data_type = data_type;
             }
+            column.type_modifier = row.try_get("type_modifier").unwrap_or(-1);
+            column.nullable = row.try_get("nullable").unwrap_or(None);
+            column.table_name = row.try_get("table_name").unwrap_or(None);
         }
-        _ => {
-            if let Ok(v) = row.try_get::<String, _>(index) {
-                serde_json::Value::String(v)
-            } else if let Ok(v) = row.try_get::<i64, _>(index) {
-                serde_json::Value::Number(v.into())
-            } else if let Ok(v) = row.try_get::<f64, _>(index) {
-                serde_json::Number::from_f64(v)
-                    .map(serde_json::Value::Number)
-                    .unwrap_or(serde_json::Value::Null)
-            } else if let Ok(v) = row.try_get::<bool, _>(index) {
-                serde_json::Value::Bool(v)
-            } else {
-                serde_json::Value::Null
-            }
+    }
+
+    described
+}
+
+/// Loads the next page of a result set returned by `execute_query`.
+#[tauri::command]
+pub async fn fetch_more_rows(
+    result_id: String,
+    page_size: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<QueryPage, CommandError> {
+    // Take the result out of the map so other result sets stay usable while
+    // this one waits on the server.
+    let mut open = state
+        .results
+        .lock()
+        .await
+        .remove(&result_id)
+        .ok_or("Result set is no longer available")?;
+
+    let page_size = page_size.unwrap_or(open.page_size).max(1);
+    let page = open.next_page(page_size).await;
+
+    if let Ok(QueryPage { has_more: true, .. }) = page {
+        state.results.lock().await.insert(result_id, open);
+        return page;
+    }
+
+    let job_id = std::mem::take(&mut open.job_id);
+    drop(open);
+    let cancelled = finish_job(&state, &job_id).await;
+    match page {
+        Err(_) if cancelled => Ok(QueryPage {
+            rows: vec![],
+            rows_affected: None,
+            has_more: false,
+            row_limit_reached: false,
+            cancelled: true,
+            notices: Vec::new(),
+        }),
+        page => page,
+    }
+}
+
+/// Discards a result set, stopping the server from producing further rows.
+#[tauri::command]
+pub async fn close_result(
+    result_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    close_results(&state, |id, _| id == result_id).await;
+    Ok(())
+}
+
+/// Discards every result set opened from `tab_id`, releasing the tab's session
+/// for its next statement.
+pub(crate) async fn close_tab_results(state: &AppState, tab_id: &str) {
+    close_results(state, |_, open| open.tab_id.as_deref() == Some(tab_id)).await;
+}
+
+/// Discards the result sets `close` picks and unregisters their jobs.
+pub(crate) async fn close_results(state: &AppState, close: impl Fn(&str, &OpenResult) -> bool) {
+    let closed: Vec<OpenResult> = state
+        .results
+        .lock()
+        .await
+        .extract_if(|id, open| close(id, open))
+        .map(|(_, open)| open)
+        .collect();
+
+    for open in closed {
+        let job_id = open.job_id.clone();
+        drop(open);
+        finish_job(state, &job_id).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ENDLESS: &str = "SELECT generate_series(1, 1000000000)";
+
+    fn in_tab(max_rows: Option<usize>) -> QueryOptions {
+        QueryOptions {
+            max_rows,
+            tab_id: Some("tab".to_string()),
+            ..Default::default()
         }
     }
+
+    #[tokio::test]
+    async fn capped_session_query_is_cancelled_rather_than_drained() {
+        let Some(state) = AppState::connected_for_tests().await else {
+            return;
+        };
+        let started = std::time::Instant::now();
+
+        let capped = execute(ENDLESS, in_tab(Some(10)), &state).await.unwrap();
+        assert_eq!(capped.rows.len(), 10);
+        assert!(capped.row_limit_reached);
+
+        let next = execute("SELECT 1", in_tab(None), &state).await.unwrap();
+        assert_eq!(next.rows, vec![vec![json!(1)]]);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_capped_query_keeps_the_transaction() {
+        let Some(state) = AppState::connected_for_tests().await else {
+            return;
+        };
+        execute("BEGIN", in_tab(None), &state).await.unwrap();
+        execute("CREATE TEMP TABLE kept (a int)", in_tab(None), &state)
+            .await
+            .unwrap();
+
+        let capped = execute(ENDLESS, in_tab(Some(10)), &state).await.unwrap();
+        assert!(capped.row_limit_reached);
+
+        let next = execute("SELECT count(*) FROM kept", in_tab(None), &state)
+            .await
+            .unwrap();
+        assert_eq!(next.transaction_state, TransactionState::InTransaction);
+        execute("ROLLBACK", in_tab(None), &state).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn returns_notices_with_their_detail_and_hint() {
+        let Some(state) = AppState::connected_for_tests().await else {
+            return;
+        };
+        let sql = "DO $$ BEGIN \
+                   RAISE NOTICE 'checked' USING DETAIL = '3 rows', HINT = 'look'; \
+                   RAISE INFO 'informed'; \
+                   RAISE WARNING 'warned'; \
+                   END $$";
+
+        let result = execute(sql, in_tab(None), &state).await.unwrap();
+
+        let notice = |severity: &str, message: &str| ServerNotice {
+            severity: severity.to_string(),
+            message: message.to_string(),
+            detail: None,
+            hint: None,
+        };
+        assert_eq!(
+            result.notices,
+            vec![
+                ServerNotice {
+                    detail: Some("3 rows".to_string()),
+                    hint: Some("look".to_string()),
+                    ..notice("NOTICE", "checked")
+                },
+                notice("INFO", "informed"),
+                notice("WARNING", "warned"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn points_errors_at_the_statement_as_written() {
+        let Some(state) = AppState::connected_for_tests().await else {
+            return;
+        };
+        let sql = "SELECT :first_value, ':a', :first_value + $1, nosuch";
+        let parameter = |name: Option<&str>, data_type: &str| QueryParameter {
+            name: name.map(str::to_string),
+            data_type: Some(data_type.to_string()),
+            value: Some("1".to_string()),
+        };
+        let options = QueryOptions {
+            parameters: Some(vec![
+                parameter(Some("first_value"), "integer"),
+                parameter(None, "bigint"),
+            ]),
+            ..Default::default()
+        };
+
+        let error = execute(sql, options, &state).await.unwrap_err();
+
+        let position = error.database().and_then(|e| e.position);
+        assert_eq!(position, Some(sql.find("nosuch").unwrap() + 1));
+    }
+
+    #[tokio::test]
+    async fn returns_money_as_an_exact_amount() {
+        let Some(state) = AppState::connected_for_tests().await else {
+            return;
+        };
+        let sql = "SELECT '1234.5'::money, ARRAY['-0.07'::money], '-92233720368547758.08'::money";
+
+        let result = execute(sql, QueryOptions::default(), &state).await.unwrap();
+
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                json!("1234.50"),
+                json!(["-0.07"]),
+                json!("-92233720368547758.08")
+            ]]
+        );
+    }
 }
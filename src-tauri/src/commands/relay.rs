@@ -0,0 +1,260 @@
+//! A loopback relay every database connection of the app goes through, so the
+//! server's notices can be read in full.
+//!
+//! The relay copies bytes both ways unchanged. On the way from the server it
+//! follows the message framing, learns the backend's process id from
+//! `BackendKeyData` and hands each `NoticeResponse` to [`notices`] before
+//! passing on the bytes that complete it. sqlx is built without TLS, so the
+//! protocol is always plain to read. The relay only listens on the loopback
+//! interface and reaches nothing a local process could not reach directly.
+
+use sqlx::postgres::PgConnectOptions;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use super::error::CommandError;
+use super::notices::{self, ServerNotice};
+
+/// Bytes read from the server at a time.
+const READ_BUFFER: usize = 64 * 1024;
+/// How long to wait before accepting again after accepting failed, for
+/// instance because the process ran out of file descriptors.
+const ACCEPT_RETRY: Duration = Duration::from_millis(50);
+
+/// Relays connections to one server until dropped. Connections already
+/// relayed keep going until either side closes them.
+pub struct NoticeRelay {
+    port: u16,
+    accepting: JoinHandle<()>,
+}
+
+impl NoticeRelay {
+    /// Starts relaying to the server `options` point at, checking it can be
+    /// reached first so a wrong address is reported as such.
+    pub async fn start(options: &PgConnectOptions) -> Result<Self, CommandError> {
+        let server = (options.get_host().to_string(), options.get_port());
+        TcpStream::connect((server.0.as_str(), server.1))
+            .await
+            .map_err(|e| format!("Failed to connect: {}", e))?;
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .map_err(|e| format!("Failed to start the connection relay: {}", e))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to start the connection relay: {}", e))?
+            .port();
+
+        let accepting = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((client, _)) => {
+                        tokio::spawn(relay(client, server.clone()));
+                    }
+                    Err(_) => tokio::time::sleep(ACCEPT_RETRY).await,
+                }
+            }
+        });
+
+        Ok(Self { port, accepting })
+    }
+
+    /// `options` with their address pointed at the relay instead.
+    pub fn route(&self, options: PgConnectOptions) -> PgConnectOptions {
+        options
+            .host(&Ipv4Addr::LOCALHOST.to_string())
+            .port(self.port)
+    }
+}
+
+impl Drop for NoticeRelay {
+    fn drop(&mut self) {
+        self.accepting.abort();
+    }
+}
+
+/// Copies one connection through to the server until either side closes it.
+async fn relay(client: TcpStream, server: (String, u16)) {
+    let Ok(upstream) = TcpStream::connect((server.0.as_str(), server.1)).await else {
+        return;
+    };
+    let _ = client.set_nodelay(true);
+    let _ = upstream.set_nodelay(true);
+    let (mut from_client, mut to_client) = client.into_split();
+    let (mut from_server, mut to_server) = upstream.into_split();
+
+    let sending = async {
+        let _ = tokio::io::copy(&mut from_client, &mut to_server).await;
+        let _ = to_server.shutdown().await;
+    };
+    let receiving = async {
+        let mut scanner = MessageScanner::default();
+        let mut buffer = vec![0; READ_BUFFER];
+        loop {
+            let read = match from_server.read(&mut buffer).await {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            for notice in scanner.scan(&buffer[..read]) {
+                if let Some(backend_pid) = scanner.backend_pid {
+                    notices::receive(backend_pid, notice);
+                }
+            }
+            if to_client.write_all(&buffer[..read]).await.is_err() {
+                break;
+            }
+        }
+        let _ = to_client.shutdown().await;
+        scanner.backend_pid
+    };
+
+    if let ((), Some(backend_pid)) = tokio::join!(sending, receiving) {
+        notices::forget(backend_pid);
+    }
+}
+
+/// Follows the messages a server sends, each a type byte and a big-endian
+/// length that counts itself, keeping the bodies of the few that matter.
+#[derive(Debug, Default)]
+struct MessageScanner {
+    /// Up to five bytes: the current message's type and length.
+    header: Vec<u8>,
+    /// Body bytes of the current message still to come.
+    remaining: usize,
+    /// The body so far, for a message that is kept.
+    body: Vec<u8>,
+    /// Learned from `BackendKeyData` once the connection is established.
+    backend_pid: Option<i32>,
+}
+
+impl MessageScanner {
+    /// Scans the next bytes from the server, returning the notices they
+    /// complete.
+    fn scan(&mut self, mut bytes: &[u8]) -> Vec<ServerNotice> {
+        let mut notices = Vec::new();
+
+        while !bytes.is_empty() {
+            if self.header.len() < 5 {
+                let taken = (5 - self.header.len()).min(bytes.len());
+                self.header.extend_from_slice(&bytes[..taken]);
+                bytes = &bytes[taken..];
+                if self.header.len() < 5 {
+                    break;
+                }
+                let length = [
+                    self.header[1],
+                    self.header[2],
+                    self.header[3],
+                    self.header[4],
+                ];
+                self.remaining = (u32::from_be_bytes(length) as usize).saturating_sub(4);
+                self.body.clear();
+            }
+
+            let taken = self.remaining.min(bytes.len());
+            if self.kept() {
+                self.body.extend_from_slice(&bytes[..taken]);
+            }
+            bytes = &bytes[taken..];
+            self.remaining -= taken;
+
+            if self.remaining == 0 {
+                match self.header[0] {
+                    b'N' => notices.push(ServerNotice::parse(&self.body)),
+                    b'K' if self.body.len() >= 4 => {
+                        let pid = [self.body[0], self.body[1], self.body[2], self.body[3]];
+                        self.backend_pid = Some(i32::from_be_bytes(pid));
+                    }
+                    _ => {}
+                }
+                self.header.clear();
+            }
+        }
+
+        notices
+    }
+
+    /// Whether the current message is a notice or `BackendKeyData`; the rest,
+    /// rows included, are only counted past.
+    fn kept(&self) -> bool {
+        matches!(self.header[0], b'N' | b'K')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(kind: u8, body: &[u8]) -> Vec<u8> {
+        let mut message = vec![kind];
+        message.extend_from_slice(&(body.len() as u32 + 4).to_be_bytes());
+        message.extend_from_slice(body);
+        message
+    }
+
+    fn notice(message_text: &str) -> Vec<u8> {
+        let body = format!("VNOTICE\0M{message_text}\0DSome detail\0\0");
+        message(b'N', body.as_bytes())
+    }
+
+    fn expected(message_text: &str) -> ServerNotice {
+        ServerNotice {
+            severity: "NOTICE".to_string(),
+            message: message_text.to_string(),
+            detail: Some("Some detail".to_string()),
+            hint: None,
+        }
+    }
+
+    fn stream() -> Vec<u8> {
+        [
+            message(b'R', &[0, 0, 0, 0]),
+            message(b'K', &[0, 0, 0x30, 0x39, 1, 2, 3, 4]),
+            message(b'Z', b"I"),
+            notice("first"),
+            message(b'D', &[0, 1, 0, 0, 0, 2, b'N', b'K']),
+            message(b'C', b"SELECT 1\0"),
+            notice("second"),
+            message(b'Z', b"I"),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn finds_notices_and_the_backend_pid() {
+        let mut scanner = MessageScanner::default();
+
+        let notices = scanner.scan(&stream());
+
+        assert_eq!(notices, vec![expected("first"), expected("second")]);
+        assert_eq!(scanner.backend_pid, Some(12345));
+    }
+
+    #[test]
+    fn follows_messages_split_across_reads() {
+        let stream = stream();
+        for chunk_size in [1, 2, 3, 5, 7, 64] {
+            let mut scanner = MessageScanner::default();
+            let notices: Vec<_> = stream
+                .chunks(chunk_size)
+                .flat_map(|chunk| scanner.scan(chunk))
+                .collect();
+
+            assert_eq!(notices, vec![expected("first"), expected("second")]);
+            assert_eq!(scanner.backend_pid, Some(12345));
+        }
+    }
+
+    #[test]
+    fn reports_a_notice_only_once_it_is_complete() {
+        let mut scanner = MessageScanner::default();
+        let notice = notice("late");
+        let (start, end) = notice.split_at(notice.len() - 1);
+
+        assert_eq!(scanner.scan(start), vec![]);
+        assert_eq!(scanner.scan(end), vec![expected("late")]);
+    }
+}
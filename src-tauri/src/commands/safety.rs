@@ -0,0 +1,74 @@
+//! How much a connection lets the editor change.
+//!
+//! Read-only connections are enforced by the server: every backend starts with
+//! `default_transaction_read_only`. On connections that confirm destructive
+//! statements, such statements are refused until they are sent again with the
+//! token the refusal handed out.
+
+use sqlx::postgres::PgConnectOptions;
+
+use super::connection::AppState;
+use super::error::CommandError;
+use super::sql::{destructive_statement, Destructive};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyLevel {
+    /// Transactions are read-only unless a statement explicitly asks otherwise.
+    ReadOnly,
+    /// `DROP`, `TRUNCATE`, `ALTER`, and `UPDATE` or `DELETE` without `WHERE`
+    /// need confirming.
+    ConfirmDestructive,
+    #[default]
+    Unrestricted,
+}
+
+impl SafetyLevel {
+    /// Makes every connection opened with `options` start at this level.
+    pub fn apply_to(self, options: PgConnectOptions) -> PgConnectOptions {
+        match self {
+            SafetyLevel::ReadOnly => options.options([("default_transaction_read_only", "on")]),
+            _ => options,
+        }
+    }
+}
+
+/// Refuses `sql` if the connection confirms destructive statements and any of
+/// `statements` is one, unless `token` was handed out for exactly this SQL.
+///
+/// A token works once. Each refusal hands out a fresh one and withdraws any
+/// earlier token for the same SQL.
+pub async fn check_destructive<'a>(
+    state: &AppState,
+    sql: &str,
+    statements: impl IntoIterator<Item = &'a str>,
+    token: Option<&str>,
+) -> Result<(), CommandError> {
+    if state.safety().await != SafetyLevel::ConfirmDestructive {
+        return Ok(());
+    }
+
+    let mut reasons: Vec<Destructive> = Vec::new();
+    for reason in statements.into_iter().filter_map(destructive_statement) {
+        if !reasons.contains(&reason) {
+            reasons.push(reason);
+        }
+    }
+    if reasons.is_empty() {
+        return Ok(());
+    }
+
+    let mut pending = state.confirmations.lock().await;
+    if let Some(token) = token {
+        if pending.get(token).is_some_and(|confirmed| confirmed == sql) {
+            pending.remove(token);
+            return Ok(());
+        }
+    }
+
+    pending.retain(|_, pending_sql| pending_sql != sql);
+    let token = uuid::Uuid::new_v4().to_string();
+    pending.insert(token.clone(), sql.to_string());
+
+    Err(CommandError::ConfirmationRequired { token, reasons })
+}
@@ -2,6 +2,7 @@ use sqlx::Row;
 use tauri::State;
 
 use super::connection::AppState;
+use super::error::CommandError;
 
 #[derive(Debug, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -27,7 +28,7 @@ pub struct DatabaseSchema {
 async fn fetch_columns(
     pool: &sqlx::PgPool,
     table_name: &str,
-) -> Result<Vec<ColumnSchema>, String> {
+) -> Result<Vec<ColumnSchema>, CommandError> {
     let columns_query = r#"
         SELECT
             c.column_name,
@@ -54,7 +55,7 @@ async fn fetch_columns(
         .bind(table_name)
         .fetch_all(pool)
         .await
-        .map_err(|e| format!("Failed to get columns for {}: {}", table_name, e))?;
+        .map_err(|e| CommandError::sqlx(&format!("Failed to get columns for {}", table_name), e))?;
 
     let columns = column_rows
         .iter()
@@ -79,7 +80,7 @@ async fn fetch_columns(
 async fn fetch_relations(
     pool: &sqlx::PgPool,
     table_type: &str,
-) -> Result<Vec<TableSchema>, String> {
+) -> Result<Vec<TableSchema>, CommandError> {
     let query = r#"
         SELECT table_name
         FROM information_schema.tables
@@ -92,14 +93,14 @@ async fn fetch_relations(
         .bind(table_type)
         .fetch_all(pool)
         .await
-        .map_err(|e| format!("Failed to get {}: {}", table_type, e))?;
+        .map_err(|e| CommandError::sqlx(&format!("Failed to get {}", table_type), e))?;
 
     let mut result = Vec::new();
 
     for row in rows {
         let name: String = row
             .try_get("table_name")
-            .map_err(|e| format!("Failed to get table name: {}", e))?;
+            .map_err(|e| CommandError::sqlx("Failed to get table name", e))?;
 
         let columns = fetch_columns(pool, &name).await?;
         result.push(TableSchema { name, columns });
@@ -109,12 +110,11 @@ async fn fetch_relations(
 }
 
 #[tauri::command]
-pub async fn get_schema(state: State<'_, AppState>) -> Result<DatabaseSchema, String> {
-    let guard = state.pool.lock().await;
-    let pool = guard.as_ref().ok_or("Not connected to a database")?;
+pub async fn get_schema(state: State<'_, AppState>) -> Result<DatabaseSchema, CommandError> {
+    let pool = state.pool().await?;
 
-    let tables = fetch_relations(pool, "BASE TABLE").await?;
-    let views = fetch_relations(pool, "VIEW").await?;
+    let tables = fetch_relations(&pool, "BASE TABLE").await?;
+    let views = fetch_relations(&pool, "VIEW").await?;
 
     Ok(DatabaseSchema { tables, views })
 }
@@ -0,0 +1,308 @@
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use sqlx::postgres::PgPool;
+use sqlx::{Either, Executor, Statement};
+use tauri::State;
+
+use super::connection::AppState;
+use super::decode::{DisplayZone, RowDecoder, ValueStyle};
+use super::error::CommandError;
+use super::history::record_script;
+use super::jobs::{finish_job, job_cancel_requested, start_job, JobConnection};
+use super::notices::{NoticeSink, ServerNotice};
+use super::query::{prepare_statement, ResultColumn, DEFAULT_MAX_ROWS};
+use super::safety::check_destructive;
+use super::session::{discard_tab_session, tab_transaction_state, TransactionState};
+use super::sql::{split_statements, statement_kind, SqlStatement, StatementKind};
+use super::timeouts::{override_timeouts, TimeoutKind, Timeouts};
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptOptions {
+    /// Keep running the remaining statements after one fails.
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// Most rows kept per statement.
+    pub max_rows: Option<usize>,
+    /// Zone TIMESTAMPTZ and TIMETZ values are shown in.
+    #[serde(default)]
+    pub display_zone: DisplayZone,
+    pub job_id: Option<String>,
+    pub tab_id: Option<String>,
+    /// Limits for every statement of this script, in place of the connection's.
+    pub timeouts: Option<Timeouts>,
+    /// Token from a `confirmationRequired` error, to run the destructive
+    /// statements it was issued for.
+    pub confirmation_token: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementResult {
+    pub statement: String,
+    pub offset: usize,
+    pub statement_kind: StatementKind,
+    pub columns: Vec<ResultColumn>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+    pub rows_affected: Option<u64>,
+    pub execution_time_ms: u64,
+    /// When the statement was sent, for the query history.
+    #[serde(skip)]
+    pub started_at: DateTime<Utc>,
+    pub row_limit_reached: bool,
+    pub error: Option<CommandError>,
+    /// Notices the statement raised, including those before it failed.
+    pub notices: Vec<ServerNotice>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptResult {
+    pub statements: Vec<StatementResult>,
+    /// Statements in the script that were never run because an earlier one
+    /// failed or the script was cancelled.
+    pub skipped: usize,
+    pub execution_time_ms: u64,
+    pub cancelled: bool,
+    /// Transaction state of the tab's session once the script has run.
+    pub transaction_state: TransactionState,
+}
+
+/// Runs every statement of a script in order on a single connection, logging
+/// each to the query history.
+#[tauri::command]
+pub async fn execute_script(
+    script: String,
+    options: Option<ScriptOptions>,
+    state: State<'_, AppState>,
+) -> Result<ScriptResult, CommandError> {
+    let options = options.unwrap_or_default();
+    let tab_id = options.tab_id.clone();
+    let started_at = Utc::now();
+
+    let outcome = run_script(&script, options, &state).await;
+    record_script(&state, &script, tab_id, started_at, &outcome).await;
+    outcome
+}
+
+async fn run_script(
+    script: &str,
+    options: ScriptOptions,
+    state: &AppState,
+) -> Result<ScriptResult, CommandError> {
+    let max_rows = options.max_rows.unwrap_or(DEFAULT_MAX_ROWS).max(1);
+    let job_id = options
+        .job_id
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let statements = split_statements(script);
+    let total = statements.len();
+
+    let pool = state.pool().await?;
+    check_destructive(
+        state,
+        script,
+        statements.iter().map(|s| s.text.as_str()),
+        options.confirmation_token.as_deref(),
+    )
+    .await?;
+
+    let limits = state.timeouts().await.merged(options.timeouts);
+    let mut conn = start_job(state, &job_id, options.tab_id.clone(), script).await?;
+
+    let saved = if conn.transaction_state() == TransactionState::Failed {
+        None
+    } else {
+        match override_timeouts(&mut conn, options.timeouts).await {
+            Ok(saved) => saved,
+            Err(e) => {
+                drop(conn);
+                finish_job(state, &job_id).await;
+                return Err(e);
+            }
+        }
+    };
+
+    let start = std::time::Instant::now();
+    let mut results = Vec::with_capacity(total);
+    let mut cancelled = false;
+
+    for statement in statements {
+        // A cancel sent while no statement was running is dropped by the
+        // server, so it is checked for before each statement as well.
+        if job_cancel_requested(state, &job_id).await {
+            cancelled = true;
+            break;
+        }
+
+        let mut result = run_statement(
+            &mut conn,
+            &pool,
+            statement,
+            Vec::new(),
+            max_rows,
+            options.display_zone,
+        )
+        .await;
+        result.error = result.error.map(|e| limits.classify(e));
+        let failed = result.error.is_some();
+        conn.statement_finished(&result.statement, failed).await;
+        results.push(result);
+
+        if failed && !options.continue_on_error {
+            break;
+        }
+    }
+
+    if let Some(saved) = saved {
+        saved.restore(&mut conn).await;
+    }
+    drop(conn);
+    cancelled |= finish_job(state, &job_id).await && results.iter().any(|r| r.error.is_some());
+
+    let session_closed = results.iter().any(|r| {
+        matches!(
+            r.error,
+            Some(CommandError::TimedOut {
+                kind: TimeoutKind::IdleInTransaction,
+                ..
+            })
+        )
+    });
+    if let (true, Some(tab_id)) = (session_closed, &options.tab_id) {
+        discard_tab_session(state, tab_id).await;
+    }
+
+    Ok(ScriptResult {
+        skipped: total - results.len(),
+        statements: results,
+        execution_time_ms: start.elapsed().as_millis() as u64,
+        cancelled,
+        transaction_state: tab_transaction_state(state, options.tab_id.as_deref()).await,
+    })
+}
+
+/// Runs one statement to completion, keeping at most `max_rows` of its rows.
+/// `binds` are text values for its `$n` placeholders.
+pub(crate) async fn run_statement(
+    conn: &mut JobConnection,
+    pool: &PgPool,
+    statement: SqlStatement,
+    binds: Vec<Option<String>>,
+    max_rows: usize,
+    display_zone: DisplayZone,
+) -> StatementResult {
+    let start = std::time::Instant::now();
+    let mut result = StatementResult {
+        statement_kind: statement_kind(&statement.text),
+        statement: statement.text,
+        offset: statement.offset,
+        columns: vec![],
+        rows: vec![],
+        rows_affected: None,
+        execution_time_ms: 0,
+        started_at: Utc::now(),
+        row_limit_reached: false,
+        error: None,
+        notices: Vec::new(),
+    };
+
+    let notices = NoticeSink::new(conn.backend_pid());
+    let sql = result.statement.clone();
+    let statement = match prepare_statement(conn, &sql).await {
+        Ok((statement, columns)) => {
+            result.columns = columns;
+            statement
+        }
+        Err(e) => {
+            result.error = Some(CommandError::sqlx("Query error", e));
+            result.execution_time_ms = start.elapsed().as_millis() as u64;
+            result.notices = notices.take();
+            return result;
+        }
+    };
+
+    let decoder = RowDecoder::new(conn, statement.columns(), display_zone, ValueStyle::Grid).await;
+    let mut undecoded = Vec::new();
+
+    let mut rows_affected = 0;
+    let query = binds
+        .into_iter()
+        .fold(statement.query(), |query, value| query.bind(value));
+    let mut stream = (&mut **conn).fetch_many(query);
+
+    loop {
+        match stream.try_next().await {
+            Ok(Some(Either::Left(done))) => rows_affected += done.rows_affected(),
+            Ok(Some(Either::Right(row))) => {
+                if result.rows.len() == max_rows {
+                    result.row_limit_reached = true;
+                    continue;
+                }
+                let decoded = decoder.decode_row(&row, result.rows.len(), &mut undecoded);
+                result.rows.push(decoded);
+            }
+            Ok(None) => {
+                if result.statement_kind != StatementKind::Query {
+                    result.rows_affected = Some(rows_affected);
+                }
+                break;
+            }
+            Err(e) => {
+                result.error = Some(CommandError::sqlx("Query error", e));
+                break;
+            }
+        }
+    }
+
+    drop(stream);
+    decoder
+        .render_undecoded(pool, &mut result.rows, undecoded)
+        .await;
+
+    result.execution_time_ms = start.elapsed().as_millis() as u64;
+    result.notices = notices.take();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn stops_before_the_next_statement_once_cancelled() {
+        let Some(state) = AppState::connected_for_tests().await else {
+            return;
+        };
+        let options = ScriptOptions {
+            job_id: Some("script".to_string()),
+            tab_id: Some("tab".to_string()),
+            continue_on_error: true,
+            ..Default::default()
+        };
+
+        // Only flags the job while the first statement sleeps, as a cancel
+        // reaching the server between two statements would do nothing more.
+        let cancel = async {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            state
+                .jobs
+                .lock()
+                .await
+                .get_mut("script")
+                .expect("the script is still running")
+                .request_cancel();
+        };
+        let (outcome, ()) = tokio::join!(
+            run_script("SELECT pg_sleep(0.6); SELECT 1; SELECT 2", options, &state),
+            cancel
+        );
+
+        let result = outcome.unwrap();
+        assert!(result.cancelled);
+        assert_eq!(result.statements.len(), 1);
+        assert_eq!(result.statements[0].rows, vec![vec![json!("")]]);
+        assert_eq!(result.skipped, 2);
+    }
+}
@@ -0,0 +1,335 @@
+//! Connections pinned to an editor tab.
+//!
+//! Running each statement on whichever pooled connection is free would scatter
+//! a transaction across backends and lose `SET`, temporary tables and prepared
+//! statements between runs. Instead every tab gets a connection of its own,
+//! outside the pool, the first time it runs something, and keeps it until the
+//! session is closed or the app disconnects.
+
+use sqlx::postgres::{PgConnection, PgPool};
+use sqlx::{Connection, Executor};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::State;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use super::connection::AppState;
+use super::error::CommandError;
+use super::query::close_tab_results;
+use super::sql::{transaction_control, TransactionControl};
+
+/// How often a busy session is checked for result sets holding it.
+const SESSION_RETRY: Duration = Duration::from_millis(200);
+
+/// Where a tab's session stands relative to a transaction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionState {
+    #[default]
+    Idle,
+    InTransaction,
+    /// A statement failed inside the transaction; the server ignores everything
+    /// but `ROLLBACK` until it ends.
+    Failed,
+}
+
+impl TransactionState {
+    /// Whether uncommitted work would be lost if the session went away.
+    pub fn is_open(self) -> bool {
+        self != TransactionState::Idle
+    }
+
+    /// The state after `sql` ran in this one, successfully or not.
+    fn after(self, sql: &str, failed: bool) -> Self {
+        let control = transaction_control(sql);
+
+        if failed {
+            return match (self, control) {
+                // A failed COMMIT, such as a deferred constraint, still ends it.
+                (_, Some(TransactionControl::End)) => TransactionState::Idle,
+                (TransactionState::Idle, _) => TransactionState::Idle,
+                _ => TransactionState::Failed,
+            };
+        }
+
+        match control {
+            Some(TransactionControl::Begin)
+            | Some(TransactionControl::Chain)
+            | Some(TransactionControl::RollbackToSavepoint) => TransactionState::InTransaction,
+            Some(TransactionControl::End) => TransactionState::Idle,
+            None => self,
+        }
+    }
+}
+
+/// A tab's dedicated connection and its transaction state.
+///
+/// Cloning gives another handle to the same session. The connection is closed
+/// once the last handle is dropped, which rolls back anything left open.
+#[derive(Clone)]
+pub struct TabSession {
+    conn: Arc<Mutex<PgConnection>>,
+    pub backend_pid: i32,
+    state: Arc<std::sync::Mutex<TransactionState>>,
+}
+
+impl TabSession {
+    /// Connects with the pool's settings, but outside it, so a session does not
+    /// hold back a pooled connection other tabs could use.
+    async fn open(pool: &PgPool) -> Result<Self, CommandError> {
+        let mut conn = PgConnection::connect_with(&pool.connect_options())
+            .await
+            .map_err(|e| CommandError::sqlx("Failed to open a session", e))?;
+
+        let backend_pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+            .fetch_one(&mut conn)
+            .await
+            .map_err(|e| CommandError::sqlx("Failed to get backend PID", e))?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            backend_pid,
+            state: Arc::new(std::sync::Mutex::new(TransactionState::Idle)),
+        })
+    }
+
+    pub fn state(&self) -> TransactionState {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Waits for the statement currently running in the session, if any, and
+    /// holds the connection until the returned guard is dropped.
+    pub async fn lock(&self) -> SessionGuard {
+        SessionGuard {
+            conn: self.conn.clone().lock_owned().await,
+            backend_pid: self.backend_pid,
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// Exclusive use of a session's connection.
+pub struct SessionGuard {
+    conn: OwnedMutexGuard<PgConnection>,
+    pub backend_pid: i32,
+    state: Arc<std::sync::Mutex<TransactionState>>,
+}
+
+impl SessionGuard {
+    pub fn transaction_state(&self) -> TransactionState {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Records that `sql` finished, so the transaction state follows any
+    /// `BEGIN`, `COMMIT` or failure it caused.
+    pub fn statement_finished(&self, sql: &str, failed: bool) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state = state.after(sql, failed);
+    }
+}
+
+impl Deref for SessionGuard {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        &self.conn
+    }
+}
+
+impl DerefMut for SessionGuard {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        &mut self.conn
+    }
+}
+
+/// The session pinned to `tab_id`, if it has one.
+pub async fn find_tab_session(state: &AppState, tab_id: &str) -> Option<TabSession> {
+    state.sessions.lock().await.get(tab_id).cloned()
+}
+
+/// The session pinned to `tab_id`, opening one if it has none yet.
+///
+/// Connecting happens outside the sessions lock so other tabs are not held up
+/// by it. If the same tab got a session in the meantime that one is kept and
+/// the new connection closed; if the app switched connections, the new one
+/// would belong to the old database and is dropped too.
+pub async fn open_tab_session(state: &AppState, tab_id: &str) -> Result<TabSession, CommandError> {
+    if let Some(session) = find_tab_session(state, tab_id).await {
+        return Ok(session);
+    }
+
+    let connection_id = state.connection_id().await;
+    let opened = TabSession::open(&state.pool().await?).await?;
+
+    let mut sessions = state.sessions.lock().await;
+    if state.connection_id().await != connection_id {
+        return Err(CommandError::NotConnected);
+    }
+    Ok(sessions.entry(tab_id.to_string()).or_insert(opened).clone())
+}
+
+/// The transaction state of `tab_id`; tabs without a session are idle.
+pub async fn tab_transaction_state(state: &AppState, tab_id: Option<&str>) -> TransactionState {
+    match tab_id {
+        Some(tab_id) => find_tab_session(state, tab_id)
+            .await
+            .map(|session| session.state())
+            .unwrap_or_default(),
+        None => TransactionState::Idle,
+    }
+}
+
+/// Takes the tab's session for a new statement.
+///
+/// Result sets the tab still has open hold the connection, so they are closed
+/// first. One being paged at the same moment goes back into the result map
+/// afterwards, which is why closing is retried while the session stays busy.
+pub async fn lock_tab_session(
+    state: &AppState,
+    tab_id: &str,
+    session: &TabSession,
+) -> SessionGuard {
+    loop {
+        close_tab_results(state, tab_id).await;
+        if let Ok(guard) = tokio::time::timeout(SESSION_RETRY, session.lock()).await {
+            return guard;
+        }
+    }
+}
+
+/// Tabs whose sessions hold uncommitted work.
+pub async fn open_transactions(state: &AppState) -> Vec<String> {
+    let mut tabs: Vec<String> = state
+        .sessions
+        .lock()
+        .await
+        .iter()
+        .filter(|(_, session)| session.state().is_open())
+        .map(|(tab_id, _)| tab_id.clone())
+        .collect();
+    tabs.sort();
+    tabs
+}
+
+/// Runs a transaction-control statement in the tab's session.
+async fn run_control(
+    state: &AppState,
+    tab_id: &str,
+    session: &TabSession,
+    sql: &str,
+) -> Result<TransactionState, CommandError> {
+    let mut conn = lock_tab_session(state, tab_id, session).await;
+    let outcome = (&mut *conn).execute(sql).await;
+    conn.statement_finished(sql, outcome.is_err());
+    outcome.map_err(|e| CommandError::sqlx("Transaction error", e))?;
+
+    Ok(session.state())
+}
+
+/// Opens a transaction in the tab's session.
+#[tauri::command]
+pub async fn begin_transaction(
+    tab_id: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, CommandError> {
+    let session = open_tab_session(&state, &tab_id).await?;
+    if session.state().is_open() {
+        return Err("A transaction is already open in this tab".into());
+    }
+
+    run_control(&state, &tab_id, &session, "BEGIN").await
+}
+
+/// Commits the tab's transaction.
+///
+/// A failed transaction cannot be committed; it is rolled back instead and the
+/// call reports an error saying so.
+#[tauri::command]
+pub async fn commit(
+    tab_id: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, CommandError> {
+    let Some(session) = find_tab_session(&state, &tab_id).await else {
+        return Ok(TransactionState::Idle);
+    };
+
+    let failed = session.state() == TransactionState::Failed;
+    let sql = if failed { "ROLLBACK" } else { "COMMIT" };
+    match run_control(&state, &tab_id, &session, sql).await {
+        Ok(_) if failed => {
+            Err("The transaction had failed, so it was rolled back instead of committed".into())
+        }
+        outcome => outcome,
+    }
+}
+
+/// Rolls back the tab's transaction.
+#[tauri::command]
+pub async fn rollback(
+    tab_id: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, CommandError> {
+    let Some(session) = find_tab_session(&state, &tab_id).await else {
+        return Ok(TransactionState::Idle);
+    };
+
+    run_control(&state, &tab_id, &session, "ROLLBACK").await
+}
+
+/// Returns the tab's session to a fresh state without reconnecting: any open
+/// transaction is rolled back, then `DISCARD ALL` drops settings, temporary
+/// tables and prepared statements.
+#[tauri::command]
+pub async fn reset_session(
+    tab_id: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, CommandError> {
+    let Some(session) = find_tab_session(&state, &tab_id).await else {
+        return Ok(TransactionState::Idle);
+    };
+
+    let mut conn = lock_tab_session(&state, &tab_id, &session).await;
+    if session.state().is_open() {
+        (&mut *conn)
+            .execute("ROLLBACK")
+            .await
+            .map_err(|e| CommandError::sqlx("Failed to reset session", e))?;
+        conn.statement_finished("ROLLBACK", false);
+    }
+
+    // The driver's statements go first, while it still knows their names.
+    conn.clear_cached_statements()
+        .await
+        .map_err(|e| CommandError::sqlx("Failed to reset session", e))?;
+    (&mut *conn)
+        .execute("DISCARD ALL")
+        .await
+        .map_err(|e| CommandError::sqlx("Failed to reset session", e))?;
+
+    Ok(session.state())
+}
+
+/// Forgets the tab's session, closing its result sets. Once the statement
+/// holding it is done, its connection closes and rolls back anything left
+/// open; the tab gets a new session the next time it runs a statement.
+pub async fn discard_tab_session(state: &AppState, tab_id: &str) {
+    close_tab_results(state, tab_id).await;
+    state.sessions.lock().await.remove(tab_id);
+}
+
+/// Closes the tab's session, rolling back anything it left open. The tab gets a
+/// new session the next time it runs a statement.
+#[tauri::command]
+pub async fn close_session(tab_id: String, state: State<'_, AppState>) -> Result<(), CommandError> {
+    discard_tab_session(&state, &tab_id).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_transaction_state(
+    tab_id: String,
+    state: State<'_, AppState>,
+) -> Result<TransactionState, CommandError> {
+    Ok(tab_transaction_state(&state, Some(&tab_id)).await)
+}
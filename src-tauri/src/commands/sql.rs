@@ -0,0 +1,1069 @@
+//! A small PostgreSQL lexer, just precise enough to find statement boundaries
+//! and keywords without being fooled by strings, quoted identifiers, comments
+//! or dollar-quoted bodies.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Whitespace,
+    /// `-- line` or `/* block */` comment, nested block comments included.
+    Comment,
+    /// Keyword or unquoted identifier.
+    Word,
+    QuotedIdentifier,
+    /// `'...'`, `E'...'`, `B'...'` or `X'...'` literal.
+    String,
+    /// `$$...$$` or `$tag$...$tag$` literal.
+    DollarString,
+    Number,
+    /// Positional parameter such as `$1`.
+    Parameter,
+    Semicolon,
+    Punct,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// Byte offset of the token in the lexed source.
+    pub start: usize,
+}
+
+impl Token<'_> {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment)
+    }
+
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+}
+
+fn is_word_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+pub fn tokenize(sql: &str) -> Vec<Token<'_>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < sql.len() {
+        let rest = &sql[pos..];
+        let c = rest.chars().next().unwrap_or_default();
+
+        let (kind, len) = if c.is_whitespace() {
+            let len = rest
+                .find(|ch: char| !ch.is_whitespace())
+                .unwrap_or(rest.len());
+            (TokenKind::Whitespace, len)
+        } else if rest.starts_with("--") {
+            (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if rest.starts_with("/*") {
+            (TokenKind::Comment, block_comment_len(rest))
+        } else if c == '\'' {
+            (TokenKind::String, quoted_len(rest, '\'', false))
+        } else if c == '"' {
+            (TokenKind::QuotedIdentifier, quoted_len(rest, '"', false))
+        } else if matches!(c, 'e' | 'E' | 'b' | 'B' | 'x' | 'X' | 'n' | 'N')
+            && bytes.get(pos + 1) == Some(&b'\'')
+        {
+            let backslash_escapes = matches!(c, 'e' | 'E');
+            (
+                TokenKind::String,
+                1 + quoted_len(&rest[1..], '\'', backslash_escapes),
+            )
+        } else if c == '$' {
+            match dollar_tag_len(rest) {
+                Some(tag_len) => {
+                    let tag = &rest[..tag_len];
+                    let body_len = rest[tag_len..]
+                        .find(tag)
+                        .map(|end| end + tag.len())
+                        .unwrap_or(rest.len() - tag_len);
+                    (TokenKind::DollarString, tag_len + body_len)
+                }
+                None => {
+                    let digits = rest[1..]
+                        .find(|ch: char| !ch.is_ascii_digit())
+                        .unwrap_or(rest.len() - 1);
+                    if digits > 0 {
+                        (TokenKind::Parameter, 1 + digits)
+                    } else {
+                        (TokenKind::Punct, 1)
+                    }
+                }
+            }
+        } else if is_word_start(c) {
+            let len = rest
+                .find(|ch: char| !is_word_char(ch))
+                .unwrap_or(rest.len());
+            (TokenKind::Word, len)
+        } else if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|ch: char| ch.is_ascii_digit()))
+        {
+            let len = rest
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '.' || ch == '_'))
+                .unwrap_or(rest.len());
+            (TokenKind::Number, len)
+        } else if c == ';' {
+            (TokenKind::Semicolon, 1)
+        } else {
+            (TokenKind::Punct, c.len_utf8())
+        };
+
+        tokens.push(Token {
+            kind,
+            text: &rest[..len],
+            start: pos,
+        });
+        pos += len;
+    }
+
+    tokens
+}
+
+/// Length of a `/* ... */` comment, honouring nesting. Unterminated comments
+/// run to the end of the input.
+fn block_comment_len(rest: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    let bytes = rest.as_bytes();
+
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else if bytes[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += 1;
+        }
+    }
+
+    rest.len()
+}
+
+/// Length of a literal opened by `quote` at the start of `rest`, where a doubled
+/// quote stands for itself.
+fn quoted_len(rest: &str, quote: char, backslash_escapes: bool) -> usize {
+    let mut chars = rest.char_indices().skip(1).peekable();
+
+    while let Some((i, ch)) = chars.next() {
+        if backslash_escapes && ch == '\\' {
+            chars.next();
+        } else if ch == quote {
+            if chars.peek().is_some_and(|&(_, next)| next == quote) {
+                chars.next();
+            } else {
+                return i + ch.len_utf8();
+            }
+        }
+    }
+
+    rest.len()
+}
+
+/// Length of the `$tag$` opening a dollar-quoted string, if `rest` starts with one.
+fn dollar_tag_len(rest: &str) -> Option<usize> {
+    let after = &rest[1..];
+    let first = after.chars().next()?;
+
+    if first == '$' {
+        return Some(2);
+    }
+    if !is_word_start(first) {
+        return None;
+    }
+
+    let tag_len = after.find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))?;
+    after[tag_len..].starts_with('$').then_some(tag_len + 2)
+}
+
+/// One statement cut out of a script.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlStatement {
+    pub text: String,
+    /// Character offset of the statement in the script, for editor highlighting.
+    pub offset: usize,
+}
+
+/// Splits a script into statements on top-level semicolons.
+///
+/// Semicolons inside literals, comments, dollar-quoted bodies and SQL-standard
+/// `BEGIN ATOMIC ... END` function bodies do not end a statement. Statements
+/// consisting only of comments are dropped.
+pub fn split_statements(sql: &str) -> Vec<SqlStatement> {
+    let tokens = tokenize(sql);
+    let mut statements = Vec::new();
+    let mut current: Vec<&Token> = Vec::new();
+    let mut begin_depth = 0usize;
+
+    for token in &tokens {
+        if token.kind == TokenKind::Semicolon && begin_depth == 0 {
+            push_statement(sql, &current, &mut statements);
+            current.clear();
+            continue;
+        }
+
+        if token.kind == TokenKind::Word && defines_routine(&current) {
+            if token.is_keyword("BEGIN") || (begin_depth > 0 && token.is_keyword("CASE")) {
+                begin_depth += 1;
+            } else if begin_depth > 0 && token.is_keyword("END") {
+                begin_depth -= 1;
+            }
+        }
+
+        current.push(token);
+    }
+
+    push_statement(sql, &current, &mut statements);
+    statements
+}
+
+/// Whether the statement so far is a `CREATE [OR REPLACE] FUNCTION/PROCEDURE`.
+fn defines_routine(tokens: &[&Token]) -> bool {
+    let mut words = tokens
+        .iter()
+        .filter(|t| !t.is_trivia())
+        .take(4)
+        .map(|t| t.text.to_ascii_uppercase());
+
+    if words.next().as_deref() != Some("CREATE") {
+        return false;
+    }
+
+    let mut next = words.next();
+    if next.as_deref() == Some("OR") {
+        words.next();
+        next = words.next();
+    }
+
+    matches!(next.as_deref(), Some("FUNCTION" | "PROCEDURE"))
+}
+
+fn push_statement(sql: &str, tokens: &[&Token], statements: &mut Vec<SqlStatement>) {
+    if tokens.iter().all(|t| t.is_trivia()) {
+        return;
+    }
+
+    let first = tokens
+        .iter()
+        .position(|t| t.kind != TokenKind::Whitespace)
+        .unwrap_or_default();
+    let last = tokens
+        .iter()
+        .rposition(|t| t.kind != TokenKind::Whitespace)
+        .unwrap_or_default();
+
+    let start = tokens[first].start;
+    let end = tokens[last].start + tokens[last].text.len();
+
+    statements.push(SqlStatement {
+        text: sql[start..end].to_string(),
+        offset: sql[..start].chars().count(),
+    });
+}
+
+/// What a statement does, as far as presenting its outcome is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StatementKind {
+    /// `SELECT`, `VALUES`, `TABLE`, `SHOW`, `EXPLAIN` or `FETCH`.
+    Query,
+    Insert,
+    Update,
+    Delete,
+    Merge,
+    /// DDL, session commands and anything else.
+    Other,
+}
+
+fn keyword_kind(token: &Token) -> Option<StatementKind> {
+    if token.kind != TokenKind::Word {
+        return None;
+    }
+
+    match token.text.to_ascii_uppercase().as_str() {
+        "SELECT" | "VALUES" | "TABLE" | "SHOW" | "EXPLAIN" | "FETCH" => Some(StatementKind::Query),
+        "INSERT" => Some(StatementKind::Insert),
+        "UPDATE" => Some(StatementKind::Update),
+        "DELETE" => Some(StatementKind::Delete),
+        "MERGE" => Some(StatementKind::Merge),
+        _ => None,
+    }
+}
+
+/// Classifies a single statement by its leading keyword, looking past comments,
+/// opening parentheses and any `WITH` clause to the statement it qualifies.
+pub fn statement_kind(sql: &str) -> StatementKind {
+    let tokens = tokenize(sql);
+    let mut significant = tokens.iter().filter(|t| !t.is_trivia() && t.text != "(");
+
+    let Some(first) = significant.next() else {
+        return StatementKind::Other;
+    };
+
+    if !first.is_keyword("WITH") {
+        return keyword_kind(first).unwrap_or(StatementKind::Other);
+    }
+
+    // The main statement is the first verb outside the parenthesised CTE bodies.
+    let mut depth = 0usize;
+    for token in tokens.iter().skip_while(|t| t.start <= first.start) {
+        match token.text {
+            "(" => depth += 1,
+            ")" => depth = depth.saturating_sub(1),
+            _ if depth == 0 => {
+                if let Some(kind) = keyword_kind(token) {
+                    return kind;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    StatementKind::Other
+}
+
+/// How a transaction-control statement moves its session between transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionControl {
+    /// `BEGIN` or `START TRANSACTION`.
+    Begin,
+    /// `COMMIT`, `END`, `ROLLBACK`, `ABORT` or `PREPARE TRANSACTION`.
+    End,
+    /// `COMMIT AND CHAIN` or `ROLLBACK AND CHAIN`, which end the transaction and
+    /// start another straight away.
+    Chain,
+    /// `ROLLBACK TO SAVEPOINT`, which recovers a failed transaction.
+    RollbackToSavepoint,
+}
+
+/// Recognises statements that open or close a transaction.
+///
+/// Two-phase `COMMIT PREPARED` and `ROLLBACK PREPARED` act on another
+/// transaction, so they do not count.
+pub fn transaction_control(sql: &str) -> Option<TransactionControl> {
+    let tokens = tokenize(sql);
+    let words: Vec<&Token> = tokens.iter().filter(|t| !t.is_trivia()).collect();
+    let is = |index: usize, keyword: &str| words.get(index).is_some_and(|t| t.is_keyword(keyword));
+    let chained = words
+        .windows(2)
+        .any(|pair| pair[0].is_keyword("AND") && pair[1].is_keyword("CHAIN"));
+
+    let first = words.first().filter(|t| t.kind == TokenKind::Word)?;
+    match first.text.to_ascii_uppercase().as_str() {
+        "BEGIN" => Some(TransactionControl::Begin),
+        "START" if is(1, "TRANSACTION") => Some(TransactionControl::Begin),
+        "PREPARE" if is(1, "TRANSACTION") => Some(TransactionControl::End),
+        "COMMIT" | "ROLLBACK" if is(1, "PREPARED") => None,
+        "ROLLBACK" | "ABORT" if is(1, "TO") || is(2, "TO") => {
+            Some(TransactionControl::RollbackToSavepoint)
+        }
+        "COMMIT" | "END" | "ROLLBACK" | "ABORT" if chained => Some(TransactionControl::Chain),
+        "COMMIT" | "END" | "ROLLBACK" | "ABORT" => Some(TransactionControl::End),
+        _ => None,
+    }
+}
+
+/// Whether a statement only reads, so stopping it part way loses nothing: a
+/// query that never mentions `INSERT`, `UPDATE`, `DELETE`, `MERGE` or `SHARE`.
+/// That rules out writing CTEs, `EXPLAIN ANALYZE` of a write and row locks.
+pub fn reads_only(sql: &str) -> bool {
+    const WRITES: [&str; 5] = ["INSERT", "UPDATE", "DELETE", "MERGE", "SHARE"];
+
+    statement_kind(sql) == StatementKind::Query
+        && !tokenize(sql)
+            .iter()
+            .any(|t| WRITES.iter().any(|keyword| t.is_keyword(keyword)))
+}
+
+/// Whether a statement deallocates every prepared statement in the session:
+/// `DISCARD ALL` or `DEALLOCATE [PREPARE] ALL`.
+pub fn discards_prepared_statements(sql: &str) -> bool {
+    let tokens = tokenize(sql);
+    let words: Vec<&Token> = tokens.iter().filter(|t| !t.is_trivia()).collect();
+    let is = |index: usize, keyword: &str| words.get(index).is_some_and(|t| t.is_keyword(keyword));
+
+    (is(0, "DISCARD") && is(1, "ALL"))
+        || (is(0, "DEALLOCATE") && (is(1, "ALL") || (is(1, "PREPARE") && is(2, "ALL"))))
+}
+
+/// `sql` without the semicolon, comments and whitespace after its last token.
+pub fn trim_statement(sql: &str) -> &str {
+    tokenize(sql)
+        .iter()
+        .rfind(|t| !t.is_trivia() && t.kind != TokenKind::Semicolon)
+        .map_or("", |last| &sql[..last.start + last.text.len()])
+}
+
+/// `sql` with `RETURNING *` added, unless it already has a `RETURNING` clause
+/// outside parentheses. A trailing semicolon and comments are dropped.
+pub fn with_returning(sql: &str) -> String {
+    let body = trim_statement(sql);
+    let mut depth = 0usize;
+    for token in tokenize(body).iter().filter(|t| !t.is_trivia()) {
+        match token.text {
+            "(" => depth += 1,
+            ")" => depth = depth.saturating_sub(1),
+            _ if depth == 0 && token.is_keyword("RETURNING") => return body.to_string(),
+            _ => {}
+        }
+    }
+
+    format!("{body} RETURNING *")
+}
+
+/// Why a statement counts as destructive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Destructive {
+    UpdateWithoutWhere,
+    DeleteWithoutWhere,
+    Drop,
+    Truncate,
+    Alter,
+}
+
+impl Destructive {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Destructive::UpdateWithoutWhere => "UPDATE without WHERE",
+            Destructive::DeleteWithoutWhere => "DELETE without WHERE",
+            Destructive::Drop => "DROP",
+            Destructive::Truncate => "TRUNCATE",
+            Destructive::Alter => "ALTER",
+        }
+    }
+}
+
+/// Recognises statements that destroy data or schema wholesale: `DROP`,
+/// `TRUNCATE`, `ALTER`, and `UPDATE` or `DELETE` with no `WHERE` of their own,
+/// whether as the main statement or in a `WITH` query.
+pub fn destructive_statement(sql: &str) -> Option<Destructive> {
+    let tokens = tokenize(sql);
+    let significant: Vec<&Token> = tokens.iter().filter(|t| !t.is_trivia()).collect();
+
+    match significant.first()?.text.to_ascii_uppercase().as_str() {
+        "DROP" => return Some(Destructive::Drop),
+        "TRUNCATE" => return Some(Destructive::Truncate),
+        "ALTER" => return Some(Destructive::Alter),
+        _ => {}
+    }
+
+    let mut depth = 0usize;
+    let mut main_seen = false;
+    for (index, token) in significant.iter().enumerate() {
+        let starts_main = depth == 0 && !main_seen && keyword_kind(token).is_some();
+        let starts_cte = index >= 2
+            && significant[index - 1].text == "("
+            && (significant[index - 2].is_keyword("AS")
+                || significant[index - 2].is_keyword("MATERIALIZED"));
+        main_seen |= starts_main;
+
+        if starts_main || starts_cte {
+            let destructive = match keyword_kind(token) {
+                Some(StatementKind::Update) => Some(Destructive::UpdateWithoutWhere),
+                Some(StatementKind::Delete) => Some(Destructive::DeleteWithoutWhere),
+                _ => None,
+            };
+            if destructive.is_some() && lacks_where(&significant[index + 1..]) {
+                return destructive;
+            }
+        }
+
+        match token.text {
+            "(" => depth += 1,
+            ")" => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Whether the statement body in `tokens`, which ends at an unmatched `)` or
+/// the end of input, has no `WHERE` outside parentheses.
+fn lacks_where(tokens: &[&Token]) -> bool {
+    let mut depth = 0usize;
+    for token in tokens {
+        match token.text {
+            "(" => depth += 1,
+            ")" if depth == 0 => return true,
+            ")" => depth -= 1,
+            _ if depth == 0 && token.is_keyword("WHERE") => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Where the text of rewritten SQL came from, so positions the server reports
+/// in it can be pointed back at the statement as written.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    /// What each rewrite replaced, in the order the rewrites ran.
+    rewrites: Vec<Vec<Replacement>>,
+}
+
+/// Text that took the place of some of the original, as character ranges.
+#[derive(Debug, Clone)]
+struct Replacement {
+    original: std::ops::Range<usize>,
+    rewritten: std::ops::Range<usize>,
+}
+
+impl SourceMap {
+    /// The 1-based character position in the statement as written that
+    /// `position` in the rewritten one came from. A position inside replaced
+    /// text points at the start of what it replaced.
+    pub fn original_position(&self, position: usize) -> usize {
+        self.rewrites
+            .iter()
+            .rev()
+            .fold(position, |position, replacements| {
+                original_offset(replacements, position.saturating_sub(1)) + 1
+            })
+    }
+}
+
+fn original_offset(replacements: &[Replacement], offset: usize) -> usize {
+    let (mut rewritten_end, mut original_end) = (0, 0);
+    for replacement in replacements {
+        if offset < replacement.rewritten.start {
+            break;
+        }
+        if offset < replacement.rewritten.end {
+            return replacement.original.start;
+        }
+        rewritten_end = replacement.rewritten.end;
+        original_end = replacement.original.end;
+    }
+    offset - rewritten_end + original_end
+}
+
+/// Builds rewritten SQL a token at a time, recording what it replaced.
+#[derive(Default)]
+struct Rewriter {
+    out: String,
+    replacements: Vec<Replacement>,
+    original_chars: usize,
+    rewritten_chars: usize,
+}
+
+impl Rewriter {
+    fn keep(&mut self, text: &str) {
+        let chars = text.chars().count();
+        self.out.push_str(text);
+        self.original_chars += chars;
+        self.rewritten_chars += chars;
+    }
+
+    fn replace(&mut self, original: &str, text: &str) {
+        let original_start = self.original_chars;
+        let rewritten_start = self.rewritten_chars;
+        self.out.push_str(text);
+        self.original_chars += original.chars().count();
+        self.rewritten_chars += text.chars().count();
+        self.replacements.push(Replacement {
+            original: original_start..self.original_chars,
+            rewritten: rewritten_start..self.rewritten_chars,
+        });
+    }
+
+    /// The rewritten SQL, and `source` extended to reach back through it.
+    fn finish(self, source: &SourceMap) -> (String, SourceMap) {
+        let mut source = source.clone();
+        source.rewrites.push(self.replacements);
+        (self.out, source)
+    }
+}
+
+/// A statement whose placeholders are all positional.
+#[derive(Debug, Clone)]
+pub struct NumberedSql {
+    pub sql: String,
+    /// The name each placeholder was written with, by position: `None` for
+    /// `$1`-style placeholders.
+    pub names: Vec<Option<String>>,
+    /// Maps positions in `sql` back to the statement as written.
+    pub source: SourceMap,
+}
+
+/// Rewrites `:name` placeholders as positional ones numbered after the highest
+/// `$n` already in the statement; repeats of a name share one number.
+///
+/// Casts (`::type`), PL/pgSQL assignments (`:=`) and array slices (`a[lo:hi]`)
+/// are left alone, as is anything inside literals and comments.
+pub fn number_placeholders(sql: &str) -> NumberedSql {
+    let tokens = tokenize(sql);
+    let positional = tokens
+        .iter()
+        .filter(|t| t.kind == TokenKind::Parameter)
+        .filter_map(|t| t.text[1..].parse::<usize>().ok())
+        .max()
+        .unwrap_or(0);
+    let mut names: Vec<Option<String>> = vec![None; positional];
+
+    let mut out = Rewriter::default();
+    let mut brackets = 0usize;
+    let mut skip_next = false;
+    for (index, token) in tokens.iter().enumerate() {
+        if std::mem::take(&mut skip_next) {
+            continue;
+        }
+
+        match token.text {
+            "[" => brackets += 1,
+            "]" => brackets = brackets.saturating_sub(1),
+            _ => {}
+        }
+
+        let previous = index.checked_sub(1).map(|i| tokens[i].text);
+        let name = tokens.get(index + 1).filter(|t| t.kind == TokenKind::Word);
+        if let (":", Some(name), 0) = (token.text, name, brackets) {
+            if previous != Some(":") {
+                let number = match names.iter().position(|n| n.as_deref() == Some(name.text)) {
+                    Some(existing) => existing + 1,
+                    None => {
+                        names.push(Some(name.text.to_string()));
+                        names.len()
+                    }
+                };
+                out.replace(&format!(":{}", name.text), &format!("${number}"));
+                skip_next = true;
+                continue;
+            }
+        }
+
+        out.keep(token.text);
+    }
+
+    let (sql, source) = out.finish(&SourceMap::default());
+    NumberedSql { sql, names, source }
+}
+
+/// Rewrites each `$n` as `($n::text::type)` with `types[n - 1]`, so every
+/// value can be sent as text and converted by the type's own input function.
+///
+/// The types are spliced in as they are, so they must be type names the server
+/// printed. Returns the SQL and a map back to the statement as written.
+pub fn cast_placeholders(numbered: &NumberedSql, types: &[String]) -> (String, SourceMap) {
+    let mut out = Rewriter::default();
+    for token in tokenize(&numbered.sql) {
+        let data_type = (token.kind == TokenKind::Parameter)
+            .then(|| token.text[1..].parse::<usize>().ok())
+            .flatten()
+            .and_then(|n| types.get(n.checked_sub(1)?));
+
+        match data_type {
+            Some(data_type) => {
+                out.replace(token.text, &format!("({}::text::{data_type})", token.text))
+            }
+            None => out.keep(token.text),
+        }
+    }
+    out.finish(&numbered.source)
+}
+
+/// Double-quotes an identifier for use in generated SQL.
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(sql: &str) -> Vec<String> {
+        split_statements(sql).into_iter().map(|s| s.text).collect()
+    }
+
+    fn kinds(sql: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(sql)
+            .into_iter()
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| (t.kind, t.text))
+            .collect()
+    }
+
+    #[test]
+    fn tokenizes_dollar_quoted_bodies() {
+        assert_eq!(
+            kinds("SELECT $fn$ a; $$ b $fn$, $1"),
+            vec![
+                (TokenKind::Word, "SELECT"),
+                (TokenKind::DollarString, "$fn$ a; $$ b $fn$"),
+                (TokenKind::Punct, ","),
+                (TokenKind::Parameter, "$1"),
+            ]
+        );
+        assert_eq!(kinds("$$;$$"), vec![(TokenKind::DollarString, "$$;$$")]);
+    }
+
+    #[test]
+    fn tokenizes_nested_block_comments() {
+        assert_eq!(
+            kinds("/* a /* b; */ c; */ x"),
+            vec![
+                (TokenKind::Comment, "/* a /* b; */ c; */"),
+                (TokenKind::Word, "x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenizes_escape_strings() {
+        assert_eq!(
+            kinds(r"E'\';' x"),
+            vec![(TokenKind::String, r"E'\';'"), (TokenKind::Word, "x")]
+        );
+        // Backslashes are plain characters in standard strings.
+        assert_eq!(
+            kinds(r"'a\' x"),
+            vec![(TokenKind::String, r"'a\'"), (TokenKind::Word, "x")]
+        );
+        assert_eq!(kinds("'it''s'"), vec![(TokenKind::String, "'it''s'")]);
+    }
+
+    #[test]
+    fn splits_on_top_level_semicolons() {
+        assert_eq!(texts("SELECT 1; SELECT 2;"), vec!["SELECT 1", "SELECT 2"]);
+        assert_eq!(texts("SELECT 1"), vec!["SELECT 1"]);
+        assert!(texts(" ; ;\n").is_empty());
+    }
+
+    #[test]
+    fn keeps_dollar_quoted_bodies_whole() {
+        let body = "CREATE FUNCTION f() RETURNS int AS $body$ SELECT 1; $$;$$ $body$ LANGUAGE sql";
+        assert_eq!(texts(&format!("{body}; SELECT 2")), vec![body, "SELECT 2"]);
+    }
+
+    #[test]
+    fn ignores_semicolons_in_comments() {
+        assert_eq!(
+            texts("SELECT 1 /* a /* b; */ c; */; SELECT 2 -- d; e\n;"),
+            vec!["SELECT 1 /* a /* b; */ c; */", "SELECT 2 -- d; e"]
+        );
+        assert_eq!(texts("SELECT 1; -- done;\n/* ; */"), vec!["SELECT 1"]);
+    }
+
+    #[test]
+    fn ignores_semicolons_in_strings() {
+        assert_eq!(
+            texts(r#"SELECT E'\';'; SELECT ';', ";";"#),
+            vec![r"SELECT E'\';'", r#"SELECT ';', ";""#]
+        );
+    }
+
+    #[test]
+    fn keeps_begin_atomic_bodies_whole() {
+        let body = "CREATE OR REPLACE FUNCTION f() RETURNS int LANGUAGE sql\n\
+                    BEGIN ATOMIC\n  SELECT CASE WHEN true THEN 1 END;\n  SELECT 2;\nEND";
+        assert_eq!(texts(&format!("{body}; SELECT 3;")), vec![body, "SELECT 3"]);
+    }
+
+    #[test]
+    fn transaction_blocks_still_split() {
+        assert_eq!(
+            texts("BEGIN; UPDATE t SET a = 1; END;"),
+            vec!["BEGIN", "UPDATE t SET a = 1", "END"]
+        );
+    }
+
+    #[test]
+    fn reports_offsets_in_characters() {
+        let statements = split_statements("SELECT 'é';\n  SELECT 2");
+        assert_eq!(statements[1].offset, 14);
+    }
+
+    #[test]
+    fn maps_positions_back_through_placeholder_rewrites() {
+        let sql = "SELECT :id, nosuch FROM t WHERE a = $1";
+        let numbered = number_placeholders(sql);
+        let types = ["integer".to_string(), "text".to_string()];
+        let (rewritten, source) = cast_placeholders(&numbered, &types);
+        assert_eq!(
+            rewritten,
+            "SELECT ($2::text::text), nosuch FROM t WHERE a = ($1::text::integer)"
+        );
+
+        let position = |text: &str, of: &str| text.find(of).unwrap() + 1;
+        assert_eq!(
+            source.original_position(position(&rewritten, "nosuch")),
+            position(sql, "nosuch")
+        );
+        assert_eq!(
+            source.original_position(position(&rewritten, "::integer")),
+            position(sql, "$1")
+        );
+        assert_eq!(
+            source.original_position(position(&rewritten, "$2")),
+            position(sql, ":id")
+        );
+        assert_eq!(source.original_position(1), 1);
+    }
+
+    fn numbered(sql: &str) -> (String, Vec<Option<String>>) {
+        let numbered = number_placeholders(sql);
+        (numbered.sql, numbered.names)
+    }
+
+    #[test]
+    fn leaves_colons_in_literals_and_comments_alone() {
+        for sql in [
+            "SELECT ':a', \":b\", E'\\:c'",
+            "SELECT $$ :d $$, $q$:e$q$",
+            "SELECT 1 -- :f\n/* :g /* :h */ */",
+        ] {
+            assert_eq!(numbered(sql), (sql.to_string(), vec![]), "{sql}");
+        }
+    }
+
+    #[test]
+    fn leaves_casts_assignments_and_slices_alone() {
+        for sql in [
+            "SELECT a::int, b :: text, c::timestamp(3)",
+            "DO $$ BEGIN x := 1; END $$",
+            "SELECT a[1:2], a[lo:hi]",
+        ] {
+            assert_eq!(numbered(sql), (sql.to_string(), vec![]), "{sql}");
+        }
+        assert_eq!(
+            numbered("SELECT :a::int"),
+            ("SELECT $1::int".to_string(), vec![Some("a".to_string())])
+        );
+    }
+
+    #[test]
+    fn numbers_each_name_once() {
+        let name = |n: &str| Some(n.to_string());
+        assert_eq!(
+            numbered("SELECT :a, :b, :a"),
+            ("SELECT $1, $2, $1".to_string(), vec![name("a"), name("b")])
+        );
+        assert_eq!(
+            numbered("SELECT $2, :a WHERE x = :a"),
+            (
+                "SELECT $2, $3 WHERE x = $3".to_string(),
+                vec![None, None, name("a")]
+            )
+        );
+    }
+
+    #[test]
+    fn maps_positions_back_through_named_placeholders() {
+        let sql = "SELECT :a_long_name, :b, nosuch, :a_long_name";
+        let numbered = number_placeholders(sql);
+        assert_eq!(numbered.sql, "SELECT $1, $2, nosuch, $1");
+
+        let position = |text: &str, of: &str| text.find(of).unwrap() + 1;
+        assert_eq!(
+            numbered
+                .source
+                .original_position(position(&numbered.sql, "nosuch")),
+            position(sql, "nosuch")
+        );
+        assert_eq!(
+            numbered
+                .source
+                .original_position(position(&numbered.sql, "$2")),
+            position(sql, ":b")
+        );
+        assert_eq!(
+            numbered.source.original_position(numbered.sql.len()),
+            sql.rfind(":a_long_name").unwrap() + 1
+        );
+    }
+
+    #[test]
+    fn flags_delete_without_where() {
+        let expected = Some(Destructive::DeleteWithoutWhere);
+        assert_eq!(destructive_statement("DELETE FROM t"), expected);
+        assert_eq!(
+            destructive_statement("/* clean up */ DELETE FROM t;"),
+            expected
+        );
+        assert_eq!(
+            destructive_statement("-- clean up\ndelete from t"),
+            expected
+        );
+        assert_eq!(destructive_statement("DELETE FROM t WHERE id = 1"), None);
+    }
+
+    #[test]
+    fn flags_truncate() {
+        let expected = Some(Destructive::Truncate);
+        assert_eq!(destructive_statement("TRUNCATE t"), expected);
+        assert_eq!(
+            destructive_statement("-- reset\nTRUNCATE TABLE t"),
+            expected
+        );
+        assert_eq!(destructive_statement("/* reset */ truncate t"), expected);
+    }
+
+    #[test]
+    fn flags_statements_wrapped_in_ctes() {
+        assert_eq!(
+            destructive_statement("WITH gone AS (DELETE FROM t RETURNING *) SELECT * FROM gone"),
+            Some(Destructive::DeleteWithoutWhere)
+        );
+        assert_eq!(
+            destructive_statement(
+                "WITH changed AS MATERIALIZED (UPDATE t SET a = 1 RETURNING *) SELECT 1"
+            ),
+            Some(Destructive::UpdateWithoutWhere)
+        );
+        assert_eq!(
+            destructive_statement("WITH ids AS (SELECT id FROM u WHERE x) DELETE FROM t"),
+            Some(Destructive::DeleteWithoutWhere)
+        );
+        assert_eq!(
+            destructive_statement("WITH ids AS (SELECT id FROM u) UPDATE t SET a = 1"),
+            Some(Destructive::UpdateWithoutWhere)
+        );
+        assert_eq!(
+            destructive_statement(
+                "WITH gone AS (DELETE FROM t WHERE id = 1 RETURNING *) SELECT * FROM gone"
+            ),
+            None
+        );
+        assert_eq!(
+            destructive_statement("WITH ids AS (SELECT id FROM u) DELETE FROM t WHERE id = 1"),
+            None
+        );
+    }
+
+    #[test]
+    fn where_inside_a_subquery_does_not_count() {
+        assert_eq!(
+            destructive_statement("UPDATE t SET a = (SELECT b FROM u WHERE u.id = t.id)"),
+            Some(Destructive::UpdateWithoutWhere)
+        );
+        assert_eq!(
+            destructive_statement(
+                "UPDATE t SET a = (SELECT b FROM u WHERE u.id = t.id) WHERE t.flag"
+            ),
+            None
+        );
+        assert_eq!(
+            destructive_statement("DELETE FROM t USING (SELECT id FROM u WHERE x) AS s"),
+            Some(Destructive::DeleteWithoutWhere)
+        );
+    }
+
+    #[test]
+    fn where_in_comments_or_strings_does_not_count() {
+        assert_eq!(
+            destructive_statement("DELETE FROM t -- WHERE id = 1"),
+            Some(Destructive::DeleteWithoutWhere)
+        );
+        assert_eq!(
+            destructive_statement("UPDATE t SET a = 'WHERE'"),
+            Some(Destructive::UpdateWithoutWhere)
+        );
+    }
+
+    #[test]
+    fn reads_only_excludes_writes_and_row_locks() {
+        assert!(reads_only("SELECT * FROM t"));
+        assert!(reads_only("WITH a AS (SELECT 1) SELECT * FROM a"));
+        assert!(reads_only("SELECT 'DELETE' -- UPDATE"));
+        assert!(!reads_only("INSERT INTO t VALUES (1) RETURNING *"));
+        assert!(!reads_only(
+            "WITH gone AS (DELETE FROM t RETURNING *) SELECT * FROM gone"
+        ));
+        assert!(!reads_only("EXPLAIN ANALYZE UPDATE t SET a = 1"));
+        assert!(!reads_only("SELECT * FROM t FOR UPDATE"));
+        assert!(!reads_only("SELECT * FROM t FOR KEY SHARE"));
+        assert!(!reads_only("CREATE TABLE t (a int)"));
+    }
+
+    #[test]
+    fn classifies_statements() {
+        use StatementKind::*;
+        let cases = [
+            ("SELECT 1", Query),
+            ("  (SELECT 1) UNION (SELECT 2)", Query),
+            ("VALUES (1), (2)", Query),
+            ("TABLE t", Query),
+            ("SHOW search_path", Query),
+            ("EXPLAIN SELECT 1", Query),
+            ("EXPLAIN ANALYZE DELETE FROM t", Query),
+            ("FETCH 10 FROM c", Query),
+            ("-- count them\nSELECT count(*) FROM t", Query),
+            ("/* a /* nested */ note */ values (1)", Query),
+            ("INSERT INTO t VALUES (1)", Insert),
+            (
+                "WITH src AS (SELECT 1 AS a) INSERT INTO t SELECT a FROM src RETURNING *",
+                Insert,
+            ),
+            (
+                "WITH RECURSIVE r(n) AS (SELECT 1 UNION SELECT n + 1 FROM r) SELECT * FROM r",
+                Query,
+            ),
+            (
+                "WITH gone AS (DELETE FROM t RETURNING *) SELECT * FROM gone",
+                Query,
+            ),
+            ("/* fix */ UPDATE t SET a = 1", Update),
+            ("WITH ids AS (SELECT 1) DELETE FROM t", Delete),
+            (
+                "MERGE INTO t USING s ON t.id = s.id WHEN MATCHED THEN DELETE",
+                Merge,
+            ),
+            ("CREATE TABLE t (a int)", Other),
+            ("BEGIN", Other),
+            ("SET search_path TO public", Other),
+            ("-- only a comment", Other),
+            ("", Other),
+        ];
+
+        for (sql, expected) in cases {
+            assert_eq!(statement_kind(sql), expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn recognises_transaction_control() {
+        use TransactionControl::*;
+        let cases = [
+            ("BEGIN", Some(Begin)),
+            ("begin isolation level serializable", Some(Begin)),
+            ("START TRANSACTION READ ONLY", Some(Begin)),
+            ("-- go\nBEGIN WORK", Some(Begin)),
+            ("COMMIT", Some(End)),
+            ("END", Some(End)),
+            ("/* undo */ ROLLBACK", Some(End)),
+            ("ABORT", Some(End)),
+            ("PREPARE TRANSACTION 'tx'", Some(End)),
+            ("COMMIT AND CHAIN", Some(Chain)),
+            ("ROLLBACK WORK AND CHAIN", Some(Chain)),
+            ("COMMIT AND NO CHAIN", Some(End)),
+            ("ROLLBACK TO SAVEPOINT sp", Some(RollbackToSavepoint)),
+            ("ROLLBACK TO sp", Some(RollbackToSavepoint)),
+            ("ROLLBACK WORK TO SAVEPOINT sp", Some(RollbackToSavepoint)),
+            ("COMMIT PREPARED 'tx'", None),
+            ("ROLLBACK PREPARED 'tx'", None),
+            ("SAVEPOINT sp", None),
+            ("RELEASE SAVEPOINT sp", None),
+            ("START something", None),
+            ("SELECT 'BEGIN'", None),
+            ("-- COMMIT", None),
+        ];
+
+        for (sql, expected) in cases {
+            assert_eq!(transaction_control(sql), expected, "{sql}");
+        }
+    }
+}
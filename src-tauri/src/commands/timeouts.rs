@@ -0,0 +1,167 @@
+//! Server-side time limits on statements.
+//!
+//! A connection's limits are startup options of every backend it opens, pooled
+//! or pinned to a tab. One execution can override them; the previous values are
+//! put back once its statement is done.
+
+use sqlx::postgres::{PgConnectOptions, PgConnection};
+use sqlx::Row;
+
+use super::error::CommandError;
+
+/// Time limits in whole seconds. `None` leaves the server's own setting, and
+/// `0` turns a limit off.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Timeouts {
+    /// `statement_timeout`: how long a single statement may run.
+    pub statement_secs: Option<u64>,
+    /// `lock_timeout`: how long a statement may wait for a lock.
+    pub lock_secs: Option<u64>,
+    /// `idle_in_transaction_session_timeout`: how long a session may sit in an
+    /// open transaction before the server closes it.
+    pub idle_in_transaction_secs: Option<u64>,
+}
+
+/// Which limit stopped a statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimeoutKind {
+    Statement,
+    Lock,
+    IdleInTransaction,
+}
+
+impl TimeoutKind {
+    /// What the user is told, e.g. "Statement timed out after 30 s".
+    pub fn message(self, seconds: Option<u64>) -> String {
+        let after = seconds.map(|s| format!(" after {s} s")).unwrap_or_default();
+        match self {
+            TimeoutKind::Statement => format!("Statement timed out{after}"),
+            TimeoutKind::Lock => format!("Timed out{after} waiting for a lock"),
+            TimeoutKind::IdleInTransaction => {
+                format!("Session closed{after} idle in a transaction")
+            }
+        }
+    }
+}
+
+impl Timeouts {
+    /// These limits, with any that `overrides` sets taking precedence.
+    pub fn merged(self, overrides: Option<Timeouts>) -> Self {
+        let overrides = overrides.unwrap_or_default();
+        Self {
+            statement_secs: overrides.statement_secs.or(self.statement_secs),
+            lock_secs: overrides.lock_secs.or(self.lock_secs),
+            idle_in_transaction_secs: overrides
+                .idle_in_transaction_secs
+                .or(self.idle_in_transaction_secs),
+        }
+    }
+
+    pub fn seconds(&self, kind: TimeoutKind) -> Option<u64> {
+        match kind {
+            TimeoutKind::Statement => self.statement_secs,
+            TimeoutKind::Lock => self.lock_secs,
+            TimeoutKind::IdleInTransaction => self.idle_in_transaction_secs,
+        }
+    }
+
+    /// The server settings for the limits that are set, e.g.
+    /// `("statement_timeout", "30s")`.
+    fn settings(&self) -> Vec<(&'static str, String)> {
+        [
+            ("statement_timeout", self.statement_secs),
+            ("lock_timeout", self.lock_secs),
+            (
+                "idle_in_transaction_session_timeout",
+                self.idle_in_transaction_secs,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, secs)| secs.map(|secs| (name, format!("{secs}s"))))
+        .collect()
+    }
+
+    /// Makes every connection opened with `options` start with these limits.
+    pub fn apply_to(&self, options: PgConnectOptions) -> PgConnectOptions {
+        let settings = self.settings();
+        if settings.is_empty() {
+            return options;
+        }
+        options.options(settings)
+    }
+
+    /// Turns a server error caused by one of these limits into a
+    /// [`CommandError::TimedOut`]; other errors are returned unchanged.
+    ///
+    /// A cancellation requested by the user shares its SQLSTATE with a statement
+    /// timeout, so the two are told apart by the server's message.
+    pub fn classify(&self, error: CommandError) -> CommandError {
+        let kind = match error.database() {
+            Some(e) if e.code == "57014" && e.message.contains("statement timeout") => {
+                TimeoutKind::Statement
+            }
+            Some(e) if e.code == "55P03" && e.message.contains("lock timeout") => TimeoutKind::Lock,
+            Some(e) if e.code == "25P03" => TimeoutKind::IdleInTransaction,
+            _ => return error,
+        };
+
+        CommandError::TimedOut {
+            kind,
+            seconds: self.seconds(kind),
+        }
+    }
+}
+
+/// Settings an execution overrode, to be put back when it is done.
+pub struct SavedSettings(Vec<(String, String)>);
+
+impl SavedSettings {
+    /// Puts the settings back. Failure is ignored: it means the transaction
+    /// failed, and rolling it back reverts the override as well.
+    pub async fn restore(self, conn: &mut PgConnection) {
+        let (names, values): (Vec<String>, Vec<String>) = self.0.into_iter().unzip();
+        let _ = sqlx::query(
+            r#"
+            SELECT set_config(name, value, false)
+            FROM unnest($1::text[], $2::text[]) AS s(name, value)
+            "#,
+        )
+        .bind(names)
+        .bind(values)
+        .execute(conn)
+        .await;
+    }
+}
+
+/// Applies `overrides` to `conn` for one execution, returning the settings to
+/// restore afterwards, or `None` when nothing was overridden.
+pub async fn override_timeouts(
+    conn: &mut PgConnection,
+    overrides: Option<Timeouts>,
+) -> Result<Option<SavedSettings>, CommandError> {
+    let settings = overrides.unwrap_or_default().settings();
+    if settings.is_empty() {
+        return Ok(None);
+    }
+
+    let (names, values): (Vec<&str>, Vec<String>) = settings.into_iter().unzip();
+    let rows = sqlx::query(
+        r#"
+        SELECT s.name, current_setting(s.name) AS previous, set_config(s.name, s.value, false)
+        FROM unnest($1::text[], $2::text[]) AS s(name, value)
+        "#,
+    )
+    .bind(names)
+    .bind(values)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| CommandError::sqlx("Failed to set timeouts", e))?;
+
+    Ok(Some(SavedSettings(
+        rows.iter()
+            .map(|row| (row.get("name"), row.get("previous")))
+            .collect(),
+    )))
+}
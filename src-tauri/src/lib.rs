@@ -1,9 +1,14 @@
 mod commands;
 
 use commands::{
-    connect, delete_connection, disconnect, execute_query, get_ai_settings, get_connection_info,
-    get_schema, list_saved_connections, load_editor_tabs, save_ai_settings, save_connection,
-    save_editor_tabs, send_ai_message, test_connection, AppState,
+    apply_row_changes, apply_row_operations, begin_transaction, cancel_query, clear_notifications,
+    close_result, close_session, commit, compare_plans, connect, delete_connection, describe_query,
+    disconnect, dry_run_query, execute_query, execute_script, explain_query, export_query,
+    fetch_more_rows, get_ai_settings, get_connection_info, get_listened_channels, get_schema,
+    get_transaction_state, list_notifications, list_running_queries, list_saved_connections,
+    listen_channel, load_editor_tabs, open_history_entry, reset_session, rollback,
+    save_ai_settings, save_connection, save_editor_tabs, search_history, send_ai_message,
+    test_connection, unlisten_channel, AppState,
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -19,6 +24,31 @@ pub fn run() {
             disconnect,
             get_connection_info,
             execute_query,
+            describe_query,
+            dry_run_query,
+            explain_query,
+            export_query,
+            compare_plans,
+            search_history,
+            open_history_entry,
+            listen_channel,
+            unlisten_channel,
+            get_listened_channels,
+            list_notifications,
+            clear_notifications,
+            execute_script,
+            fetch_more_rows,
+            close_result,
+            apply_row_changes,
+            apply_row_operations,
+            cancel_query,
+            list_running_queries,
+            begin_transaction,
+            commit,
+            rollback,
+            get_transaction_state,
+            reset_session,
+            close_session,
             get_schema,
             list_saved_connections,
             save_connection,
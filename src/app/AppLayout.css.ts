@@ -50,6 +50,13 @@ export const aiPanelArea = style({
   overflow: 'hidden',
 })
 
+export const sidePanelArea = style({
+  display: 'flex',
+  width: '360px',
+  flexShrink: 0,
+  overflow: 'hidden',
+})
+
 export const aiPanelDivider = style({
   width: '4px',
   flexShrink: 0,
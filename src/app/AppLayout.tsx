@@ -1,4 +1,4 @@
-import { useCallback, useRef, useState } from 'react'
+import { useCallback, useMemo, useRef, useState } from 'react'
 
 import { Tabs } from '../components/molecules/Tabs'
 import { SplitPane } from '../components/molecules/SplitPane'
@@ -6,8 +6,15 @@ import { TitleBar } from '../components/organisms/TitleBar'
 import { StatusBar } from '../components/organisms/StatusBar'
 import { AiPanel } from '../features/ai/components/AiPanel'
 import { useAiStore } from '../features/ai/stores/aiStore'
-import { SqlEditor, type SqlEditorHandle } from '../features/editor/components/SqlEditor'
+import {
+  SqlEditor,
+  type SqlEditorHandle,
+  type SqlErrorMarker,
+} from '../features/editor/components/SqlEditor'
+import { HistoryPanel } from '../features/history/components/HistoryPanel'
+import { NotificationsPanel } from '../features/notifications/components/NotificationsPanel'
 import { ResultsTable } from '../features/results/components/ResultsTable'
+import { useResultsStore } from '../features/results/stores/resultsStore'
 import { SchemaSidebar } from '../features/schema/components/SchemaSidebar'
 import { useEditor } from '../features/editor/hooks/useEditor'
 import * as styles from './AppLayout.css'
@@ -24,14 +31,27 @@ export function AppLayout() {
     updateTabName,
     runQuery,
     runQueryWithContent,
+    dryRun,
+    explain,
     appendWhereClause,
   } = useEditor()
 
   const { isPanelOpen } = useAiStore()
+  const { error, errorQuery } = useResultsStore()
+
+  const errorMarker = useMemo<SqlErrorMarker | null>(
+    () =>
+      error?.position && errorQuery
+        ? { query: errorQuery, position: error.position, message: error.message }
+        : null,
+    [error, errorQuery]
+  )
 
   const editorRef = useRef<SqlEditorHandle>(null)
   const [sidebarWidth, setSidebarWidth] = useState(240)
   const [aiPanelWidth, setAiPanelWidth] = useState(320)
+  const [isHistoryOpen, setHistoryOpen] = useState(false)
+  const [isNotificationsOpen, setNotificationsOpen] = useState(false)
 
   const handleDividerMouseDown = useCallback(
     (e: React.MouseEvent) => {
@@ -87,6 +107,12 @@ export function AppLayout() {
     [aiPanelWidth]
   )
 
+  const handleExplain = useCallback(
+    (query: string, analyze: boolean) =>
+      explain(query, analyze ? { analyze: true, buffers: true } : {}),
+    [explain]
+  )
+
   const handleTableDoubleClick = useCallback(
     (tableName: string) => {
       const query = `SELECT * FROM ${tableName} LIMIT 500;`
@@ -110,7 +136,12 @@ export function AppLayout() {
 
   return (
     <div className={styles.container}>
-      <TitleBar />
+      <TitleBar
+        isHistoryOpen={isHistoryOpen}
+        onToggleHistory={() => setHistoryOpen((open) => !open)}
+        isNotificationsOpen={isNotificationsOpen}
+        onToggleNotifications={() => setNotificationsOpen((open) => !open)}
+      />
 
       <div className={styles.main}>
         <div className={styles.sidebarArea} style={{ width: sidebarWidth }}>
@@ -135,6 +166,9 @@ export function AppLayout() {
                 value={activeTab?.content ?? ''}
                 onChange={updateTabContent}
                 onExecute={runQuery}
+                onDryRun={dryRun}
+                onExplain={handleExplain}
+                errorMarker={errorMarker}
               />
               <ResultsTable onAddWhereClause={handleAddWhereClause} />
             </SplitPane>
@@ -152,6 +186,18 @@ export function AppLayout() {
             </div>
           </>
         )}
+
+        {isHistoryOpen && (
+          <div className={styles.sidePanelArea}>
+            <HistoryPanel onClose={() => setHistoryOpen(false)} />
+          </div>
+        )}
+
+        {isNotificationsOpen && (
+          <div className={styles.sidePanelArea}>
+            <NotificationsPanel onClose={() => setNotificationsOpen(false)} />
+          </div>
+        )}
       </div>
 
       <StatusBar />
@@ -1,4 +1,4 @@
-import { style } from '@vanilla-extract/css'
+import { style, styleVariants } from '@vanilla-extract/css'
 
 import { vars } from '../../configs/theme.css'
 
@@ -32,3 +32,30 @@ export const shortcut = style({
   color: vars.color.foregroundSecondary,
   opacity: 0.7,
 })
+
+export const transaction = styleVariants({
+  idle: {},
+  inTransaction: {
+    color: vars.color.warning,
+  },
+  failed: {
+    color: vars.color.error,
+  },
+})
+
+export const transactionButton = style({
+  padding: `0 ${vars.space.xs}`,
+  border: `1px solid ${vars.color.border}`,
+  borderRadius: vars.radius.sm,
+  backgroundColor: 'transparent',
+  color: vars.color.foregroundSecondary,
+  fontSize: vars.fontSize.xs,
+  cursor: 'pointer',
+  ':hover': {
+    color: vars.color.foreground,
+  },
+  ':disabled': {
+    opacity: 0.5,
+    cursor: 'default',
+  },
+})
@@ -1,26 +1,93 @@
-import { Database, Clock, Rows3 } from 'lucide-react'
+import { Database, Clock, Rows3, GitCommitHorizontal } from 'lucide-react'
 
+import type { SafetyLevel, TransactionState } from '../../types/database'
+import { useEditor } from '../../features/editor/hooks/useEditor'
 import { useSchemaStore } from '../../features/schema/stores/schemaStore'
 import { useResultsStore } from '../../features/results/stores/resultsStore'
 import * as styles from './StatusBar.css'
 
+const TRANSACTION_LABELS: Record<TransactionState, string> = {
+  idle: 'Auto-commit',
+  inTransaction: 'In transaction',
+  failed: 'Transaction failed',
+}
+
+const SAFETY_LABELS: Record<SafetyLevel, string> = {
+  read_only: 'Read-only',
+  confirm_destructive: 'Confirms destructive statements',
+  unrestricted: '',
+}
+
 export function StatusBar() {
   const { connection } = useSchemaStore()
   const { results, isExecuting } = useResultsStore()
+  const {
+    transactionState,
+    beginTransaction,
+    commitTransaction,
+    rollbackTransaction,
+    resetSession,
+  } = useEditor()
 
   return (
     <div className={styles.container}>
       <div className={styles.section}>
         <Database size={12} />
         <span>{connection ? connection.name : 'Not connected'}</span>
+        {connection && connection.safety !== 'unrestricted' && (
+          <span>{SAFETY_LABELS[connection.safety]}</span>
+        )}
       </div>
 
+      {connection && (
+        <div className={styles.section}>
+          <GitCommitHorizontal size={12} />
+          <span className={styles.transaction[transactionState]}>
+            {TRANSACTION_LABELS[transactionState]}
+          </span>
+          {transactionState === 'idle' ? (
+            <button
+              className={styles.transactionButton}
+              onClick={beginTransaction}
+              disabled={isExecuting}
+            >
+              Begin
+            </button>
+          ) : (
+            <>
+              <button
+                className={styles.transactionButton}
+                onClick={commitTransaction}
+                disabled={isExecuting}
+              >
+                Commit
+              </button>
+              <button
+                className={styles.transactionButton}
+                onClick={rollbackTransaction}
+                disabled={isExecuting}
+              >
+                Rollback
+              </button>
+            </>
+          )}
+          <button
+            className={styles.transactionButton}
+            onClick={resetSession}
+            disabled={isExecuting}
+            title="Roll back and discard session settings, temporary tables and prepared statements"
+          >
+            Reset session
+          </button>
+        </div>
+      )}
+
       {results && (
         <>
           <div className={styles.section}>
             <Rows3 size={12} />
             <span>
-              {results.rowsAffected !== undefined
+              {results.rowsAffected != null && results.columns.length === 0
                 ? `${results.rowsAffected} affected`
                 : `${results.rows.length} rows`}
             </span>
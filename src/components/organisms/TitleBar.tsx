@@ -1,11 +1,23 @@
-import { LogOut, MessageSquare, RefreshCw } from 'lucide-react'
+import { Bell, History, LogOut, MessageSquare, RefreshCw } from 'lucide-react'
 
 import { Button } from '../atoms/Button'
 import { useAiStore } from '../../features/ai/stores/aiStore'
 import { useSchema } from '../../features/schema/hooks/useSchema'
 import * as styles from './TitleBar.css'
 
-export function TitleBar() {
+interface TitleBarProps {
+  isHistoryOpen?: boolean
+  onToggleHistory?: () => void
+  isNotificationsOpen?: boolean
+  onToggleNotifications?: () => void
+}
+
+export function TitleBar({
+  isHistoryOpen = false,
+  onToggleHistory,
+  isNotificationsOpen = false,
+  onToggleNotifications,
+}: TitleBarProps) {
   const { connection, isLoading, disconnectFromDatabase, refreshSchema } = useSchema()
   const { isPanelOpen, togglePanel } = useAiStore()
 
@@ -33,6 +45,28 @@ export function TitleBar() {
               <MessageSquare size={14} />
               AI
             </Button>
+            {onToggleHistory && (
+              <Button
+                variant="ghost"
+                size="sm"
+                onClick={onToggleHistory}
+                className={isHistoryOpen ? styles.activeToggle : undefined}
+              >
+                <History size={14} />
+                History
+              </Button>
+            )}
+            {onToggleNotifications && (
+              <Button
+                variant="ghost"
+                size="sm"
+                onClick={onToggleNotifications}
+                className={isNotificationsOpen ? styles.activeToggle : undefined}
+              >
+                <Bell size={14} />
+                Notify
+              </Button>
+            )}
             <Button variant="ghost" size="sm" onClick={disconnectFromDatabase} disabled={isLoading}>
               <LogOut size={14} />
               Disconnect
@@ -3,6 +3,7 @@ import { useEffect, useState, type FormEvent } from 'react'
 
 import { Button } from '../../../components/atoms/Button'
 import type { AiProvider, AiSettings as AiSettingsType, AiSettingsPublic } from '../../../types/ai'
+import { errorMessage } from '../../../utils/errors'
 import * as styles from './AiSettings.css'
 
 interface AiSettingsProps {
@@ -35,7 +36,7 @@ export function AiSettings({ settings, defaultModels, onSave, onClose }: AiSetti
       await onSave({ provider, apiKey, model })
       onClose()
     } catch (err) {
-      setSaveError(err instanceof Error ? err.message : String(err))
+      setSaveError(errorMessage(err))
     } finally {
       setIsSaving(false)
     }
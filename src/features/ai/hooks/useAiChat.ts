@@ -1,6 +1,7 @@
 import { useCallback, useEffect, useRef } from 'react'
 
 import type { AiSettings, AiSettingsPublic, ChatMessage } from '../../../types/ai'
+import { errorMessage } from '../../../utils/errors'
 import { useSchemaStore } from '../../schema/stores/schemaStore'
 import * as aiApi from '../api/aiApi'
 import { useAiStore } from '../stores/aiStore'
@@ -62,7 +63,7 @@ export function useAiChat() {
       .getAiSettings()
       .then(setSettings)
       .catch((err) => {
-        setError(err instanceof Error ? err.message : String(err))
+        setError(errorMessage(err))
       })
   }, [setSettings, setError])
 
@@ -86,7 +87,7 @@ export function useAiChat() {
         const assistantMessage: ChatMessage = { role: 'assistant', content: responseContent }
         addMessage(assistantMessage)
       } catch (err) {
-        setError(err instanceof Error ? err.message : String(err))
+        setError(errorMessage(err))
       } finally {
         setLoading(false)
       }
@@ -78,6 +78,29 @@ export const fieldRow = style({
   gap: vars.space.md,
 })
 
+export const fieldRowThirds = style({
+  display: 'grid',
+  gridTemplateColumns: '1fr 1fr 1fr',
+  gap: vars.space.md,
+})
+
+export const select = style({
+  width: '100%',
+  height: '36px',
+  padding: `0 ${vars.space.sm}`,
+  backgroundColor: vars.color.background,
+  border: `1px solid ${vars.color.border}`,
+  borderRadius: vars.radius.sm,
+  color: vars.color.foreground,
+  fontSize: vars.fontSize.sm,
+  outline: 'none',
+  cursor: 'pointer',
+
+  ':focus': {
+    borderColor: vars.color.borderFocus,
+  },
+})
+
 export const label = style({
   fontSize: vars.fontSize.sm,
   fontWeight: 500,
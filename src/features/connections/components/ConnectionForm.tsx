@@ -2,11 +2,18 @@ import { CheckCircle, Loader2, X, XCircle } from 'lucide-react'
 import { useState, type FormEvent } from 'react'
 
 import { Button } from '../../../components/atoms/Button'
-import type { SavedConnection } from '../../../types/database'
+import type { SafetyLevel, SavedConnection } from '../../../types/database'
+import { errorMessage } from '../../../utils/errors'
 import * as styles from './ConnectionForm.css'
 
 const DEFAULT_POSTGRES_PORT = 5432
 
+/** Seconds from a timeout field; blank leaves the server's setting. */
+function parseSeconds(value: string): number | null {
+  const seconds = parseInt(value, 10)
+  return Number.isNaN(seconds) || seconds < 0 ? null : seconds
+}
+
 interface ConnectionFormProps {
   connection?: SavedConnection
   onSave: (connection: Omit<SavedConnection, 'id'> | SavedConnection) => Promise<void>
@@ -23,6 +30,14 @@ export function ConnectionForm({ connection, onSave, onTest, onClose }: Connecti
   const [user, setUser] = useState(connection?.user ?? 'postgres')
   const [password, setPassword] = useState(connection?.password ?? '')
   const [database, setDatabase] = useState(connection?.database ?? '')
+  const [statementTimeout, setStatementTimeout] = useState(
+    connection?.timeouts?.statementSecs?.toString() ?? ''
+  )
+  const [lockTimeout, setLockTimeout] = useState(connection?.timeouts?.lockSecs?.toString() ?? '')
+  const [idleTimeout, setIdleTimeout] = useState(
+    connection?.timeouts?.idleInTransactionSecs?.toString() ?? ''
+  )
+  const [safety, setSafety] = useState<SafetyLevel>(connection?.safety ?? 'unrestricted')
   const [testStatus, setTestStatus] = useState<TestStatus>('idle')
   const [testError, setTestError] = useState<string | null>(null)
   const [isSaving, setIsSaving] = useState(false)
@@ -37,6 +52,12 @@ export function ConnectionForm({ connection, onSave, onTest, onClose }: Connecti
     user,
     password,
     database,
+    timeouts: {
+      statementSecs: parseSeconds(statementTimeout),
+      lockSecs: parseSeconds(lockTimeout),
+      idleInTransactionSecs: parseSeconds(idleTimeout),
+    },
+    safety,
   })
 
   const handleTest = async () => {
@@ -48,7 +69,7 @@ export function ConnectionForm({ connection, onSave, onTest, onClose }: Connecti
       setTestStatus('success')
     } catch (err) {
       setTestStatus('error')
-      setTestError(err instanceof Error ? err.message : String(err))
+      setTestError(errorMessage(err))
     }
   }
 
@@ -156,6 +177,55 @@ export function ConnectionForm({ connection, onSave, onTest, onClose }: Connecti
             />
           </div>
 
+          <div className={styles.fieldGroup}>
+            <label className={styles.label}>Safety</label>
+            <select
+              className={styles.select}
+              value={safety}
+              onChange={(e) => setSafety(e.target.value as SafetyLevel)}
+            >
+              <option value="unrestricted">Unrestricted</option>
+              <option value="confirm_destructive">Confirm destructive statements</option>
+              <option value="read_only">Read-only</option>
+            </select>
+          </div>
+
+          <div className={styles.fieldRowThirds}>
+            <div className={styles.fieldGroup}>
+              <label className={styles.label}>Statement timeout (s)</label>
+              <input
+                className={styles.input}
+                type="number"
+                min={0}
+                value={statementTimeout}
+                onChange={(e) => setStatementTimeout(e.target.value)}
+                placeholder="Server default"
+              />
+            </div>
+            <div className={styles.fieldGroup}>
+              <label className={styles.label}>Lock timeout (s)</label>
+              <input
+                className={styles.input}
+                type="number"
+                min={0}
+                value={lockTimeout}
+                onChange={(e) => setLockTimeout(e.target.value)}
+                placeholder="Server default"
+              />
+            </div>
+            <div className={styles.fieldGroup}>
+              <label className={styles.label}>Idle in transaction (s)</label>
+              <input
+                className={styles.input}
+                type="number"
+                min={0}
+                value={idleTimeout}
+                onChange={(e) => setIdleTimeout(e.target.value)}
+                placeholder="Server default"
+              />
+            </div>
+          </div>
+
           <div className={styles.footer}>
             <div className={styles.footerLeft}>
               <Button
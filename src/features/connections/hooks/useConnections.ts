@@ -1,6 +1,7 @@
 import { useCallback, useEffect } from 'react'
 
 import type { ConnectionConfig, SavedConnection } from '../../../types/database'
+import { errorMessage } from '../../../utils/errors'
 import { useSchemaStore } from '../../schema/stores/schemaStore'
 import * as connectionsApi from '../api/connectionsApi'
 import * as schemaApi from '../../schema/api/schemaApi'
@@ -29,8 +30,7 @@ export function useConnections() {
       const savedConnections = await connectionsApi.listSavedConnections()
       setConnections(savedConnections)
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err)
-      setError(errorMessage)
+      setError(errorMessage(err))
     } finally {
       setLoading(false)
     }
@@ -49,8 +49,7 @@ export function useConnections() {
         addConnection(saved)
         return saved
       } catch (err) {
-        const errorMessage = err instanceof Error ? err.message : String(err)
-        setError(errorMessage)
+        setError(errorMessage(err))
         throw err
       } finally {
         setLoading(false)
@@ -69,8 +68,7 @@ export function useConnections() {
         updateConnection(updated)
         return updated
       } catch (err) {
-        const errorMessage = err instanceof Error ? err.message : String(err)
-        setError(errorMessage)
+        setError(errorMessage(err))
         throw err
       } finally {
         setLoading(false)
@@ -88,8 +86,7 @@ export function useConnections() {
         await connectionsApi.deleteConnection(id)
         removeConnection(id)
       } catch (err) {
-        const errorMessage = err instanceof Error ? err.message : String(err)
-        setError(errorMessage)
+        setError(errorMessage(err))
         throw err
       } finally {
         setLoading(false)
@@ -103,8 +100,7 @@ export function useConnections() {
       try {
         return await connectionsApi.testConnection(connection)
       } catch (err) {
-        const errorMessage = err instanceof Error ? err.message : String(err)
-        throw new Error(errorMessage)
+        throw new Error(errorMessage(err))
       }
     },
     []
@@ -124,6 +120,8 @@ export function useConnections() {
           user: connection.user,
           password: connection.password,
           database: connection.database,
+          timeouts: connection.timeouts,
+          safety: connection.safety,
         }
 
         const connectionInfo = await connectionsApi.connect(config)
@@ -132,8 +130,7 @@ export function useConnections() {
         const schemaData = await schemaApi.getSchema()
         setSchema(schemaData)
       } catch (err) {
-        const errorMessage = err instanceof Error ? err.message : String(err)
-        setSchemaError(errorMessage)
+        setSchemaError(errorMessage(err))
         throw err
       } finally {
         setSchemaLoading(false)
@@ -1,7 +1,95 @@
 import { invoke } from '@tauri-apps/api/core'
 
-import type { QueryResult } from '../../../types/database'
+import type {
+  DryRunOptions,
+  DryRunResult,
+  ExplainOptions,
+  ExplainResult,
+  PlanComparison,
+  QueryDescription,
+  QueryJobInfo,
+  QueryOptions,
+  QueryPage,
+  QueryResult,
+  ScriptOptions,
+  ScriptResult,
+  TransactionState,
+} from '../../../types/database'
 
-export async function executeQuery(query: string): Promise<QueryResult> {
-  return invoke<QueryResult>('execute_query', { query })
+export async function executeQuery(query: string, options?: QueryOptions): Promise<QueryResult> {
+  return invoke<QueryResult>('execute_query', { query, options })
+}
+
+/** Reports the parameter types PostgreSQL infers for `$1` and `:name` placeholders. */
+export async function describeQuery(query: string, tabId?: string): Promise<QueryDescription> {
+  return invoke<QueryDescription>('describe_query', { query, tabId })
+}
+
+/** Runs an INSERT, UPDATE, DELETE or MERGE, reports what it would change, and rolls it back. */
+export async function dryRunQuery(query: string, options?: DryRunOptions): Promise<DryRunResult> {
+  return invoke<DryRunResult>('dry_run_query', { query, options })
+}
+
+/** Runs `EXPLAIN (FORMAT JSON)` with the chosen options and returns the parsed plan tree. */
+export async function explainQuery(
+  query: string,
+  options?: ExplainOptions
+): Promise<ExplainResult> {
+  return invoke<ExplainResult>('explain_query', { query, options })
+}
+
+/**
+ * Aligns two saved plans node by node and judges whether the second is faster. Either plan may
+ * be raw `EXPLAIN (FORMAT JSON)` output, as JSON or text, or a saved `ExplainResult`.
+ */
+export async function comparePlans(before: unknown, after: unknown): Promise<PlanComparison> {
+  return invoke<PlanComparison>('compare_plans', { before, after })
+}
+
+export async function executeScript(
+  script: string,
+  options?: ScriptOptions
+): Promise<ScriptResult> {
+  return invoke<ScriptResult>('execute_script', { script, options })
+}
+
+export async function fetchMoreRows(resultId: string, pageSize?: number): Promise<QueryPage> {
+  return invoke<QueryPage>('fetch_more_rows', { resultId, pageSize })
+}
+
+export async function closeResult(resultId: string): Promise<void> {
+  return invoke<void>('close_result', { resultId })
+}
+
+export async function cancelQuery(jobId: string): Promise<boolean> {
+  return invoke<boolean>('cancel_query', { jobId })
+}
+
+export async function listRunningQueries(): Promise<QueryJobInfo[]> {
+  return invoke<QueryJobInfo[]>('list_running_queries')
+}
+
+export async function beginTransaction(tabId: string): Promise<TransactionState> {
+  return invoke<TransactionState>('begin_transaction', { tabId })
+}
+
+export async function commit(tabId: string): Promise<TransactionState> {
+  return invoke<TransactionState>('commit', { tabId })
+}
+
+export async function rollback(tabId: string): Promise<TransactionState> {
+  return invoke<TransactionState>('rollback', { tabId })
+}
+
+export async function getTransactionState(tabId: string): Promise<TransactionState> {
+  return invoke<TransactionState>('get_transaction_state', { tabId })
+}
+
+/** Rolls back and discards the tab's session state: settings, temp tables, prepared statements. */
+export async function resetSession(tabId: string): Promise<TransactionState> {
+  return invoke<TransactionState>('reset_session', { tabId })
+}
+
+export async function closeSession(tabId: string): Promise<void> {
+  return invoke<void>('close_session', { tabId })
 }
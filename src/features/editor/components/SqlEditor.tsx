@@ -6,10 +6,23 @@ import { useSchemaStore } from '../../schema/stores/schemaStore'
 import { registerSqlCompletions } from '../utils/completions'
 import * as styles from './SqlEditor.css'
 
+export interface SqlErrorMarker {
+  /** The statement that failed, as it was sent to the server. */
+  query: string
+  /** 1-based character position reported by PostgreSQL. */
+  position: number
+  message: string
+}
+
 interface SqlEditorProps {
   value: string
   onChange: (value: string) => void
   onExecute: (query: string) => void
+  /** Dry-runs the statement at the cursor (Cmd+Shift+Enter). */
+  onDryRun?: (query: string) => void
+  /** Explains the statement at the cursor: Cmd+E plans it, Cmd+Shift+E also runs it. */
+  onExplain?: (query: string, analyze: boolean) => void
+  errorMarker?: SqlErrorMarker | null
 }
 
 export interface SqlEditorHandle {
@@ -43,18 +56,28 @@ function getStatementAtCursor(content: string, offset: number): string {
 }
 
 export const SqlEditor = forwardRef<SqlEditorHandle, SqlEditorProps>(function SqlEditor(
-  { value, onChange, onExecute },
+  { value, onChange, onExecute, onDryRun, onExplain, errorMarker },
   ref
 ) {
   const { schema } = useSchemaStore()
   const editorRef = useRef<editor.IStandaloneCodeEditor | null>(null)
   const monacoRef = useRef<typeof import('monaco-editor') | null>(null)
   const onExecuteRef = useRef(onExecute)
+  const onDryRunRef = useRef(onDryRun)
+  const onExplainRef = useRef(onExplain)
 
   useEffect(() => {
     onExecuteRef.current = onExecute
   }, [onExecute])
 
+  useEffect(() => {
+    onDryRunRef.current = onDryRun
+  }, [onDryRun])
+
+  useEffect(() => {
+    onExplainRef.current = onExplain
+  }, [onExplain])
+
   useImperativeHandle(ref, () => ({
     focusAtEnd: () => {
       const editor = editorRef.current
@@ -77,18 +100,36 @@ export const SqlEditor = forwardRef<SqlEditorHandle, SqlEditorProps>(function Sq
 
       registerSqlCompletions(monaco, schema)
 
-      editor.addCommand(monaco.KeyMod.CtrlCmd | monaco.KeyCode.Enter, () => {
+      const statementAtCursor = () => {
         const model = editor.getModel()
-        if (!model) return
+        if (!model) return null
 
         const position = editor.getPosition()
-        if (!position) return
+        if (!position) return null
 
         const offset = model.getOffsetAt(position)
         const fullContent = model.getValue()
-        const statement = getStatementAtCursor(fullContent, offset)
+        return getStatementAtCursor(fullContent, offset)
+      }
 
-        onExecuteRef.current(statement)
+      editor.addCommand(monaco.KeyMod.CtrlCmd | monaco.KeyCode.Enter, () => {
+        const statement = statementAtCursor()
+        if (statement !== null) onExecuteRef.current(statement)
+      })
+
+      editor.addCommand(monaco.KeyMod.CtrlCmd | monaco.KeyMod.Shift | monaco.KeyCode.Enter, () => {
+        const statement = statementAtCursor()
+        if (statement !== null) onDryRunRef.current?.(statement)
+      })
+
+      editor.addCommand(monaco.KeyMod.CtrlCmd | monaco.KeyCode.KeyE, () => {
+        const statement = statementAtCursor()
+        if (statement !== null) onExplainRef.current?.(statement, false)
+      })
+
+      editor.addCommand(monaco.KeyMod.CtrlCmd | monaco.KeyMod.Shift | monaco.KeyCode.KeyE, () => {
+        const statement = statementAtCursor()
+        if (statement !== null) onExplainRef.current?.(statement, true)
       })
 
       editor.focus()
@@ -96,6 +137,36 @@ export const SqlEditor = forwardRef<SqlEditorHandle, SqlEditorProps>(function Sq
     [schema]
   )
 
+  useEffect(() => {
+    const monaco = monacoRef.current
+    const model = editorRef.current?.getModel()
+    if (!monaco || !model) return
+
+    const start = errorMarker ? value.indexOf(errorMarker.query) : -1
+    if (!errorMarker || start < 0) {
+      monaco.editor.setModelMarkers(model, 'postgres', [])
+      return
+    }
+
+    // PostgreSQL counts characters, JavaScript offsets count UTF-16 code units.
+    const prefix = Array.from(errorMarker.query)
+      .slice(0, errorMarker.position - 1)
+      .join('')
+    const position = model.getPositionAt(start + prefix.length)
+    const word = model.getWordAtPosition(position)
+
+    monaco.editor.setModelMarkers(model, 'postgres', [
+      {
+        severity: monaco.MarkerSeverity.Error,
+        message: errorMarker.message,
+        startLineNumber: position.lineNumber,
+        startColumn: word?.startColumn ?? position.column,
+        endLineNumber: position.lineNumber,
+        endColumn: word?.endColumn ?? position.column + 1,
+      },
+    ])
+  }, [errorMarker, value])
+
   useEffect(() => {
     if (monacoRef.current && schema) {
       registerSqlCompletions(monacoRef.current, schema)
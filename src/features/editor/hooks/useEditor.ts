@@ -1,35 +1,170 @@
-import { useCallback } from 'react'
+import { useCallback, useRef } from 'react'
 
 import { useEditorStore } from '../stores/editorStore'
 import { useResultsStore } from '../../results/stores/resultsStore'
-import { executeQuery } from '../api/queryApi'
+import type {
+  ExplainOptions,
+  QueryOptions,
+  QueryParameter,
+  QueryResult,
+  TransactionState,
+} from '../../../types/database'
+import { toCommandError } from '../../../utils/errors'
+import * as queryApi from '../api/queryApi'
+import { closeResult, executeQuery } from '../api/queryApi'
+
+/** Something that looks like a `$1` or `:name` placeholder, worth asking the server about. */
+const PLACEHOLDER_PATTERN = /\$\d|(^|[^:]):[A-Za-z_]/
 
 export function useEditor() {
-  const { tabs, activeTabId, addTab, closeTab, setActiveTab, updateTabContent, updateTabName } =
-    useEditorStore()
+  const {
+    tabs,
+    activeTabId,
+    addTab,
+    closeTab: removeTab,
+    setActiveTab,
+    updateTabContent,
+    updateTabName,
+    transactionStates,
+    setTransactionState,
+  } = useEditorStore()
 
-  const { setResults, setExecuting, setError } = useResultsStore()
+  const {
+    results: currentResults,
+    setResults,
+    setPlan,
+    setExecuting,
+    setRunningJobId,
+    setError,
+  } = useResultsStore()
 
   const activeTab = tabs.find((t) => t.id === activeTabId) ?? tabs[0]
+  const transactionState = (activeTab && transactionStates[activeTab.id]) ?? 'idle'
+  const lastParameterValues = useRef<Record<string, string>>({})
 
-  const runQuery = useCallback(
-    async (query: string) => {
+  /** Re-reads a tab's transaction state after a statement failed in it. */
+  const refreshTransactionState = useCallback(
+    (tabId: string) => {
+      queryApi
+        .getTransactionState(tabId)
+        .then((state) => setTransactionState(tabId, state))
+        .catch(() => {})
+    },
+    [setTransactionState]
+  )
+
+  /**
+   * Asks for a value for each placeholder in the query, typed as PostgreSQL infers it.
+   * Resolves to undefined when there are none, or null when the user cancels or the
+   * query cannot be described.
+   */
+  const collectParameters = useCallback(
+    async (query: string): Promise<QueryParameter[] | undefined | null> => {
+      if (!PLACEHOLDER_PATTERN.test(query)) return undefined
+
+      let description
+      try {
+        description = await queryApi.describeQuery(query, activeTab?.id)
+      } catch (err) {
+        setError(toCommandError(err), query)
+        if (activeTab) refreshTransactionState(activeTab.id)
+        return null
+      }
+
+      const parameters: QueryParameter[] = []
+      for (const parameter of description.parameters) {
+        const label = parameter.name ? `:${parameter.name}` : `$${parameter.position}`
+        const value = window.prompt(
+          `Value for ${label} (${parameter.dataType}); enter NULL for null`,
+          lastParameterValues.current[label] ?? ''
+        )
+        if (value === null) return null
+
+        lastParameterValues.current[label] = value
+        parameters.push({
+          name: parameter.name ?? undefined,
+          type: parameter.dataType,
+          value: value === 'NULL' ? null : value,
+        })
+      }
+      return parameters.length > 0 ? parameters : undefined
+    },
+    [activeTab, setError, refreshTransactionState]
+  )
+
+  /**
+   * Runs a query. When the connection wants a destructive statement confirmed, asks the user
+   * and runs it again with the token the backend handed out.
+   */
+  const executeConfirmed = useCallback(
+    async (query: string, options: QueryOptions): Promise<QueryResult> => {
+      try {
+        return await executeQuery(query, options)
+      } catch (err) {
+        const commandError = toCommandError(err)
+        if (commandError.kind !== 'confirmationRequired' || !commandError.confirmationToken) {
+          throw err
+        }
+        if (!window.confirm(`${commandError.message}.\n\nRun it anyway?`)) throw err
+
+        return executeQuery(query, { ...options, confirmationToken: commandError.confirmationToken })
+      }
+    },
+    []
+  )
+
+  /**
+   * Runs `call` for a statement from the editor: closes the current result set, asks for
+   * placeholder values, and tracks the call as a job the user can cancel, showing any error.
+   */
+  const runJob = useCallback(
+    async (
+      query: string,
+      call: (jobId: string, parameters: QueryParameter[] | undefined) => Promise<void>
+    ) => {
       if (!query.trim()) return
 
+      if (currentResults?.resultId) {
+        void closeResult(currentResults.resultId)
+      }
+
+      const parameters = await collectParameters(query)
+      if (parameters === null) return
+
+      const jobId = crypto.randomUUID()
+      setRunningJobId(jobId)
       setExecuting(true)
       setError(null)
 
       try {
-        const results = await executeQuery(query)
-        setResults(results)
+        await call(jobId, parameters)
       } catch (err) {
-        const errorMessage = err instanceof Error ? err.message : String(err)
-        setError(errorMessage)
+        setError(toCommandError(err), query)
+        if (activeTab) refreshTransactionState(activeTab.id)
       } finally {
         setExecuting(false)
+        setRunningJobId(null)
       }
     },
-    [setResults, setExecuting, setError]
+    [
+      activeTab,
+      currentResults,
+      collectParameters,
+      setExecuting,
+      setRunningJobId,
+      setError,
+      refreshTransactionState,
+    ]
+  )
+
+  const runQuery = useCallback(
+    (query: string) =>
+      runJob(query, async (jobId, parameters) => {
+        const results = await executeConfirmed(query, { jobId, tabId: activeTab?.id, parameters })
+        setResults(results, { kind: 'query', query, parameters, tabId: activeTab?.id })
+        if (activeTab) setTransactionState(activeTab.id, results.transactionState)
+      }),
+    [activeTab, runJob, executeConfirmed, setResults, setTransactionState]
   )
 
   const runQueryWithContent = useCallback(
@@ -37,21 +172,113 @@ export function useEditor() {
       if (!activeTab) return
 
       updateTabContent(activeTab.id, query)
+      await runQuery(query)
+    },
+    [activeTab, updateTabContent, runQuery]
+  )
 
-      setExecuting(true)
-      setError(null)
+  /** Runs a data-changing statement and rolls it back, showing a sample of the rows it changes. */
+  const dryRun = useCallback(
+    (query: string) =>
+      runJob(query, async (jobId, parameters) => {
+        const result = await queryApi.dryRunQuery(query, {
+          jobId,
+          tabId: activeTab?.id,
+          parameters,
+        })
+        setResults({
+          columns: result.columns,
+          rows: result.sample,
+          executionTimeMs: result.executionTimeMs,
+          statementKind: result.statementKind,
+          rowsAffected: result.rowsAffected,
+          resultId: null,
+          jobId: null,
+          hasMore: false,
+          rowLimitReached: result.sampleTruncated,
+          cancelled: false,
+          transactionState: result.transactionState,
+          notices: result.notices,
+          dryRun: true,
+        })
+        if (activeTab) setTransactionState(activeTab.id, result.transactionState)
+      }),
+    [activeTab, runJob, setResults, setTransactionState]
+  )
+
+  /** Shows the statement's plan; with `analyze` it runs, and is rolled back afterwards. */
+  const explain = useCallback(
+    (query: string, options: ExplainOptions = {}) =>
+      runJob(query, async (jobId, parameters) => {
+        const plan = await queryApi.explainQuery(query, {
+          ...options,
+          jobId,
+          tabId: activeTab?.id,
+          parameters,
+        })
+        setPlan(plan)
+        if (activeTab) setTransactionState(activeTab.id, plan.transactionState)
+      }),
+    [activeTab, runJob, setPlan, setTransactionState]
+  )
+
+  const runTransactionCommand = useCallback(
+    async (command: (tabId: string) => Promise<TransactionState>) => {
+      if (!activeTab) return
 
       try {
-        const results = await executeQuery(query)
-        setResults(results)
+        setTransactionState(activeTab.id, await command(activeTab.id))
       } catch (err) {
-        const errorMessage = err instanceof Error ? err.message : String(err)
-        setError(errorMessage)
-      } finally {
-        setExecuting(false)
+        setError(toCommandError(err))
+        refreshTransactionState(activeTab.id)
+      }
+    },
+    [activeTab, setTransactionState, setError, refreshTransactionState]
+  )
+
+  const beginTransaction = useCallback(
+    () => runTransactionCommand(queryApi.beginTransaction),
+    [runTransactionCommand]
+  )
+
+  const commitTransaction = useCallback(
+    () => runTransactionCommand(queryApi.commit),
+    [runTransactionCommand]
+  )
+
+  const rollbackTransaction = useCallback(
+    () => runTransactionCommand(queryApi.rollback),
+    [runTransactionCommand]
+  )
+
+  const resetSession = useCallback(() => {
+    if (!activeTab) return
+
+    if (transactionStates[activeTab.id]) {
+      const confirmed = window.confirm(
+        'Resetting the session rolls back its uncommitted transaction. Continue?'
+      )
+      if (!confirmed) return
+    }
+    return runTransactionCommand(queryApi.resetSession)
+  }, [activeTab, transactionStates, runTransactionCommand])
+
+  /** Closing a tab closes its session, rolling back an open transaction once the user agrees. */
+  const closeTab = useCallback(
+    (id: string) => {
+      if (tabs.length === 1) return
+
+      if (transactionStates[id]) {
+        const confirmed = window.confirm(
+          'This tab has an uncommitted transaction. Close it and roll the transaction back?'
+        )
+        if (!confirmed) return
+        setTransactionState(id, 'idle')
       }
+      void queryApi.closeSession(id).catch(() => {})
+      removeTab(id)
     },
-    [activeTab, updateTabContent, setResults, setExecuting, setError]
+    [tabs.length, transactionStates, setTransactionState, removeTab]
   )
 
   const handleContentChange = useCallback(
@@ -90,8 +317,15 @@ export function useEditor() {
     setActiveTab,
     updateTabContent: handleContentChange,
     updateTabName,
+    transactionState,
+    beginTransaction,
+    commitTransaction,
+    rollbackTransaction,
+    resetSession,
     runQuery,
     runQueryWithContent,
+    dryRun,
+    explain,
     appendWhereClause,
   }
 }
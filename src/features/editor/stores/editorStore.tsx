@@ -8,17 +8,27 @@ import {
   type ReactNode,
 } from 'react'
 
-import type { Tab, PersistedEditorState } from '../../../types/database'
+import type {
+  Tab,
+  PersistedEditorState,
+  PersistedTab,
+  TransactionState,
+} from '../../../types/database'
 import { loadEditorTabs, saveEditorTabs } from '../api/editorTabsApi'
 
 interface EditorContextValue {
   tabs: Tab[]
   activeTabId: string | null
   addTab: () => void
+  /** Adds a tab with the given name and content and makes it active. */
+  openTab: (tab: PersistedTab) => void
   closeTab: (id: string) => void
   setActiveTab: (id: string) => void
   updateTabContent: (id: string, content: string) => void
   updateTabName: (id: string, name: string) => void
+  /** Transaction state of each tab's session; tabs not listed are idle. */
+  transactionStates: Record<string, TransactionState>
+  setTransactionState: (id: string, state: TransactionState) => void
   loadTabs: (connectionId: string) => Promise<void>
   reset: () => void
 }
@@ -69,6 +79,7 @@ function fromPersistedState(state: PersistedEditorState): {
 export function EditorProvider({ children }: { children: ReactNode }) {
   const [tabs, setTabs] = useState<Tab[]>([createDefaultTab()])
   const [activeTabId, setActiveTabId] = useState<string | null>(null)
+  const [transactionStates, setTransactionStates] = useState<Record<string, TransactionState>>({})
   const connectionIdRef = useRef<string | null>(null)
   const saveTimeoutRef = useRef<ReturnType<typeof setTimeout> | null>(null)
   const skipNextSaveRef = useRef(false)
@@ -140,6 +151,7 @@ export function EditorProvider({ children }: { children: ReactNode }) {
     const defaultTab = createDefaultTab()
     setTabs([defaultTab])
     setActiveTabId(null)
+    setTransactionStates({})
   }, [tabs, activeTabId])
 
   const addTab = useCallback(() => {
@@ -148,6 +160,11 @@ export function EditorProvider({ children }: { children: ReactNode }) {
     setActiveTabId(newTab.id)
   }, [tabs.length])
 
+  const openTab = useCallback((tab: PersistedTab) => {
+    setTabs((prev) => [...prev, { ...tab, isDirty: false }])
+    setActiveTabId(tab.id)
+  }, [])
+
   const closeTab = useCallback(
     (id: string) => {
       if (tabs.length === 1) {
@@ -167,6 +184,18 @@ export function EditorProvider({ children }: { children: ReactNode }) {
     [tabs, activeTabId]
   )
 
+  const setTransactionState = useCallback((id: string, state: TransactionState) => {
+    setTransactionStates((prev) => {
+      const next = { ...prev }
+      if (state === 'idle') {
+        delete next[id]
+      } else {
+        next[id] = state
+      }
+      return next
+    })
+  }, [])
+
   const setActiveTab = useCallback((id: string) => {
     setActiveTabId(id)
   }, [])
@@ -204,10 +233,13 @@ export function EditorProvider({ children }: { children: ReactNode }) {
         tabs,
         activeTabId,
         addTab,
+        openTab,
         closeTab,
         setActiveTab,
         updateTabContent,
         updateTabName,
+        transactionStates,
+        setTransactionState,
         loadTabs,
         reset,
       }}
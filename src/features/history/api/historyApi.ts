@@ -0,0 +1,13 @@
+import { invoke } from '@tauri-apps/api/core'
+
+import type { HistoryEntry, HistoryFilter, PersistedTab } from '../../../types/database'
+
+/** History entries matching the filter, newest first. */
+export async function searchHistory(filter?: HistoryFilter): Promise<HistoryEntry[]> {
+  return invoke<HistoryEntry[]>('search_history', { filter })
+}
+
+/** A new tab holding the entry's SQL, ready to add to the editor. */
+export async function openHistoryEntry(entryId: string): Promise<PersistedTab> {
+  return invoke<PersistedTab>('open_history_entry', { entryId })
+}
@@ -0,0 +1,138 @@
+import { style, styleVariants } from '@vanilla-extract/css'
+
+import { vars } from '../../../configs/theme.css'
+
+export const panel = style({
+  display: 'flex',
+  flexDirection: 'column',
+  width: '100%',
+  borderLeft: `1px solid ${vars.color.border}`,
+  backgroundColor: vars.color.backgroundSecondary,
+  overflow: 'hidden',
+})
+
+export const header = style({
+  display: 'flex',
+  alignItems: 'center',
+  justifyContent: 'space-between',
+  padding: `${vars.space.sm} ${vars.space.md}`,
+  borderBottom: `1px solid ${vars.color.border}`,
+  flexShrink: 0,
+})
+
+export const headerTitle = style({
+  fontSize: vars.fontSize.md,
+  fontWeight: 600,
+  color: vars.color.foreground,
+})
+
+export const iconButton = style({
+  display: 'flex',
+  alignItems: 'center',
+  justifyContent: 'center',
+  width: '28px',
+  height: '28px',
+  padding: 0,
+  backgroundColor: 'transparent',
+  border: 'none',
+  borderRadius: vars.radius.sm,
+  color: vars.color.foregroundSecondary,
+  cursor: 'pointer',
+
+  ':hover': {
+    backgroundColor: vars.color.backgroundTertiary,
+    color: vars.color.foreground,
+  },
+})
+
+export const filters = style({
+  display: 'flex',
+  flexDirection: 'column',
+  gap: vars.space.xs,
+  padding: vars.space.sm,
+  borderBottom: `1px solid ${vars.color.border}`,
+  flexShrink: 0,
+})
+
+export const filterRow = style({
+  display: 'flex',
+  alignItems: 'center',
+  gap: vars.space.xs,
+  fontSize: vars.fontSize.xs,
+  color: vars.color.foregroundSecondary,
+})
+
+export const input = style({
+  flex: 1,
+  minWidth: 0,
+  padding: `${vars.space.xs} ${vars.space.sm}`,
+  backgroundColor: vars.color.background,
+  border: `1px solid ${vars.color.border}`,
+  borderRadius: vars.radius.sm,
+  color: vars.color.foreground,
+  fontSize: vars.fontSize.sm,
+  outline: 'none',
+
+  ':focus': {
+    borderColor: vars.color.borderFocus,
+  },
+})
+
+export const list = style({
+  display: 'flex',
+  flexDirection: 'column',
+  flex: 1,
+  overflowY: 'auto',
+})
+
+export const entry = style({
+  display: 'flex',
+  flexDirection: 'column',
+  gap: vars.space.xs,
+  padding: `${vars.space.sm} ${vars.space.md}`,
+  borderBottom: `1px solid ${vars.color.border}`,
+  cursor: 'pointer',
+
+  ':hover': {
+    backgroundColor: vars.color.backgroundTertiary,
+  },
+})
+
+export const entryMeta = style({
+  display: 'flex',
+  gap: vars.space.sm,
+  fontSize: vars.fontSize.xs,
+  color: vars.color.foregroundSecondary,
+})
+
+export const status = styleVariants({
+  succeeded: { color: vars.color.success },
+  failed: { color: vars.color.error },
+  cancelled: { color: vars.color.warning },
+})
+
+export const sql = style({
+  margin: 0,
+  maxHeight: '4.5em',
+  overflow: 'hidden',
+  fontFamily: vars.fontFamily.mono,
+  fontSize: vars.fontSize.xs,
+  color: vars.color.foreground,
+  whiteSpace: 'pre-wrap',
+  wordBreak: 'break-word',
+})
+
+export const entryError = style({
+  fontSize: vars.fontSize.xs,
+  color: vars.color.error,
+  overflow: 'hidden',
+  textOverflow: 'ellipsis',
+  whiteSpace: 'nowrap',
+})
+
+export const empty = style({
+  padding: vars.space.md,
+  fontSize: vars.fontSize.sm,
+  color: vars.color.foregroundSecondary,
+  textAlign: 'center',
+})
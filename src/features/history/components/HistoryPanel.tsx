@@ -0,0 +1,138 @@
+import { X } from 'lucide-react'
+import { useEffect, useState } from 'react'
+
+import type { HistoryEntry } from '../../../types/database'
+import { errorMessage } from '../../../utils/errors'
+import { useEditorStore } from '../../editor/stores/editorStore'
+import { useResultsStore } from '../../results/stores/resultsStore'
+import { useSchemaStore } from '../../schema/stores/schemaStore'
+import { openHistoryEntry, searchHistory } from '../api/historyApi'
+import * as styles from './HistoryPanel.css'
+
+const SEARCH_DEBOUNCE_MS = 300
+
+interface HistoryPanelProps {
+  onClose: () => void
+}
+
+/** Start of a local calendar day as an RFC 3339 timestamp, `days` after `date`. */
+function dayStart(date: string, days = 0): string {
+  const [year, month, day] = date.split('-').map(Number)
+  return new Date(year, month - 1, day + days).toISOString()
+}
+
+function describeRows(entry: HistoryEntry): string | null {
+  if (entry.rowsAffected !== null) return `${entry.rowsAffected} affected`
+  if (entry.rowsReturned !== null) return `${entry.rowsReturned} rows`
+  return null
+}
+
+/** Past queries, searchable by text and date; clicking one opens it in a new tab. */
+export function HistoryPanel({ onClose }: HistoryPanelProps) {
+  const { connection } = useSchemaStore()
+  const { openTab } = useEditorStore()
+  const { isExecuting } = useResultsStore()
+  const [text, setText] = useState('')
+  const [from, setFrom] = useState('')
+  const [to, setTo] = useState('')
+  const [allConnections, setAllConnections] = useState(false)
+  const [entries, setEntries] = useState<HistoryEntry[]>([])
+  const [error, setError] = useState<string | null>(null)
+
+  const connectionId = allConnections ? undefined : connection?.id
+
+  useEffect(() => {
+    // Searching again once a query finishes shows it straight away.
+    if (isExecuting) return
+
+    const timeout = setTimeout(() => {
+      searchHistory({
+        text: text || undefined,
+        connectionId,
+        from: from ? dayStart(from) : undefined,
+        to: to ? dayStart(to, 1) : undefined,
+      })
+        .then((found) => {
+          setEntries(found)
+          setError(null)
+        })
+        .catch((err) => setError(errorMessage(err)))
+    }, SEARCH_DEBOUNCE_MS)
+    return () => clearTimeout(timeout)
+  }, [text, from, to, connectionId, isExecuting])
+
+  const handleOpen = async (entry: HistoryEntry) => {
+    try {
+      openTab(await openHistoryEntry(entry.id))
+    } catch (err) {
+      setError(errorMessage(err))
+    }
+  }
+
+  return (
+    <div className={styles.panel}>
+      <div className={styles.header}>
+        <span className={styles.headerTitle}>History</span>
+        <button className={styles.iconButton} onClick={onClose} type="button" title="Close">
+          <X size={14} />
+        </button>
+      </div>
+      <div className={styles.filters}>
+        <input
+          className={styles.input}
+          value={text}
+          onChange={(e) => setText(e.target.value)}
+          placeholder="Search SQL"
+        />
+        <div className={styles.filterRow}>
+          <input
+            className={styles.input}
+            type="date"
+            value={from}
+            onChange={(e) => setFrom(e.target.value)}
+            title="From"
+          />
+          <span>to</span>
+          <input
+            className={styles.input}
+            type="date"
+            value={to}
+            onChange={(e) => setTo(e.target.value)}
+            title="To"
+          />
+        </div>
+        <label className={styles.filterRow}>
+          <input
+            type="checkbox"
+            checked={allConnections}
+            onChange={(e) => setAllConnections(e.target.checked)}
+          />
+          All connections
+        </label>
+      </div>
+      <div className={styles.list}>
+        {error && <div className={styles.entryError}>{error}</div>}
+        {entries.length === 0 && !error && (
+          <div className={styles.empty}>No queries match.</div>
+        )}
+        {entries.map((entry) => (
+          <div
+            key={entry.id}
+            className={styles.entry}
+            onClick={() => void handleOpen(entry)}
+            title="Open in a new tab"
+          >
+            <div className={styles.entryMeta}>
+              <span>{new Date(entry.startedAt).toLocaleString()}</span>
+              <span>{entry.durationMs}ms</span>
+              {describeRows(entry) && <span>{describeRows(entry)}</span>}
+              <span className={styles.status[entry.status]}>{entry.status}</span>
+            </div>
+            <pre className={styles.sql}>{entry.sql}</pre>
+            {entry.error && <div className={styles.entryError}>{entry.error}</div>}
+          </div>
+        ))}
+      </div>
+    </div>
+  )
+}
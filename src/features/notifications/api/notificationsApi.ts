@@ -0,0 +1,39 @@
+import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
+
+import type { Notification } from '../../../types/database'
+
+const NOTIFICATION_EVENT = 'db-notification'
+
+/** Starts listening on a channel; resolves to every channel now listened to. */
+export async function listenChannel(channel: string): Promise<string[]> {
+  return invoke<string[]>('listen_channel', { channel })
+}
+
+/** Stops listening on a channel; resolves to the channels still listened to. */
+export async function unlistenChannel(channel: string): Promise<string[]> {
+  return invoke<string[]>('unlisten_channel', { channel })
+}
+
+export async function getListenedChannels(): Promise<string[]> {
+  return invoke<string[]>('get_listened_channels')
+}
+
+/** Notifications received on the current connection, newest first. */
+export async function listNotifications(
+  channel?: string,
+  limit?: number
+): Promise<Notification[]> {
+  return invoke<Notification[]>('list_notifications', { channel, limit })
+}
+
+export async function clearNotifications(): Promise<void> {
+  return invoke('clear_notifications')
+}
+
+/** Calls `handler` with each notification as it arrives. */
+export async function onNotification(
+  handler: (notification: Notification) => void
+): Promise<UnlistenFn> {
+  return listen<Notification>(NOTIFICATION_EVENT, (event) => handler(event.payload))
+}
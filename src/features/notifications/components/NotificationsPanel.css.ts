@@ -0,0 +1,173 @@
+import { style } from '@vanilla-extract/css'
+
+import { vars } from '../../../configs/theme.css'
+
+export const panel = style({
+  display: 'flex',
+  flexDirection: 'column',
+  width: '100%',
+  borderLeft: `1px solid ${vars.color.border}`,
+  backgroundColor: vars.color.backgroundSecondary,
+  overflow: 'hidden',
+})
+
+export const header = style({
+  display: 'flex',
+  alignItems: 'center',
+  gap: vars.space.xs,
+  padding: `${vars.space.sm} ${vars.space.md}`,
+  borderBottom: `1px solid ${vars.color.border}`,
+  flexShrink: 0,
+})
+
+export const headerTitle = style({
+  flex: 1,
+  fontSize: vars.fontSize.md,
+  fontWeight: 600,
+  color: vars.color.foreground,
+})
+
+export const iconButton = style({
+  display: 'flex',
+  alignItems: 'center',
+  justifyContent: 'center',
+  width: '28px',
+  height: '28px',
+  padding: 0,
+  backgroundColor: 'transparent',
+  border: 'none',
+  borderRadius: vars.radius.sm,
+  color: vars.color.foregroundSecondary,
+  cursor: 'pointer',
+
+  ':hover': {
+    backgroundColor: vars.color.backgroundTertiary,
+    color: vars.color.foreground,
+  },
+})
+
+export const form = style({
+  display: 'flex',
+  gap: vars.space.xs,
+  padding: vars.space.sm,
+  borderBottom: `1px solid ${vars.color.border}`,
+  flexShrink: 0,
+})
+
+export const input = style({
+  flex: 1,
+  minWidth: 0,
+  padding: `${vars.space.xs} ${vars.space.sm}`,
+  backgroundColor: vars.color.background,
+  border: `1px solid ${vars.color.border}`,
+  borderRadius: vars.radius.sm,
+  color: vars.color.foreground,
+  fontSize: vars.fontSize.sm,
+  outline: 'none',
+
+  ':focus': {
+    borderColor: vars.color.borderFocus,
+  },
+})
+
+export const button = style({
+  padding: `${vars.space.xs} ${vars.space.sm}`,
+  backgroundColor: vars.color.primary,
+  border: 'none',
+  borderRadius: vars.radius.sm,
+  color: '#ffffff',
+  fontSize: vars.fontSize.sm,
+  cursor: 'pointer',
+
+  ':hover': {
+    backgroundColor: vars.color.primaryHover,
+  },
+
+  ':disabled': {
+    opacity: 0.5,
+    cursor: 'not-allowed',
+  },
+})
+
+export const channels = style({
+  display: 'flex',
+  flexWrap: 'wrap',
+  gap: vars.space.xs,
+  padding: `0 ${vars.space.sm} ${vars.space.sm}`,
+  borderBottom: `1px solid ${vars.color.border}`,
+  flexShrink: 0,
+})
+
+export const channel = style({
+  display: 'flex',
+  alignItems: 'center',
+  gap: vars.space.xs,
+  padding: `2px ${vars.space.xs} 2px ${vars.space.sm}`,
+  backgroundColor: vars.color.backgroundTertiary,
+  borderRadius: vars.radius.sm,
+  fontFamily: vars.fontFamily.mono,
+  fontSize: vars.fontSize.xs,
+  color: vars.color.foreground,
+})
+
+export const channelRemove = style({
+  display: 'flex',
+  padding: 0,
+  backgroundColor: 'transparent',
+  border: 'none',
+  color: vars.color.foregroundSecondary,
+  cursor: 'pointer',
+
+  ':hover': {
+    color: vars.color.foreground,
+  },
+})
+
+export const list = style({
+  display: 'flex',
+  flexDirection: 'column',
+  flex: 1,
+  overflowY: 'auto',
+})
+
+export const entry = style({
+  display: 'flex',
+  flexDirection: 'column',
+  gap: vars.space.xs,
+  padding: `${vars.space.sm} ${vars.space.md}`,
+  borderBottom: `1px solid ${vars.color.border}`,
+})
+
+export const entryMeta = style({
+  display: 'flex',
+  gap: vars.space.sm,
+  fontSize: vars.fontSize.xs,
+  color: vars.color.foregroundSecondary,
+})
+
+export const entryChannel = style({
+  color: vars.color.primary,
+  fontWeight: 500,
+})
+
+export const payload = style({
+  margin: 0,
+  fontFamily: vars.fontFamily.mono,
+  fontSize: vars.fontSize.xs,
+  color: vars.color.foreground,
+  whiteSpace: 'pre-wrap',
+  wordBreak: 'break-word',
+})
+
+export const error = style({
+  padding: `${vars.space.xs} ${vars.space.md}`,
+  fontSize: vars.fontSize.xs,
+  color: vars.color.error,
+})
+
+export const empty = style({
+  padding: vars.space.md,
+  fontSize: vars.fontSize.sm,
+  color: vars.color.foregroundSecondary,
+  textAlign: 'center',
+})
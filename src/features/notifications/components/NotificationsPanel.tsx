@@ -0,0 +1,163 @@
+import { Trash2, X } from 'lucide-react'
+import { useEffect, useState, type FormEvent } from 'react'
+
+import type { Notification } from '../../../types/database'
+import { errorMessage } from '../../../utils/errors'
+import { useSchemaStore } from '../../schema/stores/schemaStore'
+import {
+  clearNotifications,
+  getListenedChannels,
+  listenChannel,
+  listNotifications,
+  onNotification,
+  unlistenChannel,
+} from '../api/notificationsApi'
+import * as styles from './NotificationsPanel.css'
+
+/** As many as the backend keeps. */
+const MAX_SHOWN = 1000
+
+function notificationKey(notification: Notification): string {
+  const { channel, pid, receivedAt, payload } = notification
+  return `${channel}\n${pid}\n${receivedAt}\n${payload}`
+}
+
+interface NotificationsPanelProps {
+  onClose: () => void
+}
+
+/** Channels listened to on the current connection and what arrived on them, newest first. */
+export function NotificationsPanel({ onClose }: NotificationsPanelProps) {
+  const { connection } = useSchemaStore()
+  const [channel, setChannel] = useState('')
+  const [channels, setChannels] = useState<string[]>([])
+  const [notifications, setNotifications] = useState<Notification[]>([])
+  const [error, setError] = useState<string | null>(null)
+
+  const connectionId = connection?.id
+
+  useEffect(() => {
+    let cancelled = false
+    // Subscribing before reading the log means nothing falls in between; one
+    // that arrives meanwhile is both live and logged, and kept once.
+    const subscription = onNotification((notification) => {
+      if (cancelled) return
+      setNotifications((prev) => [notification, ...prev].slice(0, MAX_SHOWN))
+    })
+
+    Promise.all([getListenedChannels(), listNotifications()])
+      .then(([listened, logged]) => {
+        if (cancelled) return
+        setChannels(listened)
+        setNotifications((live) => {
+          const seen = new Set(live.map(notificationKey))
+          const earlier = logged.filter((n) => !seen.has(notificationKey(n)))
+          return [...live, ...earlier].slice(0, MAX_SHOWN)
+        })
+      })
+      .catch((err) => setError(errorMessage(err)))
+
+    return () => {
+      cancelled = true
+      setChannels([])
+      setNotifications([])
+      void subscription.then((unsubscribe) => unsubscribe())
+    }
+  }, [connectionId])
+
+  const handleListen = async (e: FormEvent) => {
+    e.preventDefault()
+    const name = channel.trim()
+    if (!name) return
+    try {
+      setChannels(await listenChannel(name))
+      setChannel('')
+      setError(null)
+    } catch (err) {
+      setError(errorMessage(err))
+    }
+  }
+
+  const handleUnlisten = async (name: string) => {
+    try {
+      setChannels(await unlistenChannel(name))
+      setError(null)
+    } catch (err) {
+      setError(errorMessage(err))
+    }
+  }
+
+  const handleClear = async () => {
+    try {
+      await clearNotifications()
+      setNotifications([])
+    } catch (err) {
+      setError(errorMessage(err))
+    }
+  }
+
+  return (
+    <div className={styles.panel}>
+      <div className={styles.header}>
+        <span className={styles.headerTitle}>Notifications</span>
+        <button
+          className={styles.iconButton}
+          onClick={() => void handleClear()}
+          type="button"
+          title="Clear log"
+        >
+          <Trash2 size={14} />
+        </button>
+        <button className={styles.iconButton} onClick={onClose} type="button" title="Close">
+          <X size={14} />
+        </button>
+      </div>
+      <form className={styles.form} onSubmit={(e) => void handleListen(e)}>
+        <input
+          className={styles.input}
+          value={channel}
+          onChange={(e) => setChannel(e.target.value)}
+          placeholder="Channel"
+        />
+        <button className={styles.button} type="submit" disabled={!channel.trim()}>
+          Listen
+        </button>
+      </form>
+      {channels.length > 0 && (
+        <div className={styles.channels}>
+          {channels.map((name) => (
+            <span key={name} className={styles.channel}>
+              {name}
+              <button
+                className={styles.channelRemove}
+                onClick={() => void handleUnlisten(name)}
+                type="button"
+                title="Stop listening"
+              >
+                <X size={12} />
+              </button>
+            </span>
+          ))}
+        </div>
+      )}
+      {error && <div className={styles.error}>{error}</div>}
+      <div className={styles.list}>
+        {notifications.length === 0 && (
+          <div className={styles.empty}>
+            {channels.length === 0 ? 'Listen on a channel to see its notifications.' : 'Waiting…'}
+          </div>
+        )}
+        {notifications.map((notification, index) => (
+          <div key={index} className={styles.entry}>
+            <div className={styles.entryMeta}>
+              <span className={styles.entryChannel}>{notification.channel}</span>
+              <span>{new Date(notification.receivedAt).toLocaleTimeString()}</span>
+              <span>pid {notification.pid}</span>
+            </div>
+            {notification.payload && <pre className={styles.payload}>{notification.payload}</pre>}
+          </div>
+        ))}
+      </div>
+    </div>
+  )
+}
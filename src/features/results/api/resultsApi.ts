@@ -0,0 +1,48 @@
+import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
+
+import type {
+  ApplyChangesResult,
+  EditOptions,
+  ExportOptions,
+  ExportProgress,
+  ExportSource,
+  ExportSummary,
+  ResultColumn,
+  RowChange,
+  RowOperation,
+} from '../../../types/database'
+
+export async function applyRowChanges(
+  columns: ResultColumn[],
+  changes: RowChange[],
+  options?: EditOptions
+): Promise<ApplyChangesResult> {
+  return invoke<ApplyChangesResult>('apply_row_changes', { columns, changes, options })
+}
+
+export async function applyRowOperations(
+  columns: ResultColumn[],
+  operations: RowOperation[],
+  options?: EditOptions
+): Promise<ApplyChangesResult> {
+  return invoke<ApplyChangesResult>('apply_row_operations', { columns, operations, options })
+}
+
+const EXPORT_PROGRESS_EVENT = 'export-progress'
+
+/** Runs the source's query and streams its rows to `path`, replacing the file. */
+export async function exportQuery(
+  source: ExportSource,
+  path: string,
+  options: ExportOptions
+): Promise<ExportSummary> {
+  return invoke<ExportSummary>('export_query', { source, path, options })
+}
+
+/** Calls `handler` as exports write rows; match `jobId` to follow one export. */
+export async function onExportProgress(
+  handler: (progress: ExportProgress) => void
+): Promise<UnlistenFn> {
+  return listen<ExportProgress>(EXPORT_PROGRESS_EVENT, (event) => handler(event.payload))
+}
@@ -0,0 +1,69 @@
+import { style } from '@vanilla-extract/css'
+
+import { vars } from '../../../configs/theme.css'
+
+export const bar = style({
+  display: 'flex',
+  alignItems: 'center',
+  gap: vars.space.sm,
+  padding: `${vars.space.xs} ${vars.space.md}`,
+  backgroundColor: vars.color.backgroundSecondary,
+  borderTop: `1px solid ${vars.color.border}`,
+  fontSize: vars.fontSize.sm,
+  color: vars.color.foregroundSecondary,
+  flexShrink: 0,
+})
+
+export const select = style({
+  padding: `${vars.space.xs} ${vars.space.sm}`,
+  backgroundColor: vars.color.background,
+  border: `1px solid ${vars.color.border}`,
+  borderRadius: vars.radius.sm,
+  color: vars.color.foreground,
+  fontSize: vars.fontSize.sm,
+  outline: 'none',
+
+  ':focus': {
+    borderColor: vars.color.borderFocus,
+  },
+})
+
+export const input = style([select, { width: '140px' }])
+
+export const checkbox = style({
+  display: 'inline-flex',
+  alignItems: 'center',
+  gap: vars.space.xs,
+  whiteSpace: 'nowrap',
+  cursor: 'pointer',
+})
+
+export const button = style({
+  display: 'inline-flex',
+  alignItems: 'center',
+  gap: vars.space.xs,
+  padding: `${vars.space.xs} ${vars.space.sm}`,
+  backgroundColor: 'transparent',
+  border: `1px solid ${vars.color.border}`,
+  borderRadius: vars.radius.sm,
+  color: vars.color.foreground,
+  fontSize: vars.fontSize.sm,
+  cursor: 'pointer',
+  whiteSpace: 'nowrap',
+
+  ':hover': {
+    backgroundColor: vars.color.backgroundTertiary,
+    borderColor: vars.color.foregroundSecondary,
+  },
+})
+
+export const progress = style({
+  color: vars.color.foreground,
+  fontVariantNumeric: 'tabular-nums',
+})
+
+export const message = style({
+  overflow: 'hidden',
+  textOverflow: 'ellipsis',
+  whiteSpace: 'nowrap',
+})
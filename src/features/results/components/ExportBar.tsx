@@ -0,0 +1,206 @@
+import { save } from '@tauri-apps/plugin-dialog'
+import { Download } from 'lucide-react'
+import { useCallback, useEffect, useState } from 'react'
+
+import type {
+  ExportFormat,
+  ExportSource,
+  ExportSummary,
+  ResultColumn,
+} from '../../../types/database'
+import { toCommandError } from '../../../utils/errors'
+import { cancelQuery } from '../../editor/api/queryApi'
+import { useEditorStore } from '../../editor/stores/editorStore'
+import { exportQuery, onExportProgress } from '../api/resultsApi'
+import * as styles from './ExportBar.css'
+
+type FormatKind = ExportFormat['format']
+
+const FORMATS: { kind: FormatKind; label: string; extension: string }[] = [
+  { kind: 'csv', label: 'CSV', extension: 'csv' },
+  { kind: 'json', label: 'JSON', extension: 'json' },
+  { kind: 'ndjson', label: 'NDJSON', extension: 'ndjson' },
+  { kind: 'insert', label: 'SQL INSERT', extension: 'sql' },
+  { kind: 'markdown', label: 'Markdown', extension: 'md' },
+  { kind: 'xlsx', label: 'Excel', extension: 'xlsx' },
+  { kind: 'parquet', label: 'Parquet', extension: 'parquet' },
+]
+
+const DELIMITERS: { value: string; label: string }[] = [
+  { value: ',', label: 'Comma' },
+  { value: ';', label: 'Semicolon' },
+  { value: '\t', label: 'Tab' },
+  { value: '|', label: 'Pipe' },
+]
+
+interface ExportBarProps {
+  source: ExportSource
+  columns: ResultColumn[]
+}
+
+interface RunningExport {
+  jobId: string
+  rowsWritten: number
+}
+
+/** The table every column was read from, as the default target for INSERT statements. */
+function sourceTable(columns: ResultColumn[]): string {
+  const table = columns[0]?.tableName
+  return table && columns.every((c) => c.tableName === table) ? table : ''
+}
+
+/** Writes every row of the query behind the results to a file, not just the loaded ones. */
+export function ExportBar({ source, columns }: ExportBarProps) {
+  const { transactionStates } = useEditorStore()
+  const [kind, setKind] = useState<FormatKind>('csv')
+  const [delimiter, setDelimiter] = useState(',')
+  const [quoteAll, setQuoteAll] = useState(false)
+  const [nullMarker, setNullMarker] = useState('')
+  const [table, setTable] = useState(() => sourceTable(columns))
+  const [running, setRunning] = useState<RunningExport | null>(null)
+  const [message, setMessage] = useState<string | null>(null)
+
+  useEffect(() => {
+    setTable(sourceTable(columns))
+    setMessage(null)
+  }, [columns])
+
+  const buildFormat = useCallback((): ExportFormat => {
+    switch (kind) {
+      case 'csv':
+        return {
+          format: 'csv',
+          delimiter,
+          quoteStyle: quoteAll ? 'always' : 'necessary',
+          nullMarker,
+        }
+      case 'insert':
+        return { format: 'insert', table: table.trim() }
+      default:
+        return { format: kind }
+    }
+  }, [kind, delimiter, quoteAll, nullMarker, table])
+
+  const handleExport = useCallback(async () => {
+    if (kind === 'insert' && !table.trim()) {
+      setMessage('Enter the table to insert into.')
+      return
+    }
+
+    const { label, extension } = FORMATS.find((f) => f.kind === kind)!
+    const path = await save({
+      defaultPath: `export.${extension}`,
+      filters: [{ name: label, extensions: [extension] }],
+    })
+    if (!path) return
+
+    // Running in the tab's session closes the grid's result set, so that is only done when an
+    // open transaction holds data other sessions cannot see.
+    const exportSource =
+      source.kind === 'query' && source.tabId && !transactionStates[source.tabId]
+        ? { ...source, tabId: undefined }
+        : source
+
+    const jobId = crypto.randomUUID()
+    setRunning({ jobId, rowsWritten: 0 })
+    setMessage(null)
+    const unlisten = await onExportProgress((progress) => {
+      if (progress.jobId === jobId) setRunning({ jobId, rowsWritten: progress.rowsWritten })
+    })
+
+    const options = { ...buildFormat(), jobId }
+    try {
+      let summary: ExportSummary
+      try {
+        summary = await exportQuery(exportSource, path, options)
+      } catch (err) {
+        const commandError = toCommandError(err)
+        if (commandError.kind !== 'confirmationRequired' || !commandError.confirmationToken) {
+          throw err
+        }
+        if (!window.confirm(`${commandError.message}.\n\nRun it anyway?`)) throw err
+        summary = await exportQuery(exportSource, path, {
+          ...options,
+          confirmationToken: commandError.confirmationToken,
+        })
+      }
+      const rows = `${summary.rowsWritten} row${summary.rowsWritten === 1 ? '' : 's'}`
+      setMessage(`Exported ${rows} to ${summary.path}`)
+    } catch (err) {
+      setMessage(toCommandError(err).message)
+    } finally {
+      unlisten()
+      setRunning(null)
+    }
+  }, [kind, table, source, transactionStates, buildFormat])
+
+  return (
+    <div className={styles.bar}>
+      <select
+        className={styles.select}
+        value={kind}
+        onChange={(e) => setKind(e.target.value as FormatKind)}
+        disabled={running !== null}
+      >
+        {FORMATS.map((f) => (
+          <option key={f.kind} value={f.kind}>
+            {f.label}
+          </option>
+        ))}
+      </select>
+      {kind === 'csv' && (
+        <>
+          <select
+            className={styles.select}
+            value={delimiter}
+            onChange={(e) => setDelimiter(e.target.value)}
+            title="Delimiter"
+          >
+            {DELIMITERS.map((d) => (
+              <option key={d.label} value={d.value}>
+                {d.label}
+              </option>
+            ))}
+          </select>
+          <label className={styles.checkbox}>
+            <input
+              type="checkbox"
+              checked={quoteAll}
+              onChange={(e) => setQuoteAll(e.target.checked)}
+            />
+            Quote all
+          </label>
+          <input
+            className={styles.input}
+            value={nullMarker}
+            onChange={(e) => setNullMarker(e.target.value)}
+            placeholder="NULL as"
+            title="Written for NULL values; empty leaves the field empty"
+          />
+        </>
+      )}
+      {kind === 'insert' && (
+        <input
+          className={styles.input}
+          value={table}
+          onChange={(e) => setTable(e.target.value)}
+          placeholder="Target table"
+        />
+      )}
+      {running ? (
+        <>
+          <span className={styles.progress}>{running.rowsWritten} rows written...</span>
+          <button className={styles.button} onClick={() => void cancelQuery(running.jobId)}>
+            Cancel
+          </button>
+        </>
+      ) : (
+        <button className={styles.button} onClick={() => void handleExport()}>
+          <Download size={14} />
+          Export
+        </button>
+      )}
+      {message && <span className={styles.message}>{message}</span>}
+    </div>
+  )
+}
@@ -0,0 +1,34 @@
+import { style, styleVariants } from '@vanilla-extract/css'
+
+import { vars } from '../../../configs/theme.css'
+
+export const container = style({
+  display: 'flex',
+  flexDirection: 'column',
+  gap: vars.space.xs,
+  maxHeight: '30%',
+  overflowY: 'auto',
+  padding: `${vars.space.xs} ${vars.space.md}`,
+  backgroundColor: vars.color.backgroundSecondary,
+  borderTop: `1px solid ${vars.color.border}`,
+  fontFamily: vars.fontFamily.mono,
+  fontSize: vars.fontSize.xs,
+  flexShrink: 0,
+})
+
+export const notice = style({
+  display: 'flex',
+  flexDirection: 'column',
+  whiteSpace: 'pre-wrap',
+  color: vars.color.foreground,
+})
+
+export const severity = styleVariants({
+  warning: { color: vars.color.warning },
+  other: { color: vars.color.foregroundSecondary },
+})
+
+export const detail = style({
+  paddingLeft: vars.space.md,
+  color: vars.color.foregroundSecondary,
+})
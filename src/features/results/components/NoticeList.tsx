@@ -0,0 +1,30 @@
+import type { ServerNotice } from '../../../types/database'
+import * as styles from './NoticeList.css'
+
+interface NoticeListProps {
+  notices: ServerNotice[]
+}
+
+/** Notices the server sent while the statement ran, oldest first. */
+export function NoticeList({ notices }: NoticeListProps) {
+  if (notices.length === 0) return null
+
+  return (
+    <div className={styles.container}>
+      {notices.map((notice, index) => (
+        <div key={index} className={styles.notice}>
+          <span>
+            <span
+              className={styles.severity[notice.severity === 'WARNING' ? 'warning' : 'other']}
+            >
+              {notice.severity}:
+            </span>{' '}
+            {notice.message}
+          </span>
+          {notice.detail && <span className={styles.detail}>DETAIL: {notice.detail}</span>}
+          {notice.hint && <span className={styles.detail}>HINT: {notice.hint}</span>}
+        </div>
+      ))}
+    </div>
+  )
+}
@@ -0,0 +1,103 @@
+import type { Delta, NodeDiff, PlanComparison } from '../../../types/database'
+import { formatNumber } from '../utils/plan'
+import * as styles from './PlanView.css'
+import * as tableStyles from './ResultsTable.css'
+
+interface PlanComparisonViewProps {
+  comparison: PlanComparison
+  onClose: () => void
+}
+
+const STATUS_CLASSES: Record<NodeDiff['status'], string> = {
+  matched: '',
+  typeChanged: styles.statusChanged,
+  added: styles.statusAdded,
+  removed: styles.statusRemoved,
+}
+
+function formatDelta(delta: Delta, digits = 0): string {
+  if (delta.before === delta.after) return formatNumber(delta.after, digits)
+  const before = formatNumber(delta.before, digits) || '–'
+  const after = formatNumber(delta.after, digits) || '–'
+  return `${before} → ${after}`
+}
+
+function describeNode(node: NodeDiff): string {
+  const before = node.beforeNodeType
+  const after = node.afterNodeType
+  const name = before && after && before !== after ? `${before} → ${after}` : (after ?? before)
+  const index = node.afterIndexName ?? node.beforeIndexName
+  return [name, node.relation && `on ${node.relation}`, index && `using ${index}`]
+    .filter(Boolean)
+    .join(' ')
+}
+
+/** Two plans aligned node by node, with what changed between them. */
+export function PlanComparisonView({ comparison, onClose }: PlanComparisonViewProps) {
+  const analyzed = comparison.basis === 'executionTime'
+
+  return (
+    <div className={tableStyles.container}>
+      <div className={styles.summary}>
+        <span className={styles.verdict[comparison.verdict]}>{comparison.summary}</span>
+        <button className={tableStyles.rowActionButton} onClick={onClose}>
+          Back to plan
+        </button>
+      </div>
+      <div className={tableStyles.tableWrapper}>
+        <table className={tableStyles.table}>
+          <thead>
+            <tr>
+              <th className={tableStyles.th}>Node</th>
+              <th className={tableStyles.th}>Cost</th>
+              <th className={tableStyles.th}>Est. rows</th>
+              {analyzed && (
+                <>
+                  <th className={tableStyles.th}>Rows</th>
+                  <th className={tableStyles.th}>Self ms</th>
+                  <th className={tableStyles.th}>Read</th>
+                </>
+              )}
+            </tr>
+          </thead>
+          <tbody>
+            {comparison.nodes.map((node, index) => (
+              <tr key={index} className={tableStyles.tr}>
+                <td
+                  className={`${tableStyles.td} ${styles.nodeCell}`}
+                  style={{ paddingLeft: `${node.depth * 16 + 12}px` }}
+                >
+                  <span className={`${styles.nodeType} ${STATUS_CLASSES[node.status]}`}>
+                    {describeNode(node)}
+                  </span>
+                  {comparison.mainChanges.includes(index) && (
+                    <span className={styles.hotSpot}>main change</span>
+                  )}
+                </td>
+                <td className={`${tableStyles.td} ${styles.number}`}>
+                  {formatDelta(node.totalCost, 2)}
+                </td>
+                <td className={`${tableStyles.td} ${styles.number}`}>
+                  {formatDelta(node.estimatedRows)}
+                </td>
+                {analyzed && (
+                  <>
+                    <td className={`${tableStyles.td} ${styles.number}`}>
+                      {formatDelta(node.actualRows, 2)}
+                    </td>
+                    <td className={`${tableStyles.td} ${styles.number}`}>
+                      {formatDelta(node.exclusiveTimeMs, 3)}
+                    </td>
+                    <td className={`${tableStyles.td} ${styles.number}`}>
+                      {formatDelta(node.sharedRead)}
+                    </td>
+                  </>
+                )}
+              </tr>
+            ))}
+          </tbody>
+        </table>
+      </div>
+    </div>
+  )
+}
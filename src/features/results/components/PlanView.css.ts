@@ -0,0 +1,68 @@
+import { style, styleVariants } from '@vanilla-extract/css'
+
+import { vars } from '../../../configs/theme.css'
+
+export const summary = style({
+  display: 'flex',
+  flexWrap: 'wrap',
+  alignItems: 'center',
+  gap: vars.space.md,
+  padding: `${vars.space.xs} ${vars.space.md}`,
+  backgroundColor: vars.color.backgroundSecondary,
+  borderBottom: `1px solid ${vars.color.border}`,
+  fontSize: vars.fontSize.sm,
+  color: vars.color.foregroundSecondary,
+  flexShrink: 0,
+})
+
+export const summaryValue = style({
+  color: vars.color.foreground,
+  fontWeight: 500,
+})
+
+export const nodeCell = style({
+  whiteSpace: 'nowrap',
+})
+
+export const nodeType = style({
+  color: vars.color.foreground,
+  fontWeight: 600,
+})
+
+export const nodeDetail = style({
+  marginLeft: vars.space.sm,
+  color: vars.color.foregroundSecondary,
+})
+
+export const number = style({
+  textAlign: 'right',
+})
+
+export const hotSpot = style({
+  display: 'inline-block',
+  marginRight: vars.space.xs,
+  padding: `0 ${vars.space.xs}`,
+  border: `1px solid ${vars.color.warning}`,
+  borderRadius: vars.radius.sm,
+  color: vars.color.warning,
+  fontSize: vars.fontSize.xs,
+})
+
+export const statusChanged = style({
+  color: vars.color.warning,
+})
+
+export const statusAdded = style({
+  color: vars.color.success,
+})
+
+export const statusRemoved = style({
+  color: vars.color.foregroundSecondary,
+  textDecoration: 'line-through',
+})
+
+export const verdict = styleVariants({
+  faster: { color: vars.color.success, fontWeight: 500 },
+  slower: { color: vars.color.error, fontWeight: 500 },
+  unchanged: { color: vars.color.foreground, fontWeight: 500 },
+})
@@ -0,0 +1,196 @@
+import { useEffect, useState } from 'react'
+
+import type { ExplainResult, HotSpot, PlanComparison, PlanNode } from '../../../types/database'
+import { errorMessage } from '../../../utils/errors'
+import { comparePlans } from '../../editor/api/queryApi'
+import { useResultsStore } from '../stores/resultsStore'
+import { formatNumber } from '../utils/plan'
+import { PlanComparisonView } from './PlanComparisonView'
+import * as styles from './PlanView.css'
+import * as tableStyles from './ResultsTable.css'
+
+interface PlanViewProps {
+  plan: ExplainResult
+}
+
+interface PlanRow {
+  node: PlanNode
+  depth: number
+}
+
+function flatten(node: PlanNode, depth = 0, rows: PlanRow[] = []): PlanRow[] {
+  rows.push({ node, depth })
+  for (const child of node.children) flatten(child, depth + 1, rows)
+  return rows
+}
+
+function describeHotSpot(hotSpot: HotSpot): string {
+  switch (hotSpot.kind) {
+    case 'misestimate':
+      return `rows off ${formatNumber(hotSpot.factor)}x`
+    case 'largeSeqScan':
+      return `seq scan of ${formatNumber(hotSpot.rows)} rows`
+    case 'slow':
+      return `${Math.round(hotSpot.share * 100)}% of time`
+    case 'spill':
+      return 'spills to disk'
+  }
+}
+
+function describeNode(node: PlanNode): string {
+  const alias = node.alias && node.alias !== node.relation ? ` ${node.alias}` : ''
+  return [
+    node.joinType && `${node.joinType} join`,
+    node.relation && `on ${node.relation}${alias}`,
+    node.indexName && `using ${node.indexName}`,
+    node.condition,
+    node.filter && `filter ${node.filter}`,
+  ]
+    .filter(Boolean)
+    .join(' ')
+}
+
+/** A plan from `explain_query` as an indented table, one row per node. */
+export function PlanView({ plan }: PlanViewProps) {
+  const { baselinePlan, setBaselinePlan } = useResultsStore()
+  const [comparison, setComparison] = useState<PlanComparison | null>(null)
+  const [compareError, setCompareError] = useState<string | null>(null)
+
+  useEffect(() => {
+    setComparison(null)
+    setCompareError(null)
+  }, [plan])
+
+  const handleCompare = async () => {
+    if (!baselinePlan) return
+    try {
+      setComparison(await comparePlans(baselinePlan.raw, plan.raw))
+    } catch (err) {
+      setCompareError(errorMessage(err))
+    }
+  }
+
+  if (comparison) {
+    return <PlanComparisonView comparison={comparison} onClose={() => setComparison(null)} />
+  }
+
+  const rows = flatten(plan.plan)
+  const analyzed = plan.plan.loops !== null
+  const hasBuffers = rows.some(({ node }) => node.buffers)
+  const settings = Object.entries(plan.settings)
+
+  return (
+    <div className={tableStyles.container}>
+      <div className={styles.summary}>
+        {plan.planningTimeMs !== null && (
+          <span>
+            Planning{' '}
+            <span className={styles.summaryValue}>{formatNumber(plan.planningTimeMs, 3)} ms</span>
+          </span>
+        )}
+        {plan.executionTimeMs !== null && (
+          <span>
+            Execution{' '}
+            <span className={styles.summaryValue}>{formatNumber(plan.executionTimeMs, 3)} ms</span>
+          </span>
+        )}
+        {plan.rolledBack && <span>Ran inside a transaction that was rolled back</span>}
+        {settings.length > 0 && (
+          <span>Settings: {settings.map(([name, value]) => `${name} = ${value}`).join(', ')}</span>
+        )}
+        <button
+          className={tableStyles.rowActionButton}
+          onClick={() => setBaselinePlan(plan)}
+          disabled={baselinePlan === plan}
+        >
+          {baselinePlan === plan ? 'Baseline' : 'Set as baseline'}
+        </button>
+        {baselinePlan && baselinePlan !== plan && (
+          <button className={tableStyles.rowActionButton} onClick={handleCompare}>
+            Compare with baseline
+          </button>
+        )}
+        {compareError && <span className={tableStyles.saveMessage}>{compareError}</span>}
+      </div>
+      <div className={tableStyles.tableWrapper}>
+        <table className={tableStyles.table}>
+          <thead>
+            <tr>
+              <th className={tableStyles.th}>Node</th>
+              <th className={tableStyles.th}>Cost</th>
+              <th className={tableStyles.th}>Est. rows</th>
+              {analyzed && (
+                <>
+                  <th className={tableStyles.th}>Rows</th>
+                  <th className={tableStyles.th}>Loops</th>
+                  <th className={tableStyles.th}>Total ms</th>
+                  <th className={tableStyles.th}>Self ms</th>
+                </>
+              )}
+              {hasBuffers && (
+                <>
+                  <th className={tableStyles.th}>Hit</th>
+                  <th className={tableStyles.th}>Read</th>
+                </>
+              )}
+              <th className={tableStyles.th}>Hot spots</th>
+            </tr>
+          </thead>
+          <tbody>
+            {rows.map(({ node, depth }, index) => (
+              <tr key={index} className={tableStyles.tr}>
+                <td
+                  className={`${tableStyles.td} ${styles.nodeCell}`}
+                  style={{ paddingLeft: `${depth * 16 + 12}px` }}
+                  title={describeNode(node)}
+                >
+                  <span className={styles.nodeType}>{node.nodeType}</span>
+                  <span className={styles.nodeDetail}>{describeNode(node)}</span>
+                </td>
+                <td className={`${tableStyles.td} ${styles.number}`}>
+                  {formatNumber(node.totalCost, 2)}
+                </td>
+                <td className={`${tableStyles.td} ${styles.number}`}>
+                  {formatNumber(node.estimatedRows)}
+                </td>
+                {analyzed && (
+                  <>
+                    <td className={`${tableStyles.td} ${styles.number}`}>
+                      {node.loops === 0 ? 'never executed' : formatNumber(node.actualRows, 2)}
+                    </td>
+                    <td className={`${tableStyles.td} ${styles.number}`}>
+                      {formatNumber(node.loops)}
+                    </td>
+                    <td className={`${tableStyles.td} ${styles.number}`}>
+                      {formatNumber(node.actualTotalTimeMs, 3)}
+                    </td>
+                    <td className={`${tableStyles.td} ${styles.number}`}>
+                      {formatNumber(node.exclusiveTimeMs, 3)}
+                    </td>
+                  </>
+                )}
+                {hasBuffers && (
+                  <>
+                    <td className={`${tableStyles.td} ${styles.number}`}>
+                      {formatNumber(node.buffers?.sharedHit ?? null)}
+                    </td>
+                    <td className={`${tableStyles.td} ${styles.number}`}>
+                      {formatNumber(node.buffers?.sharedRead ?? null)}
+                    </td>
+                  </>
+                )}
+                <td className={tableStyles.td}>
+                  {node.hotSpots.map((hotSpot) => (
+                    <span key={hotSpot.kind} className={styles.hotSpot}>
+                      {describeHotSpot(hotSpot)}
+                    </span>
+                  ))}
+                </td>
+              </tr>
+            ))}
+          </tbody>
+        </table>
+      </div>
+    </div>
+  )
+}
@@ -152,6 +152,7 @@ export const message = style({
 
 export const error = style({
   display: 'flex',
+  flexDirection: 'column',
   alignItems: 'center',
   justifyContent: 'center',
   flex: 1,
@@ -160,6 +161,13 @@ export const error = style({
   fontSize: vars.fontSize.md,
   padding: vars.space.lg,
   textAlign: 'center',
+  gap: vars.space.sm,
+})
+
+export const errorDetail = style({
+  fontSize: vars.fontSize.xs,
+  color: vars.color.foregroundSecondary,
+  whiteSpace: 'pre-wrap',
 })
 
 export const executionTime = style({
@@ -230,6 +238,24 @@ export const trRowSelected = style({
   },
 })
 
+export const trDraft = style({
+  selectors: {
+    '&&, &&:nth-child(even)': {
+      backgroundColor: 'rgba(137, 209, 133, 0.08)',
+    },
+  },
+})
+
+export const trDeleted = style({
+  opacity: 0.5,
+  textDecoration: 'line-through',
+  selectors: {
+    '&&, &&:nth-child(even)': {
+      backgroundColor: 'rgba(244, 135, 113, 0.1)',
+    },
+  },
+})
+
 export const rowActionBar = style({
   display: 'flex',
   alignItems: 'center',
@@ -278,3 +304,10 @@ export const rowActionLabel = style({
   color: vars.color.foreground,
   fontWeight: 500,
 })
+
+export const saveMessage = style({
+  color: vars.color.error,
+  overflow: 'hidden',
+  textOverflow: 'ellipsis',
+  whiteSpace: 'nowrap',
+})
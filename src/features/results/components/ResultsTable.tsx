@@ -1,6 +1,14 @@
-import { useCallback, useEffect, useRef, useState } from 'react'
+import { useCallback, useEffect, useMemo, useRef, useState } from 'react'
 
+import type { CellChange, RowOperation } from '../../../types/database'
+import { toCommandError } from '../../../utils/errors'
+import { cancelQuery, fetchMoreRows } from '../../editor/api/queryApi'
+import { useEditorStore } from '../../editor/stores/editorStore'
+import { applyRowOperations } from '../api/resultsApi'
 import { useResultsStore } from '../stores/resultsStore'
+import { ExportBar } from './ExportBar'
+import { NoticeList } from './NoticeList'
+import { PlanView } from './PlanView'
 import * as styles from './ResultsTable.css'
 
 interface CellEdit {
@@ -10,6 +18,13 @@ interface CellEdit {
   newValue: string
 }
 
+/** A row added in the grid that has not been saved yet. */
+interface DraftRow {
+  /** The loaded row it copies, or null for a blank row. */
+  source: number | null
+  values: unknown[]
+}
+
 interface EditingCell {
   rowIndex: number
   cellIndex: number
@@ -49,7 +64,18 @@ function getRowSelectionBounds(sel: RowSelection) {
 }
 
 export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
-  const { results, isExecuting, error } = useResultsStore()
+  const {
+    results,
+    source,
+    plan,
+    isExecuting,
+    runningJobId,
+    error,
+    appendPage,
+    setRows,
+    setError,
+  } = useResultsStore()
+  const { activeTabId, setTransactionState } = useEditorStore()
   const tableRef = useRef<HTMLTableElement>(null)
   const inputRef = useRef<HTMLInputElement>(null)
   const [editingCell, setEditingCell] = useState<EditingCell | null>(null)
@@ -58,6 +84,18 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
   const [copiedCells, setCopiedCells] = useState<Set<string> | null>(null)
   const [rowSelection, setRowSelection] = useState<RowSelection | null>(null)
   const isDragging = useRef(false)
+  const [isLoadingMore, setLoadingMore] = useState(false)
+  const [isSaving, setSaving] = useState(false)
+  const [saveMessage, setSaveMessage] = useState<string | null>(null)
+  const [drafts, setDrafts] = useState<DraftRow[]>([])
+  const [deletedRows, setDeletedRows] = useState<Set<number>>(new Set())
+
+  const rows = useMemo(
+    () => (results ? [...results.rows, ...drafts.map((d) => d.values)] : []),
+    [results, drafts]
+  )
+  const pendingCount = modifications.size + drafts.length + deletedRows.size
+  const isEditable = (!results?.dryRun && results?.columns.some((c) => c.tableOid)) ?? false
 
   const getCellKey = (rowIndex: number, cellIndex: number) => `${rowIndex}-${cellIndex}`
 
@@ -146,30 +184,30 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
     if (!results) return
     if (rowSelection) {
       const { minRow, maxRow } = getRowSelectionBounds(rowSelection)
-      if (minRow === 0 && maxRow === results.rows.length - 1) {
+      if (minRow === 0 && maxRow === rows.length - 1) {
         setRowSelection(null)
         return
       }
     }
-    setRowSelection({ anchor: 0, focus: results.rows.length - 1 })
+    setRowSelection({ anchor: 0, focus: rows.length - 1 })
     setSelection(null)
     setEditingCell(null)
-  }, [results, rowSelection])
+  }, [results, rows, rowSelection])
 
   const handleCopySelectedRows = useCallback(() => {
     if (!rowSelection || !results) return
 
     const indices = getSelectedRowIndices()
     const lines = indices.map((rowIndex) => {
-      const cells = results.rows[rowIndex].map((cell, cellIndex) =>
+      const cells = rows[rowIndex].map((cell, cellIndex) =>
         getCellValue(rowIndex, cellIndex, cell)
       )
       return cells.join('\t')
     })
 
-    const header = results.columns.join('\t')
+    const header = results.columns.map((c) => c.name).join('\t')
     navigator.clipboard.writeText([header, ...lines].join('\n'))
-  }, [rowSelection, results, getSelectedRowIndices, modifications])
+  }, [rowSelection, results, rows, getSelectedRowIndices, modifications])
 
   const startEditing = useCallback(
     (rowIndex: number, cellIndex: number, currentValue: unknown) => {
@@ -192,7 +230,7 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
 
     const { rowIndex, cellIndex, value } = editingCell
     const key = getCellKey(rowIndex, cellIndex)
-    const originalValue = results.rows[rowIndex][cellIndex]
+    const originalValue = rows[rowIndex][cellIndex]
     const originalFormatted = formatCell(originalValue)
 
     if (value !== originalFormatted) {
@@ -210,7 +248,7 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
     }
 
     setEditingCell(null)
-  }, [editingCell, results])
+  }, [editingCell, results, rows])
 
   const cancelEdit = useCallback(() => {
     setEditingCell(null)
@@ -235,12 +273,12 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
           : Math.min(results.columns.length - 1, editingCell.cellIndex + 1)
 
         if (nextCell !== editingCell.cellIndex) {
-          const currentValue = results.rows[editingCell.rowIndex][nextCell]
+          const currentValue = rows[editingCell.rowIndex][nextCell]
           startEditing(editingCell.rowIndex, nextCell, currentValue)
         }
       }
     },
-    [commitEdit, cancelEdit, results, editingCell, startEditing]
+    [commitEdit, cancelEdit, results, rows, editingCell, startEditing]
   )
 
   const getCellValue = (rowIndex: number, cellIndex: number, originalValue: unknown): string => {
@@ -260,7 +298,7 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
     for (let r = minRow; r <= maxRow; r++) {
       const cells: string[] = []
       for (let c = minCell; c <= maxCell; c++) {
-        cells.push(getCellValue(r, c, results.rows[r][c]))
+        cells.push(getCellValue(r, c, rows[r][c]))
         keys.add(getCellKey(r, c))
       }
       lines.push(cells.join('\t'))
@@ -269,7 +307,7 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
     navigator.clipboard.writeText(lines.join('\n'))
     setCopiedCells(keys)
     setTimeout(() => setCopiedCells(null), 600)
-  }, [selection, results, modifications])
+  }, [selection, results, rows, modifications])
 
   const handleMouseDown = useCallback(
     (e: React.MouseEvent, rowIndex: number, cellIndex: number) => {
@@ -348,7 +386,7 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
         setSelection({
           anchorRow: 0,
           anchorCell: 0,
-          focusRow: results.rows.length - 1,
+          focusRow: rows.length - 1,
           focusCell: results.columns.length - 1,
         })
         return
@@ -382,7 +420,7 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
           nextRow = Math.max(0, rowIndex - 1)
           break
         case 'ArrowDown':
-          nextRow = Math.min(results.rows.length - 1, rowIndex + 1)
+          nextRow = Math.min(rows.length - 1, rowIndex + 1)
           break
         case 'ArrowLeft':
           nextCell = Math.max(0, cellIndex - 1)
@@ -404,7 +442,16 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
         focusElement(nextRow, nextCell)
       }
     },
-    [copySelection, onAddWhereClause, results, startEditing, selectCell, extendSelection, focusElement]
+    [
+      copySelection,
+      onAddWhereClause,
+      results,
+      rows,
+      startEditing,
+      selectCell,
+      extendSelection,
+      focusElement,
+    ]
   )
 
   const handleDoubleClick = useCallback(
@@ -432,10 +479,167 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
     return styles.tdFocusable
   }
 
+  const handleLoadMore = useCallback(async () => {
+    if (!results?.resultId) return
+
+    setLoadingMore(true)
+    try {
+      const page = await fetchMoreRows(results.resultId)
+      appendPage(page)
+    } catch (err) {
+      setError(toCommandError(err))
+    } finally {
+      setLoadingMore(false)
+    }
+  }, [results, appendPage, setError])
+
+  useEffect(() => {
+    setModifications(new Map())
+    setDrafts([])
+    setDeletedRows(new Set())
+    setSaveMessage(null)
+  }, [results?.columns])
+
+  const handleAddRow = useCallback(() => {
+    if (!results) return
+    setDrafts((prev) => [...prev, { source: null, values: results.columns.map(() => null) }])
+  }, [results])
+
+  const handleDuplicateSelectedRows = useCallback(() => {
+    if (!results) return
+    const copies = getSelectedRowIndices()
+      .filter((rowIndex) => rowIndex < results.rows.length)
+      .map((rowIndex) => ({ source: rowIndex, values: results.rows[rowIndex] }))
+    setDrafts((prev) => [...prev, ...copies])
+    setRowSelection(null)
+  }, [results, getSelectedRowIndices])
+
+  const handleDeleteSelectedRows = useCallback(() => {
+    const indices = getSelectedRowIndices()
+    setDeletedRows((prev) => {
+      const next = new Set(prev)
+      const restore = indices.every((rowIndex) => prev.has(rowIndex))
+      for (const rowIndex of indices) {
+        if (restore) next.delete(rowIndex)
+        else next.add(rowIndex)
+      }
+      return next
+    })
+    setRowSelection(null)
+  }, [getSelectedRowIndices])
+
+  const handleSaveChanges = useCallback(async () => {
+    if (!results || pendingCount === 0) return
+
+    const cellsByRow = new Map<number, CellChange[]>()
+    for (const mod of modifications.values()) {
+      const cells = cellsByRow.get(mod.rowIndex) ?? []
+      cells.push({
+        columnIndex: mod.cellIndex,
+        value: mod.newValue === 'NULL' ? null : mod.newValue,
+      })
+      cellsByRow.set(mod.rowIndex, cells)
+    }
+
+    const operations: RowOperation[] = []
+    results.rows.forEach((original, rowIndex) => {
+      if (deletedRows.has(rowIndex)) {
+        operations.push({ kind: 'delete', rowIndex, original })
+      } else if (cellsByRow.has(rowIndex)) {
+        operations.push({ kind: 'update', rowIndex, original, cells: cellsByRow.get(rowIndex)! })
+      }
+    })
+    drafts.forEach((draft, i) => {
+      const rowIndex = results.rows.length + i
+      if (deletedRows.has(rowIndex)) return
+      const cells = cellsByRow.get(rowIndex) ?? []
+      operations.push(
+        draft.source === null
+          ? { kind: 'insert', rowIndex, cells }
+          : { kind: 'duplicate', rowIndex, original: results.rows[draft.source], cells }
+      )
+    })
+
+    setSaving(true)
+    setSaveMessage(null)
+    try {
+      const outcome = await applyRowOperations(results.columns, operations, {
+        tabId: activeTabId ?? undefined,
+      })
+      if (activeTabId) setTransactionState(activeTabId, outcome.transactionState)
+
+      if (outcome.applied) {
+        const stored = new Map(outcome.rows.map((r) => [r.rowIndex, r.row]))
+        setRows(
+          rows.flatMap((row, rowIndex) => {
+            if (deletedRows.has(rowIndex)) return []
+            return [stored.get(rowIndex) ?? row]
+          })
+        )
+        setModifications(new Map())
+        setDrafts([])
+        setDeletedRows(new Set())
+        return
+      }
+
+      const conflicts = outcome.rows.filter((r) => r.status === 'conflict')
+      const failure = outcome.rows.find((r) => r.status === 'failed')
+      setSaveMessage(
+        failure
+          ? `Row ${failure.rowIndex + 1}: ${failure.error?.message ?? 'failed'}. No changes were saved.`
+          : `${conflicts.length} row(s) changed since they were loaded (${conflicts
+              .map((r) => r.rowIndex + 1)
+              .join(', ')}). No changes were saved.`
+      )
+    } catch (err) {
+      setSaveMessage(toCommandError(err).message)
+    } finally {
+      setSaving(false)
+    }
+  }, [
+    results,
+    rows,
+    drafts,
+    deletedRows,
+    modifications,
+    pendingCount,
+    activeTabId,
+    setTransactionState,
+    setRows,
+  ])
+
+  const handleDiscardChanges = useCallback(() => {
+    setModifications(new Map())
+    setDrafts([])
+    setDeletedRows(new Set())
+    setSaveMessage(null)
+  }, [])
+
+  const getRowClassName = (rowIndex: number): string => {
+    let className = styles.tr
+    if (isRowSelected(rowIndex)) className += ` ${styles.trRowSelected}`
+    if (deletedRows.has(rowIndex)) className += ` ${styles.trDeleted}`
+    else if (results && rowIndex >= results.rows.length) className += ` ${styles.trDraft}`
+    return className
+  }
+
   if (isExecuting) {
     return (
       <div className={styles.container}>
-        <div className={styles.message}>Executing query...</div>
+        <div className={styles.message}>
+          Executing query...
+          {runningJobId && (
+            <>
+              <br />
+              <button
+                className={styles.rowActionButton}
+                onClick={() => void cancelQuery(runningJobId)}
+              >
+                Cancel
+              </button>
+            </>
+          )}
+        </div>
       </div>
     )
   }
@@ -443,11 +647,25 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
   if (error) {
     return (
       <div className={styles.container}>
-        <div className={styles.error}>{error}</div>
+        <div className={styles.error}>
+          <span>
+            {error.severity ? `${error.severity}: ` : ''}
+            {error.message}
+          </span>
+          {error.detail && <span className={styles.errorDetail}>DETAIL: {error.detail}</span>}
+          {error.hint && <span className={styles.errorDetail}>HINT: {error.hint}</span>}
+          {error.context && <span className={styles.errorDetail}>CONTEXT: {error.context}</span>}
+          {error.code && <span className={styles.errorDetail}>SQLSTATE {error.code}</span>}
+        </div>
+        <NoticeList notices={error.notices ?? []} />
       </div>
     )
   }
 
+  if (plan) {
+    return <PlanView plan={plan} />
+  }
+
   if (!results) {
     return (
       <div className={styles.container}>
@@ -456,14 +674,29 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
     )
   }
 
-  if (results.rowsAffected != null) {
+  if (results.cancelled) {
+    return (
+      <div className={styles.container}>
+        <div className={styles.message}>
+          Query cancelled.
+          <br />
+          <span className={styles.executionTime}>{results.executionTimeMs}ms</span>
+        </div>
+      </div>
+    )
+  }
+
+  if (results.rowsAffected != null && results.columns.length === 0) {
     return (
       <div className={styles.container}>
         <div className={styles.message}>
-          Query executed successfully. {results.rowsAffected} row(s) affected.
+          {results.dryRun
+            ? `Dry run: ${results.rowsAffected} row(s) would be affected; rolled back.`
+            : `Query executed successfully. ${results.rowsAffected} row(s) affected.`}
           <br />
           <span className={styles.executionTime}>{results.executionTimeMs}ms</span>
         </div>
+        <NoticeList notices={results.notices} />
       </div>
     )
   }
@@ -476,6 +709,7 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
           <br />
           <span className={styles.executionTime}>{results.executionTimeMs}ms</span>
         </div>
+        <NoticeList notices={results.notices} />
       </div>
     )
   }
@@ -493,17 +727,17 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
                 #
               </th>
               {results.columns.map((column, i) => (
-                <th key={i} className={styles.th}>
-                  {column}
+                <th key={i} className={styles.th} title={column.dataType}>
+                  {column.name}
                 </th>
               ))}
             </tr>
           </thead>
           <tbody>
-            {results.rows.map((row, rowIndex) => (
+            {rows.map((row, rowIndex) => (
               <tr
                 key={rowIndex}
-                className={`${styles.tr}${isRowSelected(rowIndex) ? ` ${styles.trRowSelected}` : ''}`}
+                className={getRowClassName(rowIndex)}
               >
                 <td
                   className={
@@ -513,7 +747,7 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
                   }
                   onClick={(e) => handleRowSelect(rowIndex, e.shiftKey)}
                 >
-                  {rowIndex + 1}
+                  {rowIndex < results.rows.length ? rowIndex + 1 : '*'}
                 </td>
                 {row.map((cell, cellIndex) => {
                   if (isEditingCell(rowIndex, cellIndex)) {
@@ -546,7 +780,7 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
                       onMouseDown={(e) => handleMouseDown(e, rowIndex, cellIndex)}
                       onMouseEnter={() => handleMouseEnter(rowIndex, cellIndex)}
                       onKeyDown={(e) =>
-                        handleKeyDown(e, rowIndex, cellIndex, results.columns[cellIndex], cell)
+                        handleKeyDown(e, rowIndex, cellIndex, results.columns[cellIndex].name, cell)
                       }
                       onDoubleClick={() => handleDoubleClick(rowIndex, cellIndex, cell)}
                     >
@@ -559,6 +793,75 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
           </tbody>
         </table>
       </div>
+      <NoticeList notices={results.notices} />
+      {source && !results.dryRun && <ExportBar source={source} columns={results.columns} />}
+      {results.dryRun && (
+        <div className={styles.rowActionBar}>
+          <span className={styles.rowActionLabel}>
+            Dry run: {results.rowsAffected} row{results.rowsAffected === 1 ? '' : 's'} would be
+            affected; rolled back.
+            {results.rowLimitReached && ` Showing the first ${results.rows.length}.`}
+          </span>
+        </div>
+      )}
+      {!results.dryRun && (results.hasMore || results.rowLimitReached || isEditable) && (
+        <div className={styles.rowActionBar}>
+          <span className={styles.rowActionLabel}>
+            {results.rows.length} row{results.rows.length === 1 ? '' : 's'} loaded
+            {results.rowLimitReached && ' (row limit reached)'}
+          </span>
+          <button className={styles.rowActionButton} onClick={handleAddRow}>
+            Add row
+          </button>
+          {results.hasMore && (
+            <button
+              className={styles.rowActionButton}
+              onClick={handleLoadMore}
+              disabled={isLoadingMore}
+            >
+              {isLoadingMore ? 'Loading...' : 'Load more'}
+            </button>
+          )}
+          {isLoadingMore && results.jobId && (
+            <button
+              className={styles.rowActionButton}
+              onClick={() => void cancelQuery(results.jobId!)}
+            >
+              Cancel
+            </button>
+          )}
+        </div>
+      )}
+      {(pendingCount > 0 || saveMessage) && (
+        <div className={styles.rowActionBar}>
+          <span className={styles.rowActionLabel}>
+            {[
+              modifications.size > 0 &&
+                `${modifications.size} cell${modifications.size === 1 ? '' : 's'} changed`,
+              drafts.length > 0 && `${drafts.length} row${drafts.length === 1 ? '' : 's'} added`,
+              deletedRows.size > 0 &&
+                `${deletedRows.size} row${deletedRows.size === 1 ? '' : 's'} deleted`,
+            ]
+              .filter(Boolean)
+              .join(', ')}
+          </span>
+          {saveMessage && <span className={styles.saveMessage}>{saveMessage}</span>}
+          <button
+            className={styles.rowActionButton}
+            onClick={handleSaveChanges}
+            disabled={isSaving || pendingCount === 0}
+          >
+            {isSaving ? 'Saving...' : 'Save changes'}
+          </button>
+          <button
+            className={styles.rowActionButton}
+            onClick={handleDiscardChanges}
+            disabled={isSaving}
+          >
+            Discard
+          </button>
+        </div>
+      )}
       {rowSelection && (
         <div className={styles.rowActionBar}>
           <span className={styles.rowActionLabel}>
@@ -570,7 +873,10 @@ export function ResultsTable({ onAddWhereClause }: ResultsTableProps) {
           >
             Copy
           </button>
-          <button className={styles.rowActionButtonDanger}>
+          <button className={styles.rowActionButton} onClick={handleDuplicateSelectedRows}>
+            Duplicate
+          </button>
+          <button className={styles.rowActionButtonDanger} onClick={handleDeleteSelectedRows}>
             Delete
           </button>
         </div>
@@ -1,14 +1,35 @@
 import { createContext, useContext, useState, useCallback, type ReactNode } from 'react'
 
-import type { QueryResult } from '../../../types/database'
+import type {
+  CommandError,
+  ExplainResult,
+  ExportSource,
+  QueryPage,
+  QueryResult,
+} from '../../../types/database'
 
 interface ResultsContextValue {
   results: QueryResult | null
+  /** The query behind `results`, to run again for an export; null when they cannot be. */
+  source: ExportSource | null
+  /** A plan from `explain_query`, shown in place of results. */
+  plan: ExplainResult | null
+  /** A plan kept to compare later plans against, e.g. from before adding an index. */
+  baselinePlan: ExplainResult | null
   isExecuting: boolean
-  error: string | null
-  setResults: (results: QueryResult | null) => void
+  runningJobId: string | null
+  error: CommandError | null
+  /** The query that produced `error`, so the editor can mark its position. */
+  errorQuery: string | null
+  setResults: (results: QueryResult | null, source?: ExportSource) => void
+  setPlan: (plan: ExplainResult | null) => void
+  setBaselinePlan: (plan: ExplainResult | null) => void
+  appendPage: (page: QueryPage) => void
+  /** Replaces the loaded rows, e.g. with their stored values after an edit. */
+  setRows: (rows: unknown[][]) => void
   setExecuting: (isExecuting: boolean) => void
-  setError: (error: string | null) => void
+  setRunningJobId: (jobId: string | null) => void
+  setError: (error: CommandError | null, query?: string) => void
   clear: () => void
 }
 
@@ -16,33 +37,86 @@ const ResultsContext = createContext<ResultsContextValue | null>(null)
 
 export function ResultsProvider({ children }: { children: ReactNode }) {
   const [results, setResultsState] = useState<QueryResult | null>(null)
+  const [source, setSource] = useState<ExportSource | null>(null)
+  const [plan, setPlanState] = useState<ExplainResult | null>(null)
+  const [baselinePlan, setBaselinePlan] = useState<ExplainResult | null>(null)
   const [isExecuting, setExecuting] = useState(false)
-  const [error, setErrorState] = useState<string | null>(null)
+  const [runningJobId, setRunningJobId] = useState<string | null>(null)
+  const [error, setErrorState] = useState<CommandError | null>(null)
+  const [errorQuery, setErrorQuery] = useState<string | null>(null)
 
-  const setResults = useCallback((results: QueryResult | null) => {
+  const setResults = useCallback((results: QueryResult | null, source?: ExportSource) => {
     setResultsState(results)
+    setSource(source ?? null)
+    setPlanState(null)
     setErrorState(null)
+    setErrorQuery(null)
   }, [])
 
-  const setError = useCallback((error: string | null) => {
+  const setPlan = useCallback((plan: ExplainResult | null) => {
+    setPlanState(plan)
+    setResultsState(null)
+    setSource(null)
+    setErrorState(null)
+    setErrorQuery(null)
+  }, [])
+
+  const appendPage = useCallback((page: QueryPage) => {
+    setResultsState((prev) =>
+      prev
+        ? {
+            ...prev,
+            rows: [...prev.rows, ...page.rows],
+            rowsAffected: page.rowsAffected ?? prev.rowsAffected,
+            hasMore: page.hasMore,
+            rowLimitReached: page.rowLimitReached,
+            resultId: page.hasMore ? prev.resultId : null,
+            jobId: page.hasMore ? prev.jobId : null,
+            notices: [...prev.notices, ...page.notices],
+          }
+        : prev
+    )
+  }, [])
+
+  const setRows = useCallback((rows: unknown[][]) => {
+    setResultsState((prev) => (prev ? { ...prev, rows } : prev))
+  }, [])
+
+  const setError = useCallback((error: CommandError | null, query?: string) => {
     setErrorState(error)
+    setErrorQuery(query ?? null)
     setResultsState(null)
+    setSource(null)
+    setPlanState(null)
   }, [])
 
   const clear = useCallback(() => {
     setResultsState(null)
+    setSource(null)
+    setPlanState(null)
     setExecuting(false)
     setErrorState(null)
+    setErrorQuery(null)
   }, [])
 
   return (
     <ResultsContext.Provider
       value={{
         results,
+        source,
+        plan,
+        baselinePlan,
         isExecuting,
+        runningJobId,
         error,
+        errorQuery,
         setResults,
+        setPlan,
+        setBaselinePlan,
+        appendPage,
+        setRows,
         setExecuting,
+        setRunningJobId,
         setError,
         clear,
       }}
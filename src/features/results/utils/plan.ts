@@ -0,0 +1,5 @@
+/** Formats a plan figure for display; null, for a figure the plan lacks, becomes blank. */
+export function formatNumber(value: number | null, digits = 0): string {
+  if (value === null) return ''
+  return value.toLocaleString(undefined, { maximumFractionDigits: digits })
+}
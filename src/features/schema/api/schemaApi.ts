@@ -6,8 +6,9 @@ export async function connect(config: ConnectionConfig): Promise<ConnectionInfo>
   return invoke<ConnectionInfo>('connect', { config })
 }
 
-export async function disconnect(): Promise<void> {
-  return invoke<void>('disconnect')
+/** Pass `force` to discard transactions still open in editor tabs. */
+export async function disconnect(force?: boolean): Promise<void> {
+  return invoke<void>('disconnect', { force })
 }
 
 export async function getConnectionInfo(): Promise<ConnectionInfo | null> {
@@ -1,5 +1,6 @@
 import { useCallback } from 'react'
 
+import { errorMessage, toCommandError } from '../../../utils/errors'
 import { useSchemaStore } from '../stores/schemaStore'
 import * as schemaApi from '../api/schemaApi'
 
@@ -12,8 +13,19 @@ export function useSchema() {
       await schemaApi.disconnect()
       reset()
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err)
-      setError(errorMessage)
+      const commandError = toCommandError(err)
+      if (commandError.kind === 'openTransactions') {
+        const confirmed = window.confirm(
+          `${commandError.message}. Disconnecting will roll them back. Disconnect anyway?`
+        )
+        if (confirmed) {
+          await schemaApi.disconnect(true)
+          reset()
+        }
+        return
+      }
+
+      setError(errorMessage(err))
     }
   }, [reset, setError])
 
@@ -25,8 +37,7 @@ export function useSchema() {
       const schemaData = await schemaApi.getSchema()
       setSchema(schemaData)
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : String(err)
-      setError(errorMessage)
+      setError(errorMessage(err))
     } finally {
       setLoading(false)
     }
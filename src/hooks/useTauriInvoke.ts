@@ -1,6 +1,8 @@
 import { invoke } from '@tauri-apps/api/core'
 import { useCallback, useState } from 'react'
 
+import { errorMessage } from '../utils/errors'
+
 interface UseTauriInvokeResult<T> {
   data: T | null
   error: string | null
@@ -23,8 +25,7 @@ export function useTauriInvoke<T>(command: string): UseTauriInvokeResult<T> {
         setData(result)
         return result
       } catch (err) {
-        const errorMessage = err instanceof Error ? err.message : String(err)
-        setError(errorMessage)
+        setError(errorMessage(err))
         return null
       } finally {
         setIsLoading(false)
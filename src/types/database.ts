@@ -17,11 +17,380 @@ export const databaseSchemaSchema = z.object({
   views: z.array(tableSchemaSchema),
 })
 
+export const safetyLevelSchema = z.enum(['read_only', 'confirm_destructive', 'unrestricted'])
+
+/** Limits in whole seconds; unset leaves the server's setting and 0 turns a limit off. */
+export const timeoutsSchema = z.object({
+  statementSecs: z.number().nullish(),
+  lockSecs: z.number().nullish(),
+  idleInTransactionSecs: z.number().nullish(),
+})
+
+/** A notice the server sent while a statement ran, such as `RAISE NOTICE` output. */
+export const serverNoticeSchema = z.object({
+  severity: z.string(),
+  message: z.string(),
+  detail: z.string().nullable(),
+  hint: z.string().nullable(),
+})
+
+export const commandErrorSchema = z.object({
+  kind: z.enum([
+    'notConnected',
+    'database',
+    'openTransactions',
+    'confirmationRequired',
+    'timedOut',
+    'failed',
+  ]),
+  message: z.string(),
+  tabIds: z.array(z.string()).optional(),
+  confirmationToken: z.string().optional(),
+  reasons: z.array(z.string()).optional(),
+  timeout: z.enum(['statement', 'lock', 'idleInTransaction']).optional(),
+  timeoutSecs: z.number().optional(),
+  code: z.string().optional(),
+  severity: z.string().optional(),
+  detail: z.string().nullish(),
+  hint: z.string().nullish(),
+  position: z.number().nullish(),
+  context: z.string().nullish(),
+  schema: z.string().nullish(),
+  table: z.string().nullish(),
+  column: z.string().nullish(),
+  dataType: z.string().nullish(),
+  constraint: z.string().nullish(),
+  notices: z.array(serverNoticeSchema).optional(),
+})
+
+export const statementKindSchema = z.enum([
+  'query',
+  'insert',
+  'update',
+  'delete',
+  'merge',
+  'other',
+])
+
+export const resultColumnSchema = z.object({
+  name: z.string(),
+  typeName: z.string(),
+  typeOid: z.number().nullable(),
+  dataType: z.string(),
+  typeModifier: z.number(),
+  nullable: z.boolean().nullable(),
+  tableOid: z.number().nullable(),
+  tableName: z.string().nullable(),
+  columnNumber: z.number().nullable(),
+})
+
+export const transactionStateSchema = z.enum(['idle', 'inTransaction', 'failed'])
+
 export const queryResultSchema = z.object({
-  columns: z.array(z.string()),
+  columns: z.array(resultColumnSchema),
+  rows: z.array(z.array(z.unknown())),
+  executionTimeMs: z.number(),
+  statementKind: statementKindSchema,
+  rowsAffected: z.number().nullable(),
+  resultId: z.string().nullable(),
+  /** Job to cancel while a later page of an open result set is loading. */
+  jobId: z.string().nullable(),
+  hasMore: z.boolean(),
+  rowLimitReached: z.boolean(),
+  cancelled: z.boolean(),
+  transactionState: transactionStateSchema,
+  notices: z.array(serverNoticeSchema),
+  /** Set by the editor when the rows are a dry run's sample, already rolled back. */
+  dryRun: z.boolean().optional(),
+})
+
+export const queryPageSchema = z.object({
+  rows: z.array(z.array(z.unknown())),
+  rowsAffected: z.number().nullable(),
+  hasMore: z.boolean(),
+  rowLimitReached: z.boolean(),
+  /** The page was cancelled, which closes the result set. */
+  cancelled: z.boolean(),
+  notices: z.array(serverNoticeSchema),
+})
+
+export const displayZoneSchema = z.enum(['session', 'local', 'utc'])
+
+export const queryParameterSchema = z.object({
+  name: z.string().optional(),
+  type: z.string().optional(),
+  value: z.string().nullable(),
+})
+
+export const parameterDescriptionSchema = z.object({
+  position: z.number(),
+  name: z.string().nullable(),
+  dataType: z.string(),
+  typeOid: z.number().nullable(),
+})
+
+export const queryDescriptionSchema = z.object({
+  parameters: z.array(parameterDescriptionSchema),
+  columns: z.array(resultColumnSchema),
+})
+
+export const queryOptionsSchema = z.object({
+  pageSize: z.number().optional(),
+  maxRows: z.number().optional(),
+  displayZone: displayZoneSchema.optional(),
+  jobId: z.string().optional(),
+  tabId: z.string().optional(),
+  parameters: z.array(queryParameterSchema).optional(),
+  timeouts: timeoutsSchema.optional(),
+  confirmationToken: z.string().optional(),
+})
+
+export const queryJobInfoSchema = z.object({
+  jobId: z.string(),
+  tabId: z.string().nullable(),
+  backendPid: z.number(),
+  query: z.string(),
+  startedAt: z.string(),
+})
+
+export const scriptOptionsSchema = z.object({
+  continueOnError: z.boolean().optional(),
+  maxRows: z.number().optional(),
+  displayZone: displayZoneSchema.optional(),
+  jobId: z.string().optional(),
+  tabId: z.string().optional(),
+  timeouts: timeoutsSchema.optional(),
+  confirmationToken: z.string().optional(),
+})
+
+export const dryRunOptionsSchema = z.object({
+  sampleSize: z.number().optional(),
+  displayZone: displayZoneSchema.optional(),
+  jobId: z.string().optional(),
+  tabId: z.string().optional(),
+  parameters: z.array(queryParameterSchema).optional(),
+  timeouts: timeoutsSchema.optional(),
+})
+
+export const dryRunResultSchema = z.object({
+  statementKind: statementKindSchema,
+  rowsAffected: z.number(),
+  columns: z.array(resultColumnSchema),
+  sample: z.array(z.array(z.unknown())),
+  sampleTruncated: z.boolean(),
+  executionTimeMs: z.number(),
+  transactionState: transactionStateSchema,
+  notices: z.array(serverNoticeSchema),
+})
+
+export const explainOptionsSchema = z.object({
+  analyze: z.boolean().optional(),
+  buffers: z.boolean().optional(),
+  verbose: z.boolean().optional(),
+  settings: z.boolean().optional(),
+  wal: z.boolean().optional(),
+  jobId: z.string().optional(),
+  tabId: z.string().optional(),
+  parameters: z.array(queryParameterSchema).optional(),
+  timeouts: timeoutsSchema.optional(),
+})
+
+export const buffersSchema = z.object({
+  sharedHit: z.number(),
+  sharedRead: z.number(),
+  sharedDirtied: z.number(),
+  sharedWritten: z.number(),
+  localHit: z.number(),
+  localRead: z.number(),
+  localDirtied: z.number(),
+  localWritten: z.number(),
+  tempRead: z.number(),
+  tempWritten: z.number(),
+})
+
+export const walSchema = z.object({
+  records: z.number(),
+  fullPageImages: z.number(),
+  bytes: z.number(),
+})
+
+export const hotSpotSchema = z.discriminatedUnion('kind', [
+  z.object({
+    kind: z.literal('misestimate'),
+    estimated: z.number(),
+    actual: z.number(),
+    factor: z.number(),
+  }),
+  z.object({ kind: z.literal('largeSeqScan'), rows: z.number() }),
+  z.object({ kind: z.literal('slow'), share: z.number() }),
+  z.object({ kind: z.literal('spill') }),
+])
+
+export interface PlanNode {
+  nodeType: string
+  parentRelationship: string | null
+  relation: string | null
+  alias: string | null
+  indexName: string | null
+  joinType: string | null
+  condition: string | null
+  filter: string | null
+  startupCost: number
+  totalCost: number
+  estimatedRows: number
+  actualRows: number | null
+  loops: number | null
+  actualStartupTimeMs: number | null
+  actualTotalTimeMs: number | null
+  exclusiveTimeMs: number | null
+  rowsRemovedByFilter: number | null
+  buffers: z.infer<typeof buffersSchema> | null
+  wal: z.infer<typeof walSchema> | null
+  hotSpots: z.infer<typeof hotSpotSchema>[]
+  children: PlanNode[]
+}
+
+export const planNodeSchema: z.ZodType<PlanNode> = z.lazy(() =>
+  z.object({
+    nodeType: z.string(),
+    parentRelationship: z.string().nullable(),
+    relation: z.string().nullable(),
+    alias: z.string().nullable(),
+    indexName: z.string().nullable(),
+    joinType: z.string().nullable(),
+    condition: z.string().nullable(),
+    filter: z.string().nullable(),
+    startupCost: z.number(),
+    totalCost: z.number(),
+    estimatedRows: z.number(),
+    actualRows: z.number().nullable(),
+    loops: z.number().nullable(),
+    actualStartupTimeMs: z.number().nullable(),
+    actualTotalTimeMs: z.number().nullable(),
+    exclusiveTimeMs: z.number().nullable(),
+    rowsRemovedByFilter: z.number().nullable(),
+    buffers: buffersSchema.nullable(),
+    wal: walSchema.nullable(),
+    hotSpots: z.array(hotSpotSchema),
+    children: z.array(planNodeSchema),
+  })
+)
+
+export const explainResultSchema = z.object({
+  plan: planNodeSchema,
+  planningTimeMs: z.number().nullable(),
+  executionTimeMs: z.number().nullable(),
+  planningBuffers: buffersSchema.nullable(),
+  settings: z.record(z.string(), z.string()),
+  rolledBack: z.boolean(),
+  raw: z.unknown(),
+  transactionState: transactionStateSchema,
+})
+
+export const deltaSchema = z.object({
+  before: z.number().nullable(),
+  after: z.number().nullable(),
+  change: z.number().nullable(),
+})
+
+export const nodeDiffSchema = z.object({
+  depth: z.number(),
+  status: z.enum(['matched', 'typeChanged', 'added', 'removed']),
+  beforeNodeType: z.string().nullable(),
+  afterNodeType: z.string().nullable(),
+  relation: z.string().nullable(),
+  beforeIndexName: z.string().nullable(),
+  afterIndexName: z.string().nullable(),
+  totalCost: deltaSchema,
+  ownCost: deltaSchema,
+  estimatedRows: deltaSchema,
+  actualRows: deltaSchema,
+  timeMs: deltaSchema,
+  exclusiveTimeMs: deltaSchema,
+  sharedHit: deltaSchema,
+  sharedRead: deltaSchema,
+})
+
+export const planComparisonSchema = z.object({
+  nodes: z.array(nodeDiffSchema),
+  planningTimeMs: deltaSchema,
+  executionTimeMs: deltaSchema,
+  totalCost: deltaSchema,
+  verdict: z.enum(['faster', 'slower', 'unchanged']),
+  basis: z.enum(['executionTime', 'estimatedCost']),
+  speedup: z.number().nullable(),
+  mainChanges: z.array(z.number()),
+  summary: z.string(),
+})
+
+export const statementResultSchema = z.object({
+  statement: z.string(),
+  offset: z.number(),
+  statementKind: statementKindSchema,
+  columns: z.array(resultColumnSchema),
   rows: z.array(z.array(z.unknown())),
+  rowsAffected: z.number().nullable(),
   executionTimeMs: z.number(),
-  rowsAffected: z.number().optional(),
+  rowLimitReached: z.boolean(),
+  error: commandErrorSchema.nullable(),
+  notices: z.array(serverNoticeSchema),
+})
+
+export const scriptResultSchema = z.object({
+  statements: z.array(statementResultSchema),
+  skipped: z.number(),
+  executionTimeMs: z.number(),
+  cancelled: z.boolean(),
+  transactionState: transactionStateSchema,
+})
+
+export const editOptionsSchema = z.object({
+  displayZone: displayZoneSchema.optional(),
+  tabId: z.string().optional(),
+})
+
+export const cellChangeSchema = z.object({
+  columnIndex: z.number(),
+  value: z.string().nullable(),
+})
+
+export const rowChangeSchema = z.object({
+  rowIndex: z.number(),
+  original: z.array(z.unknown()),
+  cells: z.array(cellChangeSchema),
+})
+
+export const rowOperationSchema = z.discriminatedUnion('kind', [
+  rowChangeSchema.extend({ kind: z.literal('update') }),
+  z.object({
+    kind: z.literal('insert'),
+    rowIndex: z.number(),
+    cells: z.array(cellChangeSchema),
+  }),
+  z.object({
+    kind: z.literal('duplicate'),
+    rowIndex: z.number(),
+    original: z.array(z.unknown()),
+    cells: z.array(cellChangeSchema),
+  }),
+  z.object({
+    kind: z.literal('delete'),
+    rowIndex: z.number(),
+    original: z.array(z.unknown()),
+  }),
+])
+
+export const rowOutcomeSchema = z.object({
+  rowIndex: z.number(),
+  status: z.enum(['applied', 'conflict', 'failed']),
+  row: z.array(z.unknown()).nullable(),
+  error: commandErrorSchema.nullable(),
+})
+
+export const applyChangesResultSchema = z.object({
+  rows: z.array(rowOutcomeSchema),
+  applied: z.boolean(),
+  transactionState: transactionStateSchema,
 })
 
 export const connectionConfigSchema = z.object({
@@ -32,6 +401,8 @@ export const connectionConfigSchema = z.object({
   user: z.string(),
   password: z.string(),
   database: z.string(),
+  timeouts: timeoutsSchema.optional(),
+  safety: safetyLevelSchema.optional(),
 })
 
 export const connectionInfoSchema = z.object({
@@ -41,6 +412,7 @@ export const connectionInfoSchema = z.object({
   port: z.number(),
   database: z.string(),
   isConnected: z.boolean(),
+  safety: safetyLevelSchema,
 })
 
 export const savedConnectionSchema = z.object({
@@ -51,12 +423,46 @@ export const savedConnectionSchema = z.object({
   user: z.string(),
   password: z.string(),
   database: z.string(),
+  timeouts: timeoutsSchema.optional(),
+  safety: safetyLevelSchema.optional(),
 })
 
 export type ColumnSchema = z.infer<typeof columnSchemaSchema>
 export type TableSchema = z.infer<typeof tableSchemaSchema>
 export type DatabaseSchema = z.infer<typeof databaseSchemaSchema>
+export type StatementKind = z.infer<typeof statementKindSchema>
+export type ResultColumn = z.infer<typeof resultColumnSchema>
+export type TransactionState = z.infer<typeof transactionStateSchema>
 export type QueryResult = z.infer<typeof queryResultSchema>
+export type QueryPage = z.infer<typeof queryPageSchema>
+export type DisplayZone = z.infer<typeof displayZoneSchema>
+export type QueryParameter = z.infer<typeof queryParameterSchema>
+export type ParameterDescription = z.infer<typeof parameterDescriptionSchema>
+export type QueryDescription = z.infer<typeof queryDescriptionSchema>
+export type QueryOptions = z.infer<typeof queryOptionsSchema>
+export type QueryJobInfo = z.infer<typeof queryJobInfoSchema>
+export type ScriptOptions = z.infer<typeof scriptOptionsSchema>
+export type DryRunOptions = z.infer<typeof dryRunOptionsSchema>
+export type DryRunResult = z.infer<typeof dryRunResultSchema>
+export type ExplainOptions = z.infer<typeof explainOptionsSchema>
+export type Buffers = z.infer<typeof buffersSchema>
+export type HotSpot = z.infer<typeof hotSpotSchema>
+export type ExplainResult = z.infer<typeof explainResultSchema>
+export type Delta = z.infer<typeof deltaSchema>
+export type NodeDiff = z.infer<typeof nodeDiffSchema>
+export type PlanComparison = z.infer<typeof planComparisonSchema>
+export type SafetyLevel = z.infer<typeof safetyLevelSchema>
+export type Timeouts = z.infer<typeof timeoutsSchema>
+export type ServerNotice = z.infer<typeof serverNoticeSchema>
+export type CommandError = z.infer<typeof commandErrorSchema>
+export type StatementResult = z.infer<typeof statementResultSchema>
+export type ScriptResult = z.infer<typeof scriptResultSchema>
+export type EditOptions = z.infer<typeof editOptionsSchema>
+export type CellChange = z.infer<typeof cellChangeSchema>
+export type RowChange = z.infer<typeof rowChangeSchema>
+export type RowOperation = z.infer<typeof rowOperationSchema>
+export type RowOutcome = z.infer<typeof rowOutcomeSchema>
+export type ApplyChangesResult = z.infer<typeof applyChangesResultSchema>
 export type ConnectionConfig = z.infer<typeof connectionConfigSchema>
 export type ConnectionInfo = z.infer<typeof connectionInfoSchema>
 export type SavedConnection = z.infer<typeof savedConnectionSchema>
@@ -87,6 +493,108 @@ export const persistedEditorStateSchema = z.object({
 export type PersistedTab = z.infer<typeof persistedTabSchema>
 export type PersistedEditorState = z.infer<typeof persistedEditorStateSchema>
 
+export const historyStatusSchema = z.enum(['succeeded', 'failed', 'cancelled'])
+
+export const historyEntrySchema = z.object({
+  id: z.string(),
+  connectionId: z.string(),
+  tabId: z.string().nullable(),
+  sql: z.string(),
+  startedAt: z.string(),
+  durationMs: z.number(),
+  rowsReturned: z.number().nullable(),
+  rowsAffected: z.number().nullable(),
+  status: historyStatusSchema,
+  error: z.string().nullable(),
+})
+
+export const historyFilterSchema = z.object({
+  text: z.string().optional(),
+  connectionId: z.string().optional(),
+  /** RFC 3339 timestamps; `to` is exclusive. */
+  from: z.string().optional(),
+  to: z.string().optional(),
+  status: historyStatusSchema.optional(),
+  limit: z.number().optional(),
+})
+
+export type HistoryStatus = z.infer<typeof historyStatusSchema>
+export type HistoryEntry = z.infer<typeof historyEntrySchema>
+export type HistoryFilter = z.infer<typeof historyFilterSchema>
+
+/** A `NOTIFY` received on a listened channel; `receivedAt` is when the app got it. */
+export const notificationSchema = z.object({
+  channel: z.string(),
+  payload: z.string(),
+  pid: z.number(),
+  receivedAt: z.string(),
+})
+
+export type Notification = z.infer<typeof notificationSchema>
+
+/** Rows to export: a query to run, or the query behind an open result set, run again. */
+export const exportSourceSchema = z.discriminatedUnion('kind', [
+  z.object({
+    kind: z.literal('query'),
+    query: z.string(),
+    parameters: z.array(queryParameterSchema).optional(),
+    tabId: z.string().optional(),
+  }),
+  z.object({ kind: z.literal('result'), resultId: z.string() }),
+])
+
+export const exportFormatSchema = z.discriminatedUnion('format', [
+  z.object({
+    format: z.literal('csv'),
+    delimiter: z.string().optional(),
+    quote: z.string().optional(),
+    quoteStyle: z.enum(['necessary', 'always']).optional(),
+    /** Written unquoted for NULL; defaults to an empty field. */
+    nullMarker: z.string().optional(),
+    header: z.boolean().optional(),
+  }),
+  z.object({ format: z.literal('json') }),
+  z.object({ format: z.literal('ndjson') }),
+  z.object({
+    format: z.literal('insert'),
+    /** Used as written, so quote it as needed. */
+    table: z.string(),
+    rowsPerStatement: z.number().optional(),
+  }),
+  z.object({ format: z.literal('markdown') }),
+  z.object({ format: z.literal('xlsx') }),
+  z.object({ format: z.literal('parquet') }),
+])
+
+export const exportOptionsSchema = z.intersection(
+  exportFormatSchema,
+  z.object({
+    displayZone: displayZoneSchema.optional(),
+    jobId: z.string().optional(),
+    timeouts: timeoutsSchema.optional(),
+    confirmationToken: z.string().optional(),
+  })
+)
+
+export const exportProgressSchema = z.object({
+  jobId: z.string(),
+  rowsWritten: z.number(),
+  bytesWritten: z.number().nullable(),
+})
+
+export const exportSummarySchema = z.object({
+  path: z.string(),
+  rowsWritten: z.number(),
+  bytesWritten: z.number(),
+  executionTimeMs: z.number(),
+})
+
+export type ExportSource = z.infer<typeof exportSourceSchema>
+export type ExportFormat = z.infer<typeof exportFormatSchema>
+export type ExportOptions = z.infer<typeof exportOptionsSchema>
+export type ExportProgress = z.infer<typeof exportProgressSchema>
+export type ExportSummary = z.infer<typeof exportSummarySchema>
+
 export interface QueryError {
   message: string
   code?: string
@@ -0,0 +1,20 @@
+import { commandErrorSchema, type CommandError } from '../types/database'
+
+/**
+ * Normalizes anything thrown by `invoke` or the frontend into a CommandError.
+ * Commands reject with a structured error; other failures are wrapped.
+ */
+export function toCommandError(err: unknown): CommandError {
+  const parsed = commandErrorSchema.safeParse(err)
+  if (parsed.success) {
+    return parsed.data
+  }
+  return {
+    kind: 'failed',
+    message: err instanceof Error ? err.message : String(err),
+  }
+}
+
+export function errorMessage(err: unknown): string {
+  return toCommandError(err).message
+}